@@ -0,0 +1,251 @@
+use std::convert::TryFrom;
+
+use bytes::BufMut;
+use failure::Fail;
+
+use super::parser::{AMX_VERSION, FILE_VERSION, HEADER_SIZE, MAGIC};
+use super::tables::{NAMEOFS_SIZE, NAMETABLE_HEADER_SIZE};
+use super::UCell;
+
+// sNAMEMAX, names are never considered shorter than the compiler default
+const DEFAULT_MAX_NAME_LENGTH: usize = 31;
+
+#[derive(Debug, Fail)]
+pub enum BuildError {
+    #[fail(display = "Invalid cellsize, must be 4 or 8, got: {}", _0)]
+    InvalidCellSize(usize),
+    #[fail(display = "Name is too long: {}", _0)]
+    NameTooLong(String),
+    #[fail(display = "Image does not fit into 32 bit offsets")]
+    ImageTooLarge,
+}
+
+/// Name and address (or id) of the table record.
+type Record = (String, UCell);
+
+/// Assembles amx image from code, data and tables.
+///
+/// Records reference names stored in the nametable, tables keep the order
+/// records were added in. COD section starts at the cell aligned offset right
+/// after the nametable, DAT section follows it.
+#[derive(Debug)]
+pub struct Builder {
+    cellsize: usize,
+    cod: Vec<u8>,
+    dat: Vec<u8>,
+    stack_size: u32,
+    cip: Option<UCell>,
+    publics: Vec<Record>,
+    natives: Vec<Record>,
+    libraries: Vec<Record>,
+    pubvars: Vec<Record>,
+    tags: Vec<Record>,
+}
+
+impl Builder {
+    pub fn new(cellsize: usize) -> Self {
+        Builder {
+            cellsize,
+            cod: vec![],
+            dat: vec![],
+            stack_size: 0,
+            cip: None,
+            publics: vec![],
+            natives: vec![],
+            libraries: vec![],
+            pubvars: vec![],
+            tags: vec![],
+        }
+    }
+
+    pub fn code(&mut self, cod: &[u8]) -> &mut Self {
+        self.cod = cod.to_owned();
+        self
+    }
+
+    pub fn data(&mut self, dat: &[u8]) -> &mut Self {
+        self.dat = dat.to_owned();
+        self
+    }
+
+    /// Memory for stack and heap, taken right after DAT section.
+    pub fn stack_size(&mut self, size: u32) -> &mut Self {
+        self.stack_size = size;
+        self
+    }
+
+    /// COD relative address of `main`.
+    pub fn main(&mut self, address: UCell) -> &mut Self {
+        self.cip = Some(address);
+        self
+    }
+
+    /// Public function at COD relative address.
+    pub fn public(&mut self, name: &str, address: UCell) -> &mut Self {
+        self.publics.push((name.to_owned(), address));
+        self
+    }
+
+    pub fn native(&mut self, name: &str) -> &mut Self {
+        self.natives.push((name.to_owned(), 0));
+        self
+    }
+
+    pub fn library(&mut self, name: &str) -> &mut Self {
+        self.libraries.push((name.to_owned(), 0));
+        self
+    }
+
+    /// Public variable at DAT relative address.
+    pub fn pubvar(&mut self, name: &str, address: UCell) -> &mut Self {
+        self.pubvars.push((name.to_owned(), address));
+        self
+    }
+
+    pub fn tag(&mut self, name: &str, id: UCell) -> &mut Self {
+        self.tags.push((name.to_owned(), id));
+        self
+    }
+
+    pub fn build(&self) -> Result<Vec<u8>, BuildError> {
+        let cellsize = self.cellsize;
+        if !(cellsize == 4 || cellsize == 8) {
+            return Err(BuildError::InvalidCellSize(cellsize));
+        }
+
+        let defsize = cellsize * 2;
+        let tables = [
+            &self.publics,
+            &self.natives,
+            &self.libraries,
+            &self.pubvars,
+            &self.tags,
+        ];
+        let records: Vec<&Record> = tables.iter().flat_map(|t| t.iter()).collect();
+
+        let mut max_name_length = DEFAULT_MAX_NAME_LENGTH;
+        for (name, _) in records.iter() {
+            if name.len() > usize::from(u16::MAX) {
+                return Err(BuildError::NameTooLong(name.clone()));
+            }
+            max_name_length = max_name_length.max(name.len());
+        }
+
+        // Offsets of the tables, the last one is the nametable
+        let mut offsets = vec![HEADER_SIZE];
+        for table in tables.iter() {
+            offsets.push(offsets[offsets.len() - 1] + table.len() * defsize);
+        }
+        let nametable = offsets[offsets.len() - 1];
+        let names_size: usize = records.iter().map(|(name, _)| name.len() + 1).sum();
+        let names_end = nametable + NAMETABLE_HEADER_SIZE + names_size;
+        let cod = names_end.div_ceil(cellsize) * cellsize;
+        let dat = cod + self.cod.len();
+        let hea = dat + self.dat.len();
+        let stp = hea + self.stack_size as usize;
+        to_u32(stp)?;
+
+        let mut bin = Vec::with_capacity(hea);
+        bin.put_u32_le(to_u32(hea)?);
+        bin.put_u16_le(MAGIC);
+        bin.put_u8(FILE_VERSION);
+        bin.put_u8(AMX_VERSION);
+        bin.put_u16_le(0);
+        bin.put_u16_le(defsize as u16);
+        bin.put_u32_le(to_u32(cod)?);
+        bin.put_u32_le(to_u32(dat)?);
+        bin.put_u32_le(to_u32(hea)?);
+        bin.put_u32_le(to_u32(stp)?);
+        match self.cip {
+            Some(cip) => bin.put_u32_le(u32::try_from(cip).map_err(|_| BuildError::ImageTooLarge)?),
+            None => bin.put_u32_le(0xFFFF_FFFF),
+        }
+        for &offset in offsets.iter() {
+            bin.put_u32_le(to_u32(offset)?);
+        }
+
+        let mut name_offset = nametable + NAMETABLE_HEADER_SIZE;
+        for (name, value) in records.iter() {
+            bin.put_uint_le(*value, cellsize);
+            bin.put_u32_le(to_u32(name_offset)?);
+            bin.put_slice(&vec![0; cellsize - NAMEOFS_SIZE]);
+            name_offset += name.len() + 1;
+        }
+
+        bin.put_u16_le(max_name_length as u16);
+        for (name, _) in records.iter() {
+            bin.put_slice(name.as_bytes());
+            bin.put_u8(0);
+        }
+        bin.put_slice(&vec![0; cod - names_end]);
+
+        bin.put_slice(&self.cod);
+        bin.put_slice(&self.dat);
+
+        Ok(bin)
+    }
+}
+
+fn to_u32(value: usize) -> Result<u32, BuildError> {
+    u32::try_from(value).map_err(|_| BuildError::ImageTooLarge)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{BuildError, Builder};
+    use crate::amx::opcode_type::OpcodeType;
+    use crate::amx::tables::{Native, Public, Tag};
+    use crate::amx::File as AmxFile;
+
+    #[test]
+    fn it_builds_image_readable_by_parser() {
+        for &cellsize in [4, 8].iter() {
+            let mut cod = vec![0; 2 * cellsize];
+            cod[cellsize] = OpcodeType::OpProc as u8;
+
+            let bin = Builder::new(cellsize)
+                .code(&cod)
+                .data(&vec![1; cellsize])
+                .stack_size(1024)
+                .public("plugin_init", cellsize as u64)
+                .native("register_plugin")
+                .tag("Float", 0x4000_0005)
+                .build()
+                .expect("Image should be built");
+
+            let file = AmxFile::try_from(&bin[..]).expect("Built image should be parsed");
+            let header = file.header();
+            assert_eq!(file.cellsize(), cellsize);
+            assert_eq!(header.cod as usize % cellsize, 0);
+            assert_eq!(header.stp, header.hea + 1024);
+            assert_eq!(header.cip, 0xFFFF_FFFF);
+            assert_eq!(file.cod_slice().unwrap(), &cod[..]);
+            assert_eq!(file.dat_slice().unwrap(), &vec![1; cellsize][..]);
+            assert_eq!(file.opcodes().unwrap().count(), 2);
+
+            let publics: Vec<Public> = file.publics().unwrap().map(Result::unwrap).collect();
+            assert_eq!(
+                publics,
+                [Public {
+                    name: "plugin_init".to_owned(),
+                    address: cellsize as u64,
+                }]
+            );
+            let natives: Vec<Native> = file.natives().unwrap().map(Result::unwrap).collect();
+            assert_eq!(natives[0].name, "register_plugin");
+            let tags: Vec<Tag> = file.tags().unwrap().map(Result::unwrap).collect();
+            assert_eq!(tags[0].id, 0x4000_0005);
+            assert_eq!(file.nametable().unwrap().max_name_length(), 31);
+        }
+    }
+
+    #[test]
+    fn it_fails_with_invalid_cellsize() {
+        match Builder::new(2).build() {
+            Err(BuildError::InvalidCellSize(2)) => (),
+            _ => panic!("Error should be BuildError::InvalidCellSize"),
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Pawn compact encoding of COD and DAT sections.
+//!
+//! Every cell is stored as a big endian sequence of 7 bit groups, all bytes
+//! but the last one have high bit set. Bit 0x40 of the first byte is the sign.
+
+use std::convert::TryFrom;
+
+use byteorder::{ByteOrder, LittleEndian};
+use failure::Fail;
+
+use super::tables::defsize_cellsize;
+use super::{Flags, UCell};
+
+const SIZE_OFFSET: usize = 0;
+const FLAGS_OFFSET: usize = 8;
+const DEFSIZE_OFFSET: usize = 10;
+const COD_OFFSET: usize = 12;
+const HEA_OFFSET: usize = 20;
+const HEADER_SIZE: usize = 56;
+
+const CONTINUATION_BIT: u8 = 0x80;
+const SIGN_BIT: u8 = 0x40;
+const GROUP_MASK: u8 = 0x7f;
+
+#[derive(Debug, Fail)]
+pub enum CompactError {
+    #[fail(display = "Header is corrupted")]
+    HeaderEOF,
+    #[fail(display = "Unsupported table record size (defsize): {}", _0)]
+    UnexpectedDefsize(u16),
+    #[fail(display = "Cod and dat sections got invalid bounds")]
+    SectionsOutOfBounds,
+    #[fail(display = "Compact encoded data ends in the middle of a cell")]
+    UnterminatedCell,
+    #[fail(display = "Expanded size mismatch, expected: {}, got: {}", _0, _1)]
+    ExpandedSizeMismatch(usize, usize),
+    #[fail(display = "Compact encoded image does not fit into 32 bit size")]
+    ImageTooLarge,
+}
+
+fn cell_mask(cellsize: usize) -> UCell {
+    UCell::MAX >> (64 - cellsize * 8)
+}
+
+/// Decodes compact encoded cells into plain little endian cells.
+pub fn expand(compact_bin: &[u8], cellsize: usize) -> Result<Vec<u8>, CompactError> {
+    let mut expanded = vec![];
+    let mut cell: Option<UCell> = None;
+
+    for &byte in compact_bin.iter() {
+        let value = cell.unwrap_or(if byte & SIGN_BIT != 0 { UCell::MAX } else { 0 });
+        let value = (value << 7) | UCell::from(byte & GROUP_MASK);
+
+        if byte & CONTINUATION_BIT != 0 {
+            cell = Some(value);
+            continue;
+        }
+
+        let mut cell_bin = [0; 8];
+        LittleEndian::write_uint(&mut cell_bin, value & cell_mask(cellsize), cellsize);
+        expanded.extend_from_slice(&cell_bin[..cellsize]);
+        cell = None;
+    }
+
+    if cell.is_some() {
+        return Err(CompactError::UnterminatedCell);
+    }
+
+    Ok(expanded)
+}
+
+/// Encodes plain little endian cells, as Pawn compiler does with compact encoding enabled.
+pub fn compact(cells_bin: &[u8], cellsize: usize) -> Vec<u8> {
+    // Bytes needed to hold whole cell and bits left in the highest group
+    let max_groups = (cellsize * 8).div_ceil(7);
+    let high_group_mask = (1u8 << (cellsize * 8 - (max_groups - 1) * 7)) - 1;
+
+    let mut compacted = vec![];
+    for cell_bin in cells_bin.chunks(cellsize) {
+        let mut value = LittleEndian::read_uint(cell_bin, cell_bin.len());
+        let mut groups = Vec::with_capacity(max_groups);
+        for _ in 0..max_groups {
+            groups.push((value as u8) & GROUP_MASK);
+            value >>= 7;
+        }
+
+        let mut length = max_groups;
+        // Skip leading zeros
+        while length > 1 && groups[length - 1] == 0 && groups[length - 2] & SIGN_BIT == 0 {
+            length -= 1;
+        }
+        // Skip leading -1s
+        if length == max_groups
+            && groups[length - 1] == high_group_mask
+            && groups[length - 2] & SIGN_BIT != 0
+        {
+            length -= 1;
+        }
+        while length > 1 && groups[length - 1] == GROUP_MASK && groups[length - 2] & SIGN_BIT != 0 {
+            length -= 1;
+        }
+
+        for index in (0..length).rev() {
+            let continuation = if index == 0 { 0 } else { CONTINUATION_BIT };
+            compacted.push(groups[index] | continuation);
+        }
+    }
+
+    compacted
+}
+
+struct ImageLayout {
+    size: usize,
+    flags: Flags,
+    cellsize: usize,
+    cod: usize,
+    hea: usize,
+}
+
+fn read_layout(bin: &[u8]) -> Result<ImageLayout, CompactError> {
+    let header = bin.get(..HEADER_SIZE).ok_or(CompactError::HeaderEOF)?;
+    let field = |offset: usize| LittleEndian::read_u32(&header[offset..]) as usize;
+
+    let defsize = LittleEndian::read_u16(&header[DEFSIZE_OFFSET..]);
+    let cellsize = defsize_cellsize(defsize).ok_or(CompactError::UnexpectedDefsize(defsize))?;
+
+    Ok(ImageLayout {
+        size: field(SIZE_OFFSET),
+        flags: Flags::from_bits_truncate(LittleEndian::read_u16(&header[FLAGS_OFFSET..])),
+        cellsize,
+        cod: field(COD_OFFSET),
+        hea: field(HEA_OFFSET),
+    })
+}
+
+fn write_header(image: &mut [u8], size: usize, flags: Flags) -> Result<(), CompactError> {
+    let size = u32::try_from(size).map_err(|_| CompactError::ImageTooLarge)?;
+    LittleEndian::write_u32(&mut image[SIZE_OFFSET..], size);
+    LittleEndian::write_u16(&mut image[FLAGS_OFFSET..], flags.bits());
+
+    Ok(())
+}
+
+/// Expands compact encoded amx image to the plain cell layout, clearing its compact flag.
+///
+/// Data following the image (debug information) is kept as is.
+pub fn expand_image(bin: &[u8]) -> Result<Vec<u8>, CompactError> {
+    let layout = read_layout(bin)?;
+    let compact_bin = bin
+        .get(layout.cod..layout.size)
+        .ok_or(CompactError::SectionsOutOfBounds)?;
+
+    let expanded_size = layout
+        .hea
+        .checked_sub(layout.cod)
+        .ok_or(CompactError::SectionsOutOfBounds)?;
+    let expanded = expand(compact_bin, layout.cellsize)?;
+    if expanded.len() != expanded_size {
+        return Err(CompactError::ExpandedSizeMismatch(
+            expanded_size,
+            expanded.len(),
+        ));
+    }
+
+    let mut image = bin[..layout.cod].to_owned();
+    image.extend_from_slice(&expanded);
+    image.extend_from_slice(&bin[layout.size..]);
+    write_header(&mut image, layout.hea, layout.flags - Flags::COMPACT)?;
+
+    Ok(image)
+}
+
+/// Compact encodes COD and DAT sections of plain amx image, setting its compact flag.
+pub fn compact_image(bin: &[u8]) -> Result<Vec<u8>, CompactError> {
+    let layout = read_layout(bin)?;
+    let cells_bin = bin
+        .get(layout.cod..layout.hea)
+        .ok_or(CompactError::SectionsOutOfBounds)?;
+    let trailing_bin = bin
+        .get(layout.size.max(layout.hea)..)
+        .ok_or(CompactError::SectionsOutOfBounds)?;
+
+    let mut image = bin[..layout.cod].to_owned();
+    image.extend(compact(cells_bin, layout.cellsize));
+    let size = image.len();
+    image.extend_from_slice(trailing_bin);
+    write_header(&mut image, size, layout.flags | Flags::COMPACT)?;
+
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{self, Read};
+
+    use super::{compact, compact_image, expand, expand_image, CompactError};
+
+    fn _read_file(path: &str) -> io::Result<Vec<u8>> {
+        let mut file = File::open(path)?;
+        let mut plugin = vec![];
+        file.read_to_end(&mut plugin)?;
+
+        Ok(plugin)
+    }
+
+    fn read_file(path: &str) -> Vec<u8> {
+        _read_file(path).expect(&format!("Could not read {} file", path))
+    }
+
+    fn cells(values: &[i64], cellsize: usize) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|v| v.to_le_bytes()[..cellsize].to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn it_compacts_cells() {
+        assert_eq!(compact(&cells(&[0, 1, -1], 4), 4), [0x00, 0x01, 0x7f]);
+        assert_eq!(compact(&cells(&[0x40], 4), 4), [0x80, 0x40]);
+        assert_eq!(compact(&cells(&[-0x41], 4), 4), [0xff, 0x3f]);
+        assert_eq!(
+            compact(&cells(&[0x7fff_ffff], 4), 4),
+            [0x87, 0xff, 0xff, 0xff, 0x7f]
+        );
+        assert_eq!(compact(&cells(&[-1, 0x40], 8), 8), [0x7f, 0x80, 0x40]);
+    }
+
+    #[test]
+    fn it_expands_cells() {
+        let values = [0, 1, -1, 0x40, -0x41, 0x7fff_ffff, -0x8000_0000, 123_456];
+        for &cellsize in [4, 8].iter() {
+            let bin = cells(&values, cellsize);
+            assert_eq!(expand(&compact(&bin, cellsize), cellsize).unwrap(), bin);
+        }
+
+        let bin = cells(&[i64::MAX, i64::MIN], 8);
+        assert_eq!(expand(&compact(&bin, 8), 8).unwrap(), bin);
+    }
+
+    #[test]
+    fn it_fails_on_unterminated_cell() {
+        match expand(&[0x01, 0x81], 4).err() {
+            Some(CompactError::UnterminatedCell) => (),
+            _ => panic!("Error should be CompactError::UnterminatedCell"),
+        }
+    }
+
+    #[test]
+    fn it_compacts_and_expands_image() {
+        for path in [
+            "test/fixtures/amxx/simple.cellsize4.amx183",
+            "test/fixtures/amxx/simple.cellsize8.amx181",
+        ]
+        .iter()
+        {
+            let image = read_file(path);
+            let compacted = compact_image(&image).unwrap();
+
+            assert!(compacted.len() < image.len());
+            // Compact flag is set
+            assert_eq!(compacted[8] & 0x04, 0x04);
+            assert_eq!(expand_image(&compacted).unwrap(), image);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::UCell;
+
+/// Value passed as a constant argument, e.g. pushed with `push.c`.
+///
+/// Compiler does not keep types of constants, so addresses pointing into
+/// DAT section are assumed to be strings and everything else plain cells.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Cell(UCell),
+    String(String),
+}
+
+/// Reads constant from DAT section, strings are unpacked: one character per cell.
+pub(crate) fn read_constant(dat_bin: &[u8], address: UCell, cellsize: usize) -> Constant {
+    if address >= dat_bin.len() as UCell {
+        return Constant::Cell(address);
+    }
+
+    let string: Vec<u8> = dat_bin[(address as usize)..]
+        .chunks(cellsize)
+        .map(|c| LittleEndian::read_uint(c, c.len()) as u8)
+        .take_while(|&c| c != 0)
+        .collect();
+
+    Constant::String(String::from_utf8_lossy(&string).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_constant, Constant};
+
+    const DAT_BIN: &[u8] = b"h\0\0\0i\0\0\0\0\0\0\0\x05\0\0\0";
+
+    #[test]
+    fn it_reads_unpacked_string() {
+        assert_eq!(
+            read_constant(DAT_BIN, 0, 4),
+            Constant::String("hi".to_owned())
+        );
+        assert_eq!(
+            read_constant(DAT_BIN, 4, 4),
+            Constant::String("i".to_owned())
+        );
+    }
+
+    #[test]
+    fn it_reads_cell_outside_of_dat() {
+        assert_eq!(read_constant(DAT_BIN, 16, 4), Constant::Cell(16));
+        assert_eq!(read_constant(DAT_BIN, 100_000, 4), Constant::Cell(100_000));
+    }
+}
@@ -0,0 +1,410 @@
+use std::io::Cursor;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use bytes::Buf;
+use failure::Fail;
+
+use super::UCell;
+
+const MAGIC: u16 = 0xF1EF;
+const HEADER_SIZE: usize = 22;
+
+#[derive(Debug, Fail)]
+pub enum DebugParseError {
+    #[fail(display = "File has no debug information")]
+    DebugInfoMissing,
+    #[fail(display = "Debug information header is corrupted")]
+    HeaderEOF,
+    #[fail(
+        display = "Debug information magic mismatch, expected: 0x{:X}, got: 0x{:X}",
+        _0, _1
+    )]
+    MagicMismatch(u16, u16),
+    #[fail(display = "Unexpected end of debug information in {} table", _0)]
+    TableEOF(&'static str),
+    #[fail(display = "Invalid name in {} table", _0)]
+    InvalidName(&'static str),
+    #[fail(display = "Unknown debug symbol kind: {}", _0)]
+    UnknownSymbolKind(u8),
+    #[fail(display = "Unknown debug symbol class: {}", _0)]
+    UnknownSymbolClass(u8),
+}
+
+/// Source file, generated code for it starts at address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugFile {
+    pub address: UCell,
+    pub name: String,
+}
+
+/// Source line, generated code for it starts at address.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugLine {
+    pub address: UCell,
+    pub line: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Variable,
+    Reference,
+    Array,
+    RefArray,
+    Function,
+}
+
+impl SymbolKind {
+    fn from_ident(ident: u8) -> Option<Self> {
+        match ident {
+            1 => Some(SymbolKind::Variable),
+            2 => Some(SymbolKind::Reference),
+            3 => Some(SymbolKind::Array),
+            4 => Some(SymbolKind::RefArray),
+            9 => Some(SymbolKind::Function),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolClass {
+    Global,
+    Local,
+    Static,
+}
+
+impl SymbolClass {
+    fn from_vclass(vclass: u8) -> Option<Self> {
+        match vclass {
+            0 => Some(SymbolClass::Global),
+            1 => Some(SymbolClass::Local),
+            2 => Some(SymbolClass::Static),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SymbolDimension {
+    pub tag: i16,
+    pub size: UCell,
+}
+
+/// Function or variable, visible in code between `codestart` and `codeend`.
+///
+/// Address of locals is relative to the frame, globals are relative to DAT
+/// and functions to COD.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub address: UCell,
+    pub tag: i16,
+    pub codestart: UCell,
+    pub codeend: UCell,
+    pub kind: SymbolKind,
+    pub class: SymbolClass,
+    pub dimensions: Vec<SymbolDimension>,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugTag {
+    pub id: i16,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Automaton {
+    pub id: i16,
+    pub address: UCell,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct State {
+    pub id: i16,
+    pub automaton: i16,
+    pub name: String,
+}
+
+/// Symbolic information stored after amx image of plugins compiled with debug info.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugInfo {
+    pub files: Vec<DebugFile>,
+    pub lines: Vec<DebugLine>,
+    pub symbols: Vec<Symbol>,
+    pub tags: Vec<DebugTag>,
+    pub automatons: Vec<Automaton>,
+    pub states: Vec<State>,
+}
+
+impl DebugInfo {
+    /// Function which code starts at the address.
+    pub fn function(&self, address: UCell) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .find(|s| s.kind == SymbolKind::Function && s.codestart == address)
+    }
+
+    /// Source file which contains code at the address.
+    pub fn file(&self, address: UCell) -> Option<&DebugFile> {
+        self.files.iter().rev().find(|f| f.address <= address)
+    }
+
+    /// Source line which contains code at the address.
+    pub fn line(&self, address: UCell) -> Option<i32> {
+        self.lines
+            .iter()
+            .rev()
+            .find(|l| l.address <= address)
+            .map(|l| l.line)
+    }
+
+    /// Local variables and arguments in scope at the address.
+    pub fn locals(&self, address: UCell) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter().filter(move |s| {
+            s.class == SymbolClass::Local
+                && s.kind != SymbolKind::Function
+                && s.codestart <= address
+                && address < s.codeend
+        })
+    }
+
+    pub fn tag_name(&self, id: i16) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|t| t.id == id)
+            .map(|t| t.name.as_str())
+    }
+}
+
+struct DebugReader<'debug_bin> {
+    reader: Cursor<&'debug_bin [u8]>,
+    cellsize: usize,
+    table: &'static str,
+}
+
+impl<'debug_bin> DebugReader<'debug_bin> {
+    fn eof(&self) -> DebugParseError {
+        DebugParseError::TableEOF(self.table)
+    }
+
+    fn cell(&mut self) -> Result<UCell, DebugParseError> {
+        let cellsize = self.cellsize;
+        self.reader
+            .read_uint::<LittleEndian>(cellsize)
+            .map_err(|_| self.eof())
+    }
+
+    fn i16(&mut self) -> Result<i16, DebugParseError> {
+        self.reader
+            .read_i16::<LittleEndian>()
+            .map_err(|_| self.eof())
+    }
+
+    fn i32(&mut self) -> Result<i32, DebugParseError> {
+        self.reader
+            .read_i32::<LittleEndian>()
+            .map_err(|_| self.eof())
+    }
+
+    fn u8(&mut self) -> Result<u8, DebugParseError> {
+        self.reader.read_u8().map_err(|_| self.eof())
+    }
+
+    fn name(&mut self) -> Result<String, DebugParseError> {
+        let mut name = vec![];
+        loop {
+            match self.u8()? {
+                0 => break,
+                c => name.push(c),
+            }
+        }
+
+        String::from_utf8(name).map_err(|_| DebugParseError::InvalidName(self.table))
+    }
+
+    fn table<T, F>(
+        &mut self,
+        table: &'static str,
+        count: i16,
+        read: F,
+    ) -> Result<Vec<T>, DebugParseError>
+    where
+        F: Fn(&mut Self) -> Result<T, DebugParseError>,
+    {
+        self.table = table;
+        (0..count.max(0)).map(|_| read(self)).collect()
+    }
+}
+
+impl DebugInfo {
+    /// Parses debug information block, `bin` must start with its header.
+    pub fn parse(bin: &[u8], cellsize: usize) -> Result<DebugInfo, DebugParseError> {
+        let header_bin = bin.get(0..HEADER_SIZE).ok_or(DebugParseError::HeaderEOF)?;
+
+        let mut header_reader = Cursor::new(header_bin);
+
+        let _size = header_reader.get_u32_le();
+        let magic = header_reader.get_u16_le();
+        let _file_version = header_reader.get_u8();
+        let _amx_version = header_reader.get_u8();
+        let _flags = header_reader.get_u16_le();
+        let files = header_reader.get_i16_le();
+        let lines = header_reader.get_i16_le();
+        let symbols = header_reader.get_i16_le();
+        let tags = header_reader.get_i16_le();
+        let automatons = header_reader.get_i16_le();
+        let states = header_reader.get_i16_le();
+
+        if magic != MAGIC {
+            return Err(DebugParseError::MagicMismatch(MAGIC, magic));
+        }
+
+        let mut reader = DebugReader {
+            reader: Cursor::new(&bin[HEADER_SIZE..]),
+            cellsize,
+            table: "file",
+        };
+
+        let files = reader.table("file", files, |r| {
+            Ok(DebugFile {
+                address: r.cell()?,
+                name: r.name()?,
+            })
+        })?;
+
+        let lines = reader.table("line", lines, |r| {
+            Ok(DebugLine {
+                address: r.cell()?,
+                line: r.i32()?,
+            })
+        })?;
+
+        let symbols = reader.table("symbol", symbols, |r| {
+            let address = r.cell()?;
+            let tag = r.i16()?;
+            let codestart = r.cell()?;
+            let codeend = r.cell()?;
+            let ident = r.u8()?;
+            let kind =
+                SymbolKind::from_ident(ident).ok_or(DebugParseError::UnknownSymbolKind(ident))?;
+            let vclass = r.u8()?;
+            let class = SymbolClass::from_vclass(vclass)
+                .ok_or(DebugParseError::UnknownSymbolClass(vclass))?;
+            let dimensions_count = r.i16()?;
+            let name = r.name()?;
+            let dimensions = (0..dimensions_count.max(0))
+                .map(|_| {
+                    Ok(SymbolDimension {
+                        tag: r.i16()?,
+                        size: r.cell()?,
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+
+            Ok(Symbol {
+                address,
+                tag,
+                codestart,
+                codeend,
+                kind,
+                class,
+                dimensions,
+                name,
+            })
+        })?;
+
+        let tags = reader.table("tag", tags, |r| {
+            Ok(DebugTag {
+                id: r.i16()?,
+                name: r.name()?,
+            })
+        })?;
+
+        let automatons = reader.table("automaton", automatons, |r| {
+            Ok(Automaton {
+                id: r.i16()?,
+                address: r.cell()?,
+                name: r.name()?,
+            })
+        })?;
+
+        let states = reader.table("state", states, |r| {
+            Ok(State {
+                id: r.i16()?,
+                automaton: r.i16()?,
+                name: r.name()?,
+            })
+        })?;
+
+        Ok(DebugInfo {
+            files,
+            lines,
+            symbols,
+            tags,
+            automatons,
+            states,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DebugInfo, DebugLine, DebugParseError, SymbolClass, SymbolKind};
+
+    // Header: 1 file, 1 line, 2 symbols (one with dimension), 1 tag, no automatons and states
+    const DEBUG_BIN: &[u8] = b"\0\0\0\0\xef\xf1\x08\x08\0\0\
+        \x01\0\x01\0\x02\0\x01\0\0\0\0\0\
+        \x08\0\0\0a.sma\0\
+        \x0c\0\0\0\x02\0\0\0\
+        \x08\0\0\0\0\0\x08\0\0\0\x40\0\0\0\x09\0\0\0func\0\
+        \xf8\xff\xff\xff\x01\0\x0c\0\0\0\x40\0\0\0\x03\x01\x01\0arr\0\x01\0\x21\0\0\0\
+        \x01\0bool\0";
+
+    #[test]
+    fn it_parses_debug_info() {
+        let debug_info = DebugInfo::parse(DEBUG_BIN, 4).expect("Debug info should be parsed");
+
+        assert_eq!(debug_info.files[0].name, "a.sma");
+        assert_eq!(
+            debug_info.lines,
+            [DebugLine {
+                address: 12,
+                line: 2,
+            }]
+        );
+
+        let function = debug_info.function(8).expect("Function should be found");
+        assert_eq!(function.name, "func");
+        assert_eq!(function.kind, SymbolKind::Function);
+        assert_eq!(function.class, SymbolClass::Global);
+
+        let locals: Vec<_> = debug_info.locals(0x10).collect();
+        assert_eq!(locals.len(), 1);
+        assert_eq!(locals[0].name, "arr");
+        assert_eq!(locals[0].address as u32 as i32, -8);
+        assert_eq!(locals[0].kind, SymbolKind::Array);
+        assert_eq!(locals[0].dimensions[0].size, 33);
+
+        assert_eq!(debug_info.line(0x10), Some(2));
+        assert_eq!(debug_info.line(0x4), None);
+        assert_eq!(debug_info.file(0x10).unwrap().name, "a.sma");
+        assert_eq!(debug_info.tag_name(1), Some("bool"));
+    }
+
+    #[test]
+    fn it_fails_with_magic_mismatch() {
+        match DebugInfo::parse(&[0; 22], 4).err() {
+            Some(DebugParseError::MagicMismatch(0xF1EF, 0)) => (),
+            _ => panic!("Error should be DebugParseError::MagicMismatch"),
+        }
+    }
+
+    #[test]
+    fn it_fails_on_truncated_table() {
+        match DebugInfo::parse(&DEBUG_BIN[..36], 4).err() {
+            Some(DebugParseError::TableEOF("line")) => (),
+            e => panic!("Error should be DebugParseError::TableEOF, got: {:?}", e),
+        }
+    }
+}
@@ -1,12 +1,24 @@
+pub mod builder;
+pub mod compact;
+pub mod constant;
+pub mod debug;
 pub mod opcode;
 pub mod opcode_type;
 pub mod opcodes_iterator;
 pub mod parser;
+pub mod tables;
 
+use constant::{read_constant, Constant};
+use debug::{DebugInfo, DebugParseError};
 use failure::Fail;
 use opcodes_iterator::OpcodesIterator;
+use tables::{
+    defsize_cellsize, uses_nametable, Library, NameTable, Native, Public, Pubvar, TableError,
+    TableIterator, TableRecord, Tag,
+};
 
-pub type UCell = u32;
+// Wide enough to hold cell of any supported cellsize
+pub type UCell = u64;
 
 bitflags! {
     pub struct Flags: u16 {
@@ -22,15 +34,60 @@ bitflags! {
     }
 }
 
+/// Serialized as the list of set flag names, e.g. `["debug", "compact"]`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Flags {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let names = [
+            (Flags::DEBUG, "debug"),
+            (Flags::COMPACT, "compact"),
+            (Flags::BYTEOPC, "byteopc"),
+            (Flags::NOCHECKS, "nochecks"),
+            (Flags::NTVREG, "ntvreg"),
+            (Flags::JITC, "jitc"),
+            (Flags::BROWSE, "browse"),
+            (Flags::RELOC, "reloc"),
+        ];
+        serializer.collect_seq(
+            names
+                .iter()
+                .filter(|(flag, _)| self.contains(*flag))
+                .map(|(_, name)| name),
+        )
+    }
+}
+
 #[derive(Debug, Fail)]
 pub enum ParseError {
     #[fail(display = "Cod section got invalid offset")]
     CodSectionMismatch,
+    #[fail(display = "Dat section got invalid offset")]
+    DatSectionMismatch,
+}
+
+/// Layout of the image stored in its header, addresses are relative to the image start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Header {
+    /// Size of the image without debug info.
+    pub size: u32,
+    pub flags: Flags,
+    /// Size of table records.
+    pub defsize: u16,
+    pub cod: u32,
+    pub dat: u32,
+    /// Initial heap top, end of DAT section.
+    pub hea: u32,
+    /// Stack top, end of the memory plugin takes.
+    pub stp: u32,
+    /// Entry point of `main`, `-1` when plugin has none.
+    pub cip: u32,
 }
 
 #[derive(Debug, PartialEq)]
 pub struct File {
     bin: Vec<u8>,
+    size: u32,
     flags: Flags,
     defsize: u16,
     cod: u32,
@@ -55,14 +112,107 @@ impl File {
             .ok_or_else(|| ParseError::CodSectionMismatch)
     }
 
-    pub fn opcodes(&self) -> Result<OpcodesIterator, ParseError> {
-        Ok(OpcodesIterator::new(self.cod_slice()?))
+    pub fn dat_slice(&self) -> Result<&[u8], ParseError> {
+        self.bin
+            .get((self.dat as usize)..(self.hea as usize))
+            .ok_or(ParseError::DatSectionMismatch)
+    }
+
+    pub fn opcodes(&self) -> Result<OpcodesIterator<'_>, ParseError> {
+        Ok(OpcodesIterator::new(self.cod_slice()?, self.cellsize()))
+    }
+
+    /// Constant at the DAT relative address, see `Constant` on how its type is guessed.
+    pub fn read_constant(&self, address: UCell) -> Result<Constant, ParseError> {
+        Ok(read_constant(self.dat_slice()?, address, self.cellsize()))
+    }
+
+    /// Size of a cell in bytes, 4 for 32 bit and 8 for 64 bit images.
+    pub fn cellsize(&self) -> usize {
+        defsize_cellsize(self.defsize).expect("defsize is validated on parsing")
+    }
+
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    pub fn header(&self) -> Header {
+        Header {
+            size: self.size,
+            flags: self.flags,
+            defsize: self.defsize,
+            cod: self.cod,
+            dat: self.dat,
+            hea: self.hea,
+            stp: self.stp,
+            cip: self.cip,
+        }
+    }
+
+    /// Symbolic information, available when plugin is compiled with debug info.
+    pub fn debug_info(&self) -> Result<DebugInfo, DebugParseError> {
+        if !self.flags.contains(Flags::DEBUG) {
+            return Err(DebugParseError::DebugInfoMissing);
+        }
+
+        let debug_bin = self
+            .bin
+            .get((self.size as usize)..)
+            .ok_or(DebugParseError::HeaderEOF)?;
+
+        DebugInfo::parse(debug_bin, self.cellsize())
+    }
+
+    /// Names are stored in the nametable, unless the file uses inline names.
+    pub fn nametable(&self) -> Result<NameTable<'_>, TableError> {
+        if !uses_nametable(self.defsize, self.cellsize()) {
+            return Err(TableError::NametableMissing);
+        }
+
+        NameTable::new(&self.bin, self.nametable as usize, self.cod as usize)
+    }
+
+    pub fn publics(&self) -> Result<TableIterator<'_, Public>, TableError> {
+        self.table(self.publics, self.natives)
+    }
+
+    pub fn natives(&self) -> Result<TableIterator<'_, Native>, TableError> {
+        self.table(self.natives, self.libraries)
+    }
+
+    pub fn libraries(&self) -> Result<TableIterator<'_, Library>, TableError> {
+        self.table(self.libraries, self.pubvars)
+    }
+
+    pub fn pubvars(&self) -> Result<TableIterator<'_, Pubvar>, TableError> {
+        self.table(self.pubvars, self.tags)
+    }
+
+    pub fn tags(&self) -> Result<TableIterator<'_, Tag>, TableError> {
+        self.table(self.tags, self.nametable)
+    }
+
+    fn table<T: TableRecord>(
+        &self,
+        start: u32,
+        end: u32,
+    ) -> Result<TableIterator<'_, T>, TableError> {
+        let nametable = match self.nametable() {
+            Ok(nametable) => Some(nametable),
+            Err(TableError::NametableMissing) => None,
+            Err(e) => return Err(e),
+        };
+        let records = self.bin.get((start as usize)..(end as usize));
+
+        TableIterator::new(records, nametable, self.defsize, self.cellsize())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{File as AmxFile, Flags};
+    use super::{
+        Constant, DebugParseError, File as AmxFile, Flags, Native, Public, TableError, Tag,
+    };
     use std::convert::TryFrom;
     use std::fs::File as IoFile;
     use std::io::{self, Read};
@@ -89,4 +239,162 @@ mod tests {
 
         // TODO: Test cod parsing correctness
     }
+
+    #[test]
+    fn it_returns_header() {
+        let bin = read_file("test/fixtures/amxx/simple.cellsize4.amx183");
+        let header = AmxFile::try_from(&bin[..]).unwrap().header();
+
+        assert_eq!(header.size, 296);
+        assert_eq!(header.flags, Flags::DEBUG);
+        assert_eq!((header.cod, header.dat, header.hea), (116, 192, 296));
+        assert_eq!(header.stp, 16680);
+        assert_eq!(header.cip, 0xFFFF_FFFF);
+    }
+
+    #[test]
+    fn it_returns_tables() {
+        let bin = read_file("test/fixtures/amxx/simple.cellsize4.amx183");
+        let file = AmxFile::try_from(&bin[..]).unwrap();
+
+        let publics: Vec<Public> = file.publics().unwrap().map(Result::unwrap).collect();
+        assert_eq!(
+            publics,
+            [Public {
+                name: "plugin_init".to_owned(),
+                address: 8,
+            }]
+        );
+
+        let natives: Vec<Native> = file.natives().unwrap().map(Result::unwrap).collect();
+        assert_eq!(
+            natives,
+            [Native {
+                name: "register_plugin".to_owned(),
+                address: 0,
+            }]
+        );
+
+        assert_eq!(file.libraries().unwrap().count(), 0);
+        assert_eq!(file.pubvars().unwrap().count(), 0);
+
+        let tags: Vec<Tag> = file.tags().unwrap().map(Result::unwrap).collect();
+        assert_eq!(
+            tags,
+            [Tag {
+                name: "Float".to_owned(),
+                id: 0x4000_0005,
+            }]
+        );
+
+        assert_eq!(file.nametable().unwrap().max_name_length(), 31);
+    }
+
+    #[test]
+    fn it_returns_tables_of_64_bit_file() {
+        let bin = read_file("test/fixtures/amxx/simple.cellsize8.amx181");
+        let file = AmxFile::try_from(&bin[..]).unwrap();
+
+        let publics: Vec<Public> = file.publics().unwrap().map(Result::unwrap).collect();
+        assert_eq!(
+            publics,
+            [Public {
+                name: "plugin_init".to_owned(),
+                address: 16,
+            }]
+        );
+
+        let tags: Vec<Tag> = file.tags().unwrap().map(Result::unwrap).collect();
+        assert_eq!(tags[0].name, "Float");
+    }
+
+    #[test]
+    fn it_fails_on_table_out_of_file() {
+        let mut bin = read_file("test/fixtures/amxx/simple.cellsize4.amx183");
+        // natives table offset
+        bin[36] = 0xff;
+        let file = AmxFile::try_from(&bin[..]).unwrap();
+
+        match file.publics().err() {
+            Some(TableError::PublicsOutOfBounds) => (),
+            _ => panic!("Error should be TableError::PublicsOutOfBounds"),
+        }
+    }
+
+    #[test]
+    fn it_returns_debug_info() {
+        let bin = read_file("test/fixtures/amxx/simple.cellsize4.amx183");
+        let file = AmxFile::try_from(&bin[..]).unwrap();
+        let debug_info = file.debug_info().expect("Debug info should be parsed");
+
+        assert!(debug_info.files[0].name.ends_with("simple.sma"));
+        assert_eq!(debug_info.function(8).unwrap().name, "plugin_init");
+        assert_eq!(debug_info.line(0x10), Some(3));
+        assert_eq!(debug_info.tags.len(), 13);
+        assert_eq!(debug_info.automatons.len(), 1);
+    }
+
+    #[test]
+    fn it_fails_without_debug_info() {
+        let bin = read_file("test/fixtures/amxx/simple.cellsize8.amx181");
+        let file = AmxFile::try_from(&bin[..]).unwrap();
+
+        match file.debug_info().err() {
+            Some(DebugParseError::DebugInfoMissing) => (),
+            _ => panic!("Error should be DebugParseError::DebugInfoMissing"),
+        }
+    }
+
+    #[test]
+    fn it_returns_cellsize() {
+        let bin = read_file("test/fixtures/amxx/simple.cellsize4.amx183");
+        assert_eq!(AmxFile::try_from(&bin[..]).unwrap().cellsize(), 4);
+
+        let bin = read_file("test/fixtures/amxx/simple.cellsize8.amx181");
+        assert_eq!(AmxFile::try_from(&bin[..]).unwrap().cellsize(), 8);
+    }
+
+    #[test]
+    fn it_returns_natives_and_publics() {
+        let bin = read_file("test/fixtures/amxx/two_natives.cellsize4.amx183");
+        let file = AmxFile::try_from(&bin[..]).unwrap();
+
+        let natives: Vec<String> = file.natives().unwrap().map(|n| n.unwrap().name).collect();
+        assert_eq!(natives, ["native_one", "native_two"]);
+
+        let publics: Vec<Public> = file.publics().unwrap().map(Result::unwrap).collect();
+        assert_eq!(
+            publics,
+            [Public {
+                name: "func".to_owned(),
+                address: 8,
+            }]
+        );
+    }
+
+    #[test]
+    fn it_reads_constants() {
+        let bin = read_file("test/fixtures/amxx/cell_constants.cellsize4.amx183");
+        let file = AmxFile::try_from(&bin[..]).unwrap();
+
+        assert_eq!(
+            file.read_constant(0).unwrap(),
+            Constant::String("simple plugin".to_owned())
+        );
+        assert_eq!(
+            file.read_constant(99_999_999).unwrap(),
+            Constant::Cell(99_999_999)
+        );
+    }
+
+    #[test]
+    fn it_reads_constants_of_64_bit_file() {
+        let bin = read_file("test/fixtures/amxx/simple.cellsize8.amx181");
+        let file = AmxFile::try_from(&bin[..]).unwrap();
+
+        assert_eq!(
+            file.read_constant(0).unwrap(),
+            Constant::String("simple plugin".to_owned())
+        );
+    }
 }
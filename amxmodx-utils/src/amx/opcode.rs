@@ -2,35 +2,139 @@ use super::opcode_type::OpcodeType;
 use super::UCell;
 use std::fmt;
 
-#[derive(Debug, Copy, Clone)]
+/// Single `case` of the case table, jump address is relative to COD section.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Case {
+    pub value: UCell,
+    pub address: UCell,
+}
+
+/// Jump table following `casetbl` opcode, used by preceding `switch`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CaseTable {
+    /// Jump address when none of the cases matched.
+    pub default: UCell,
+    pub cases: Vec<Case>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Operands {
+    Cells(Vec<UCell>),
+    CaseTable(CaseTable),
+    /// Raw payload of obsolete debug opcodes.
+    Payload(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Opcode {
+    address: UCell,
     code: OpcodeType,
-    argument: Option<UCell>,
+    operands: Operands,
 }
 
 impl Opcode {
-    pub fn new(code: OpcodeType, argument: Option<UCell>) -> Opcode {
-        Opcode { code, argument }
+    /// `address` is CIP of the opcode, relative to COD section.
+    pub fn new(address: UCell, code: OpcodeType, operands: Operands) -> Opcode {
+        Opcode {
+            address,
+            code,
+            operands,
+        }
+    }
+
+    pub fn address(&self) -> UCell {
+        self.address
+    }
+
+    pub fn code(&self) -> OpcodeType {
+        self.code
+    }
+
+    pub fn operands(&self) -> &Operands {
+        &self.operands
+    }
+
+    /// First operand cell, the only one for most opcodes.
+    pub fn argument(&self) -> Option<UCell> {
+        match self.operands {
+            Operands::Cells(ref cells) => cells.first().cloned(),
+            _ => None,
+        }
+    }
+
+    pub fn case_table(&self) -> Option<&CaseTable> {
+        match self.operands {
+            Operands::CaseTable(ref case_table) => Some(case_table),
+            _ => None,
+        }
     }
 }
 
 impl fmt::Display for Opcode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.argument {
-            // Align + Pad as Cell
-            Some(v) => write!(f, "{} 0x{:0>8X}", self.code, v),
-            None => write!(f, "{}", self.code),
+        write!(f, "{}", self.code)?;
+
+        // Align + Pad as Cell
+        match self.operands {
+            Operands::Cells(ref cells) => {
+                for cell in cells.iter() {
+                    write!(f, " 0x{:0>8X}", cell)?;
+                }
+            }
+            Operands::CaseTable(ref case_table) => {
+                write!(
+                    f,
+                    " 0x{:0>8X} 0x{:0>8X}",
+                    case_table.cases.len(),
+                    case_table.default
+                )?;
+                for case in case_table.cases.iter() {
+                    write!(f, " 0x{:0>8X} 0x{:0>8X}", case.value, case.address)?;
+                }
+            }
+            Operands::Payload(ref payload) => write!(f, " 0x{:0>8X}", payload.len())?,
         }
+
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Opcode, OpcodeType};
+    use super::{Case, CaseTable, Opcode, OpcodeType, Operands};
 
     #[test]
     fn has_fmt_with_argument() {
-        let opcode = Opcode::new(OpcodeType::OpPushPri, Some(1000));
+        let opcode = Opcode::new(0, OpcodeType::OpPushPri, Operands::Cells(vec![1000]));
         assert_eq!("push.pri 0x000003E8", format!("{}", opcode));
     }
+
+    #[test]
+    fn has_fmt_without_arguments() {
+        let opcode = Opcode::new(0, OpcodeType::OpProc, Operands::Cells(vec![]));
+        assert_eq!("proc", format!("{}", opcode));
+        assert_eq!(opcode.argument(), None);
+    }
+
+    #[test]
+    fn has_fmt_with_case_table() {
+        let case_table = CaseTable {
+            default: 0x40,
+            cases: vec![Case {
+                value: 1,
+                address: 0x30,
+            }],
+        };
+        let opcode = Opcode::new(0, OpcodeType::OpCasetbl, Operands::CaseTable(case_table));
+        assert_eq!(
+            "casetbl 0x00000001 0x00000040 0x00000001 0x00000030",
+            format!("{}", opcode)
+        );
+        assert_eq!(opcode.case_table().unwrap().default, 0x40);
+    }
 }
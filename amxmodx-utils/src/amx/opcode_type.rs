@@ -1,4 +1,5 @@
 use num_derive::FromPrimitive;
+use num_traits::FromPrimitive as _;
 use std::fmt;
 
 #[derive(Copy, Clone, Debug, PartialEq, FromPrimitive)]
@@ -145,16 +146,48 @@ pub enum OpcodeType {
 
 use self::OpcodeType::*;
 
-pub const SINGLE_PARAM_OPCODES: &[OpcodeType] = &[
-    OpLoadPri, OpLoadAlt, OpLoadSPri, OpLoadSAlt, OpLrefPri, OpLrefAlt, OpLrefSPri, OpLrefSAlt,
-    OpLodbI, OpConstPri, OpConstAlt, OpAddrPri, OpAddrAlt, OpStorPri, OpStorAlt, OpStorSPri,
-    OpStorSAlt, OpSrefPri, OpSrefAlt, OpSrefSPri, OpSrefSAlt, OpStrbI, OpLidxB, OpIdxaddrB,
-    OpAlignPri, OpAlignAlt, OpLctrl, OpSctrl, OpPushR, OpPushC, OpPush, OpPushS, OpStack, OpHeap,
-    OpCall, OpJump, OpJrel, OpJzer, OpJnz, OpJeq, OpJneq, OpJless, OpJleq, OpJgrtr, OpJgeq,
-    OpJsless, OpJsleq, OpJsgrtr, OpJsgeq, OpShl, OpShr, OpSshr, OpShlCPri, OpShlCAlt, OpShrCPri,
-    OpShrCAlt, OpAddC, OpSmulC, OpZero, OpZeroS, OpEqCPri, OpEqCAlt, OpInc, OpIncS, OpDec, OpDecS,
-    OpMovs, OpCmps, OpFill, OpHalt, OpBounds, OpSysreqC, OpSwitch, OpPushaddr,
-];
+/// Layout of operands following the opcode in COD section.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OperandsSchema {
+    /// Fixed number of cells, zero for opcodes without operands.
+    Cells(usize),
+    /// Number of records, default jump address and (value, jump address) pairs.
+    CaseTable,
+    /// Payload size in bytes followed by the payload, used by obsolete debug opcodes.
+    Sized,
+}
+
+impl OpcodeType {
+    pub fn operands(self) -> OperandsSchema {
+        match self {
+            OpLoadPri | OpLoadAlt | OpLoadSPri | OpLoadSAlt | OpLrefPri | OpLrefAlt
+            | OpLrefSPri | OpLrefSAlt | OpLodbI | OpConstPri | OpConstAlt | OpAddrPri
+            | OpAddrAlt | OpStorPri | OpStorAlt | OpStorSPri | OpStorSAlt | OpSrefPri
+            | OpSrefAlt | OpSrefSPri | OpSrefSAlt | OpStrbI | OpLidxB | OpIdxaddrB | OpAlignPri
+            | OpAlignAlt | OpLctrl | OpSctrl | OpPushR | OpPushC | OpPush | OpPushS | OpStack
+            | OpHeap | OpCall | OpJump | OpJrel | OpJzer | OpJnz | OpJeq | OpJneq | OpJless
+            | OpJleq | OpJgrtr | OpJgeq | OpJsless | OpJsleq | OpJsgrtr | OpJsgeq | OpShlCPri
+            | OpShlCAlt | OpShrCPri | OpShrCAlt | OpAddC | OpSmulC | OpZero | OpZeroS
+            | OpEqCPri | OpEqCAlt | OpInc | OpIncS | OpDec | OpDecS | OpMovs | OpCmps | OpFill
+            | OpHalt | OpBounds | OpSysreqC | OpSwitch | OpPushaddr | OpSysreqD | OpSymtag => {
+                OperandsSchema::Cells(1)
+            }
+            // Line number and file index, range size and dimension
+            OpLine | OpSrange => OperandsSchema::Cells(2),
+            OpFile | OpSymbol => OperandsSchema::Sized,
+            OpCasetbl => OperandsSchema::CaseTable,
+            // Shifts by register (shl, shr, sshr) take no operand, unlike their .c variants
+            OpNone | OpLoadI | OpStorI | OpLidx | OpIdxaddr | OpMovePri | OpMoveAlt | OpXchg
+            | OpPushPri | OpPushAlt | OpPopPri | OpPopAlt | OpProc | OpRet | OpRetn | OpCallPri
+            | OpShl | OpShr | OpSshr | OpSmul | OpSdiv | OpSdivAlt | OpUmul | OpUdiv
+            | OpUdivAlt | OpAdd | OpSub | OpSubAlt | OpAnd | OpOr | OpXor | OpNot | OpNeg
+            | OpInvert | OpZeroPri | OpZeroAlt | OpSignPri | OpSignAlt | OpEq | OpNeq | OpLess
+            | OpLeq | OpGrtr | OpGeq | OpSless | OpSleq | OpSgrtr | OpSgeq | OpIncPri
+            | OpIncAlt | OpIncI | OpDecPri | OpDecAlt | OpDecI | OpSysreqPri | OpJumpPri
+            | OpSwapPri | OpSwapAlt | OpNop | OpBreak => OperandsSchema::Cells(0),
+        }
+    }
+}
 
 const OPCODE_FMT_NAMES: &[&str] = &[
     "invalid",    // invalid opcode
@@ -187,7 +220,7 @@ const OPCODE_FMT_NAMES: &[&str] = &[
     "idxaddr",    // PRI = ALT + (PRI * sizeof(cell))
     "idxaddr.b",  // PRI = ALT + (PRI << param)
     "align.pri",  // PRI ^= cellsize - param
-    "align.pri",  // ALT ^= cellsize - param
+    "align.alt",  // ALT ^= cellsize - param
     "lctrl",      // PRI is set to special register value.
     "sctrl",      // the special register is set to PRI
     "move.pri",   // PRI = ALT
@@ -297,6 +330,14 @@ const OPCODE_FMT_NAMES: &[&str] = &[
     "break",      // Breakpoint
 ];
 
+impl OpcodeType {
+    /// Opcode by its mnemonic, e.g. `const.pri`.
+    pub fn from_name(name: &str) -> Option<OpcodeType> {
+        let id = OPCODE_FMT_NAMES.iter().position(|&n| n == name)?;
+        OpcodeType::from_usize(id)
+    }
+}
+
 impl fmt::Display for OpcodeType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let id = *self as usize;
@@ -306,12 +347,40 @@ impl fmt::Display for OpcodeType {
     }
 }
 
+/// Serialized as the mnemonic, e.g. `"const.pri"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for OpcodeType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::OpcodeType::*;
+    use super::OpcodeType::{self, *};
+    use super::OperandsSchema;
 
     #[test]
     fn has_fmt() {
         assert_eq!("load.pri", format!("{}", OpLoadPri));
     }
+
+    #[test]
+    fn it_finds_opcode_by_name() {
+        assert_eq!(OpcodeType::from_name("load.pri"), Some(OpLoadPri));
+        assert_eq!(OpcodeType::from_name("align.alt"), Some(OpAlignAlt));
+        assert_eq!(OpcodeType::from_name("break"), Some(OpBreak));
+        assert_eq!(OpcodeType::from_name("mov.pri"), None);
+    }
+
+    #[test]
+    fn it_describes_operands() {
+        assert_eq!(OpPushPri.operands(), OperandsSchema::Cells(0));
+        assert_eq!(OpPushC.operands(), OperandsSchema::Cells(1));
+        assert_eq!(OpShl.operands(), OperandsSchema::Cells(0));
+        assert_eq!(OpSysreqD.operands(), OperandsSchema::Cells(1));
+        assert_eq!(OpLine.operands(), OperandsSchema::Cells(2));
+        assert_eq!(OpCasetbl.operands(), OperandsSchema::CaseTable);
+        assert_eq!(OpFile.operands(), OperandsSchema::Sized);
+    }
 }
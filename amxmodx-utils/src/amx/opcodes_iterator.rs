@@ -1,14 +1,15 @@
-use super::opcode::Opcode;
-use super::opcode_type::{OpcodeType, SINGLE_PARAM_OPCODES};
+use super::opcode::{Case, CaseTable, Opcode, Operands};
+use super::opcode_type::{OpcodeType, OperandsSchema};
+use super::UCell;
 use byteorder::{LittleEndian, ReadBytesExt};
 use failure::Fail;
 use num_traits::FromPrimitive;
-use std::io::Cursor;
+use std::io::{Cursor, Read};
 
 #[derive(Debug, Fail)]
 pub enum ParseError {
     #[fail(display = "Invalid opcode code: {}", _0)]
-    InvalidOpcodeCode(u32),
+    InvalidOpcodeCode(u64),
     #[fail(display = "Unexpected end of cod, missing opcode argument")]
     MissingOpcodeArgument,
 }
@@ -16,19 +17,75 @@ pub enum ParseError {
 #[derive(Debug)]
 pub struct OpcodesIterator<'amx_bin> {
     stop_iteration: bool,
+    cellsize: usize,
     cod_reader: Cursor<&'amx_bin [u8]>,
 }
 
 impl<'amx_bin> OpcodesIterator<'amx_bin> {
-    pub fn new(cod_bin: &'amx_bin [u8]) -> OpcodesIterator<'amx_bin> {
+    /// Opcodes and their arguments are read as `cellsize` bytes wide cells.
+    pub fn new(cod_bin: &'amx_bin [u8], cellsize: usize) -> OpcodesIterator<'amx_bin> {
         let stop_iteration = false;
         let cod_reader = Cursor::new(cod_bin);
 
         OpcodesIterator {
             stop_iteration,
+            cellsize,
             cod_reader,
         }
     }
+
+    fn read_cell(&mut self) -> Option<UCell> {
+        self.cod_reader
+            .read_uint::<LittleEndian>(self.cellsize)
+            .ok()
+    }
+
+    fn read_argument(&mut self) -> Result<UCell, ParseError> {
+        self.read_cell().ok_or(ParseError::MissingOpcodeArgument)
+    }
+
+    fn read_operands(&mut self, schema: OperandsSchema) -> Result<Operands, ParseError> {
+        match schema {
+            OperandsSchema::Cells(count) => {
+                let cells = (0..count)
+                    .map(|_| self.read_argument())
+                    .collect::<Result<_, _>>()?;
+                Ok(Operands::Cells(cells))
+            }
+            OperandsSchema::CaseTable => Ok(Operands::CaseTable(self.read_case_table()?)),
+            OperandsSchema::Sized => {
+                let size = self.read_argument()?;
+                let mut payload = vec![];
+                self.cod_reader
+                    .by_ref()
+                    .take(size)
+                    .read_to_end(&mut payload)
+                    .map_err(|_| ParseError::MissingOpcodeArgument)?;
+
+                if payload.len() as UCell != size {
+                    return Err(ParseError::MissingOpcodeArgument);
+                }
+
+                Ok(Operands::Payload(payload))
+            }
+        }
+    }
+
+    fn read_case_table(&mut self) -> Result<CaseTable, ParseError> {
+        let records = self.read_argument()?;
+        let default = self.read_argument()?;
+
+        // Records count comes from the file, do not trust it for preallocation
+        let mut cases = vec![];
+        for _ in 0..records {
+            cases.push(Case {
+                value: self.read_argument()?,
+                address: self.read_argument()?,
+            });
+        }
+
+        Ok(CaseTable { default, cases })
+    }
 }
 
 impl<'amx_bin> Iterator for OpcodesIterator<'amx_bin> {
@@ -39,39 +96,32 @@ impl<'amx_bin> Iterator for OpcodesIterator<'amx_bin> {
             return None;
         }
 
-        let opcode_code = self.cod_reader.read_u32::<LittleEndian>().ok()?;
-        let opcode_type = match OpcodeType::from_u32(opcode_code) {
+        let address = self.cod_reader.position();
+        let opcode_code = self.read_cell()?;
+        let opcode_type = match OpcodeType::from_u64(opcode_code) {
             Some(opcode_type) => opcode_type,
             None => {
                 self.stop_iteration = true;
-                // TODO: test
                 return Some(Err(ParseError::InvalidOpcodeCode(opcode_code)));
             }
         };
 
-        let has_arguments = SINGLE_PARAM_OPCODES.iter().any(|e| e == &opcode_type);
-
-        let opcode_argument = if has_arguments {
-            match self.cod_reader.read_u32::<LittleEndian>().ok() {
-                Some(value) => Some(value),
-                None => {
-                    self.stop_iteration = true;
-                    // TODO: Test
-                    return Some(Err(ParseError::MissingOpcodeArgument));
-                }
+        match self.read_operands(opcode_type.operands()) {
+            Ok(operands) => Some(Ok(Opcode::new(address, opcode_type, operands))),
+            Err(e) => {
+                self.stop_iteration = true;
+                Some(Err(e))
             }
-        } else {
-            None
-        };
-
-        Some(Ok(Opcode::new(opcode_type, opcode_argument)))
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Opcode, ParseError};
+    use super::{Case, CaseTable, Opcode, OpcodesIterator, ParseError};
+    use crate::amx::opcode_type::OpcodeType::{self, *};
     use crate::amx::File as AmxFile;
+    use crate::amxx::File as AmxxFile;
     use std::convert::TryFrom;
     use std::fs::File;
     use std::io::{self, Read};
@@ -88,15 +138,150 @@ mod tests {
         _read_file(path).expect(&format!("Could not read {} file", path))
     }
 
+    fn cod(cells: &[u64]) -> Vec<u8> {
+        cells
+            .iter()
+            .flat_map(|c| (*c as u32).to_le_bytes().to_vec())
+            .collect()
+    }
+
+    fn op(opcode_type: OpcodeType) -> u64 {
+        opcode_type as u64
+    }
+
     #[test]
     fn it_iterates_opcodes_on_correct_file() {
         let amx_bin = read_file("test/fixtures/amxx/simple.cellsize4.amx183");
         let amx_file = AmxFile::try_from(&amx_bin[..]).unwrap();
-        let mut opcodes_iterator = amx_file.opcodes().expect("Should return opcodes iterator");
-        let opcodes: Vec<Result<Opcode, ParseError>> = opcodes_iterator.collect();
+        let opcodes_iterator = amx_file.opcodes().expect("Should return opcodes iterator");
+        let opcodes: Vec<Opcode> = opcodes_iterator
+            .collect::<Result<_, ParseError>>()
+            .expect("Opcodes should be correctly parsed");
 
-        // TODO: Test parsing correctness
+        assert_eq!(opcodes[0].code(), OpHalt);
+        assert_eq!(opcodes[1].code(), OpProc);
+        assert_eq!(opcodes[1].address(), 8);
+        assert_eq!(opcodes.last().unwrap().code(), OpRetn);
     }
 
-    // TODO: Failing test cases
+    #[test]
+    fn it_reads_64_bit_cells() {
+        let amx_bin = read_file("test/fixtures/amxx/simple.cellsize8.amx181");
+        let amx_file = AmxFile::try_from(&amx_bin[..]).unwrap();
+        let opcodes: Vec<String> = amx_file
+            .opcodes()
+            .expect("Should return opcodes iterator")
+            .map(|opcode| format!("{}", opcode.expect("Opcode should be correctly parsed")))
+            .collect();
+
+        assert_eq!(opcodes[0], "halt 0x00000000");
+        assert_eq!(opcodes[1], "proc");
+        assert_eq!(opcodes[2], "push.c 0x00000090");
+    }
+
+    #[test]
+    fn it_reads_shl_without_operand() {
+        let amxx_bin = read_file("test/fixtures/amxx/shl_minimal_case.amxx");
+        let amxx_file = AmxxFile::try_from(&amxx_bin[..]).unwrap();
+        let amx_file = amxx_file
+            .sections()
+            .next()
+            .unwrap()
+            .unwrap()
+            .unpack()
+            .unwrap();
+        let opcodes: Vec<Opcode> = amx_file
+            .opcodes()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .expect("Opcodes following shl should be correctly parsed");
+
+        let shl_position = opcodes.iter().position(|o| o.code() == OpShl).unwrap();
+        assert_eq!(opcodes[shl_position].argument(), None);
+        assert_eq!(opcodes[shl_position + 1].code(), OpJzer);
+    }
+
+    #[test]
+    fn it_reads_case_table() {
+        let cod_bin = cod(&[
+            op(OpSwitch),
+            0x10,
+            op(OpCasetbl),
+            2,
+            0x40,
+            1,
+            0x30,
+            5,
+            0x38,
+            op(OpRetn),
+        ]);
+        let opcodes: Vec<Opcode> = OpcodesIterator::new(&cod_bin, 4)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(opcodes.len(), 3);
+        assert_eq!(opcodes[0].argument(), Some(0x10));
+        assert_eq!(opcodes[1].address(), 0x08);
+        assert_eq!(
+            opcodes[1].case_table(),
+            Some(&CaseTable {
+                default: 0x40,
+                cases: vec![
+                    Case {
+                        value: 1,
+                        address: 0x30,
+                    },
+                    Case {
+                        value: 5,
+                        address: 0x38,
+                    },
+                ],
+            })
+        );
+        assert_eq!(opcodes[2].code(), OpRetn);
+        assert_eq!(opcodes[2].address(), 0x24);
+    }
+
+    #[test]
+    fn it_reads_multiple_operands() {
+        let cod_bin = cod(&[op(OpLine), 12, 0, op(OpShl), op(OpFile), 8, 0, 0, op(OpNop)]);
+        let opcodes: Vec<String> = OpcodesIterator::new(&cod_bin, 4)
+            .map(|opcode| format!("{}", opcode.unwrap()))
+            .collect();
+
+        assert_eq!(
+            opcodes,
+            [
+                "line 0x0000000C 0x00000000",
+                "shl",
+                "file 0x00000008",
+                "nop"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_fails_on_invalid_opcode() {
+        let cod_bin = cod(&[op(OpProc), 0xFFFF, op(OpRetn)]);
+        let mut opcodes = OpcodesIterator::new(&cod_bin, 4);
+
+        assert!(opcodes.next().unwrap().is_ok());
+        match opcodes.next() {
+            Some(Err(ParseError::InvalidOpcodeCode(0xFFFF))) => (),
+            _ => panic!("Error should be ParseError::InvalidOpcodeCode"),
+        }
+        assert!(opcodes.next().is_none());
+    }
+
+    #[test]
+    fn it_fails_on_truncated_case_table() {
+        let cod_bin = cod(&[op(OpCasetbl), 2, 0x40, 1, 0x30]);
+        let mut opcodes = OpcodesIterator::new(&cod_bin, 4);
+
+        match opcodes.next() {
+            Some(Err(ParseError::MissingOpcodeArgument)) => (),
+            _ => panic!("Error should be ParseError::MissingOpcodeArgument"),
+        }
+        assert!(opcodes.next().is_none());
+    }
 }
@@ -1,3 +1,5 @@
+use super::compact::{expand_image, CompactError};
+use super::tables::defsize_cellsize;
 use super::{File, Flags};
 use bytes::Buf;
 use failure::Fail;
@@ -5,9 +7,10 @@ use std::convert::TryFrom;
 use std::io::Cursor;
 use std::mem::size_of;
 
-const MAGIC: u16 = 0xF1E0;
-const FILE_VERSION: u8 = 8;
-const AMX_VERSION: u8 = 8;
+pub(crate) const MAGIC: u16 = 0xF1E0;
+pub(crate) const FILE_VERSION: u8 = 8;
+pub(crate) const AMX_VERSION: u8 = 8;
+pub(crate) const HEADER_SIZE: usize = size_of::<RawAmxHeader>();
 
 #[derive(Debug, Fail)]
 pub enum HeaderParseError {
@@ -24,6 +27,10 @@ pub enum HeaderParseError {
         _0
     )]
     UnexpectedAmxFlags(u16),
+    #[fail(display = "Unsupported table record size (defsize): {}", _0)]
+    UnexpectedDefsize(u16),
+    #[fail(display = "Failed to expand compact encoded image: {}", _0)]
+    CompactExpansion(#[cause] CompactError),
 }
 
 // Struct used only to calculate header size for reading
@@ -54,12 +61,12 @@ impl TryFrom<&[u8]> for File {
     fn try_from(bin: &[u8]) -> Result<Self, Self::Error> {
         // TODO: Test
         let header_bin = bin
-            .get(0..size_of::<RawAmxHeader>())
+            .get(0..HEADER_SIZE)
             .ok_or_else(|| HeaderParseError::HeaderEOF)?;
 
         let mut header_reader = Cursor::new(header_bin);
 
-        let _size = header_reader.get_u32_le();
+        let size = header_reader.get_u32_le();
         let magic = header_reader.get_u16_le();
         let file_version = header_reader.get_u8();
         let amx_version = header_reader.get_u8();
@@ -98,15 +105,24 @@ impl TryFrom<&[u8]> for File {
             ));
         }
 
-        // TODO: defsize mismatch
+        if defsize_cellsize(defsize).is_none() {
+            return Err(HeaderParseError::UnexpectedDefsize(defsize));
+        }
 
         // TODO: Test
         let flags =
             Flags::from_bits(flags).ok_or_else(|| HeaderParseError::UnexpectedAmxFlags(flags))?;
+
+        // Expanded image has compact flag cleared, so it is parsed as usual
+        if flags.contains(Flags::COMPACT) {
+            let expanded = expand_image(bin).map_err(HeaderParseError::CompactExpansion)?;
+            return File::try_from(&expanded[..]);
+        }
         let bin = bin.to_owned();
 
         Ok(File {
             bin,
+            size,
             flags,
             defsize,
             cod,
@@ -126,7 +142,8 @@ impl TryFrom<&[u8]> for File {
 
 #[cfg(test)]
 mod tests {
-    use super::{File as AmxFile, Flags};
+    use super::{File as AmxFile, Flags, HeaderParseError};
+    use crate::amx::compact::compact_image;
     use std::convert::TryFrom;
     use std::fs::File as IoFile;
     use std::io::{self, Read};
@@ -151,6 +168,7 @@ mod tests {
         match file {
             AmxFile {
                 bin: unpacked_bin,
+                size: 296,
                 flags: Flags::DEBUG,
                 defsize: 8,
                 cod: 116,
@@ -168,4 +186,26 @@ mod tests {
             _ => panic!("Amxx file parsed invalid"),
         }
     }
+
+    #[test]
+    fn it_expands_compact_file() {
+        let unpacked_bin = read_file("test/fixtures/amxx/simple.cellsize4.amx183");
+        let compact_bin = compact_image(&unpacked_bin).unwrap();
+
+        let file = AmxFile::try_from(&compact_bin[..]).expect("Plugin should be correctly parsed");
+        let expected_file = AmxFile::try_from(&unpacked_bin[..]).unwrap();
+
+        assert_eq!(file, expected_file);
+    }
+
+    #[test]
+    fn it_fails_with_unexpected_defsize() {
+        let mut bin = read_file("test/fixtures/amxx/simple.cellsize4.amx183");
+        bin[10] = 3;
+
+        match AmxFile::try_from(&bin[..]).err() {
+            Some(HeaderParseError::UnexpectedDefsize(3)) => (),
+            _ => panic!("Error should be HeaderParseError::UnexpectedDefsize"),
+        }
+    }
 }
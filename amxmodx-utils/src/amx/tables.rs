@@ -0,0 +1,323 @@
+use std::marker::PhantomData;
+use std::str;
+
+use byteorder::{ByteOrder, LittleEndian};
+use failure::Fail;
+
+use super::UCell;
+
+// sEXPMAX + 1, size of the name stored right in the record when there is no nametable
+const INLINE_NAME_SIZE: usize = 20;
+pub(crate) const NAMEOFS_SIZE: usize = 4;
+pub(crate) const NAMETABLE_HEADER_SIZE: usize = 2;
+
+#[derive(Debug, Fail)]
+pub enum TableError {
+    #[fail(display = "Publics table got invalid bounds")]
+    PublicsOutOfBounds,
+    #[fail(display = "Public #{} got invalid name", _0)]
+    InvalidPublicName(usize),
+    #[fail(display = "Natives table got invalid bounds")]
+    NativesOutOfBounds,
+    #[fail(display = "Native #{} got invalid name", _0)]
+    InvalidNativeName(usize),
+    #[fail(display = "Libraries table got invalid bounds")]
+    LibrariesOutOfBounds,
+    #[fail(display = "Library #{} got invalid name", _0)]
+    InvalidLibraryName(usize),
+    #[fail(display = "Public variables table got invalid bounds")]
+    PubvarsOutOfBounds,
+    #[fail(display = "Public variable #{} got invalid name", _0)]
+    InvalidPubvarName(usize),
+    #[fail(display = "Tags table got invalid bounds")]
+    TagsOutOfBounds,
+    #[fail(display = "Tag #{} got invalid name", _0)]
+    InvalidTagName(usize),
+    #[fail(display = "Nametable got invalid bounds")]
+    NametableOutOfBounds,
+    #[fail(display = "File has no nametable, names are stored inline")]
+    NametableMissing,
+}
+
+/// Record of one of amx header tables, constructed from address and resolved name.
+pub trait TableRecord: Sized {
+    fn new(address: UCell, name: String) -> Self;
+    fn out_of_bounds() -> TableError;
+    fn invalid_name(index: usize) -> TableError;
+}
+
+macro_rules! table_record {
+    ($(#[$meta:meta])* $name:ident, $address:ident, $out_of_bounds:ident, $invalid_name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+        pub struct $name {
+            pub name: String,
+            pub $address: UCell,
+        }
+
+        impl TableRecord for $name {
+            fn new($address: UCell, name: String) -> Self {
+                $name { name, $address }
+            }
+
+            fn out_of_bounds() -> TableError {
+                TableError::$out_of_bounds
+            }
+
+            fn invalid_name(index: usize) -> TableError {
+                TableError::$invalid_name(index)
+            }
+        }
+    };
+}
+
+table_record!(
+    /// Public function, address is relative to COD section.
+    Public,
+    address,
+    PublicsOutOfBounds,
+    InvalidPublicName
+);
+table_record!(
+    /// Native function, address is filled by amxmodx when plugin is loaded.
+    Native,
+    address,
+    NativesOutOfBounds,
+    InvalidNativeName
+);
+table_record!(
+    /// Library natives are loaded from.
+    Library,
+    address,
+    LibrariesOutOfBounds,
+    InvalidLibraryName
+);
+table_record!(
+    /// Public variable, address is relative to DAT section.
+    Pubvar,
+    address,
+    PubvarsOutOfBounds,
+    InvalidPubvarName
+);
+table_record!(
+    /// Tag name with its numeric id.
+    Tag,
+    id,
+    TagsOutOfBounds,
+    InvalidTagName
+);
+
+/// Cellsize of the file with given table record size, if such records are supported.
+pub(crate) fn defsize_cellsize(defsize: u16) -> Option<usize> {
+    [4, 8].iter().cloned().find(|&cellsize| {
+        let defsize = usize::from(defsize);
+        defsize == cellsize * 2 || defsize == cellsize + INLINE_NAME_SIZE
+    })
+}
+
+/// Whether records reference names in nametable instead of storing them inline.
+pub(crate) fn uses_nametable(defsize: u16, cellsize: usize) -> bool {
+    usize::from(defsize) == cellsize * 2
+}
+
+fn read_name(bin: &[u8]) -> Option<String> {
+    let end = bin.iter().position(|&c| c == 0)?;
+    str::from_utf8(&bin[..end]).ok().map(str::to_owned)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NameTable<'amx_bin> {
+    max_name_length: u16,
+    offset: usize,
+    // Whole image up to the end of nametable, names are addressed by absolute offsets
+    bin: &'amx_bin [u8],
+}
+
+impl<'amx_bin> NameTable<'amx_bin> {
+    /// `end` is the offset where names end, the start of COD section.
+    pub(crate) fn new(bin: &'amx_bin [u8], offset: usize, end: usize) -> Result<Self, TableError> {
+        let header = bin
+            .get(offset..end)
+            .and_then(|names| names.get(..NAMETABLE_HEADER_SIZE))
+            .ok_or(TableError::NametableOutOfBounds)?;
+
+        Ok(NameTable {
+            max_name_length: LittleEndian::read_u16(header),
+            offset,
+            bin: &bin[..end],
+        })
+    }
+
+    /// Maximum name length the plugin was compiled with.
+    pub fn max_name_length(&self) -> u16 {
+        self.max_name_length
+    }
+
+    /// Name at the absolute file offset, as referenced from the table records.
+    pub fn name_at(&self, offset: usize) -> Option<String> {
+        if offset < self.offset + NAMETABLE_HEADER_SIZE {
+            return None;
+        }
+
+        read_name(self.bin.get(offset..)?)
+    }
+}
+
+/// Iterates over records of a single amx header table.
+#[derive(Debug)]
+pub struct TableIterator<'amx_bin, T> {
+    records: &'amx_bin [u8],
+    nametable: Option<NameTable<'amx_bin>>,
+    defsize: usize,
+    cellsize: usize,
+    current_record: usize,
+    stop_iteration: bool,
+    record_type: PhantomData<T>,
+}
+
+impl<'amx_bin, T: TableRecord> TableIterator<'amx_bin, T> {
+    /// Records are taken from `records` slice, which size must be multiple of `defsize`.
+    /// Without nametable names are expected to be inline.
+    pub(crate) fn new(
+        records: Option<&'amx_bin [u8]>,
+        nametable: Option<NameTable<'amx_bin>>,
+        defsize: u16,
+        cellsize: usize,
+    ) -> Result<Self, TableError> {
+        let defsize = usize::from(defsize);
+        let records = records.ok_or_else(T::out_of_bounds)?;
+
+        if records.len() % defsize != 0 {
+            return Err(T::out_of_bounds());
+        }
+
+        Ok(TableIterator {
+            records,
+            nametable,
+            defsize,
+            cellsize,
+            current_record: 0,
+            stop_iteration: false,
+            record_type: PhantomData,
+        })
+    }
+
+    fn read_record(&self, record: &[u8]) -> Option<T> {
+        let address = LittleEndian::read_uint(&record[..self.cellsize], self.cellsize);
+        let name_field = &record[self.cellsize..];
+
+        let name = match self.nametable {
+            Some(nametable) => {
+                let name_offset = LittleEndian::read_u32(name_field.get(..NAMEOFS_SIZE)?);
+                nametable.name_at(name_offset as usize)?
+            }
+            None => read_name(name_field)?,
+        };
+
+        Some(T::new(address, name))
+    }
+}
+
+impl<'amx_bin, T: TableRecord> Iterator for TableIterator<'amx_bin, T> {
+    type Item = Result<T, TableError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stop_iteration {
+            return None;
+        }
+
+        let position = self.current_record * self.defsize;
+        let record = self.records.get(position..(position + self.defsize))?;
+
+        let result = match self.read_record(record) {
+            Some(record) => Ok(record),
+            None => {
+                self.stop_iteration = true;
+                Err(T::invalid_name(self.current_record))
+            }
+        };
+
+        self.current_record += 1;
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{defsize_cellsize, NameTable, Public, TableError, TableIterator, Tag};
+
+    const NAMETABLE_BIN: &[u8] = b"\x1f\0func\0Float\0";
+
+    #[test]
+    fn it_resolves_cellsize_from_defsize() {
+        assert_eq!(defsize_cellsize(8), Some(4));
+        assert_eq!(defsize_cellsize(16), Some(8));
+        assert_eq!(defsize_cellsize(24), Some(4));
+        assert_eq!(defsize_cellsize(28), Some(8));
+        assert_eq!(defsize_cellsize(12), None);
+    }
+
+    #[test]
+    fn it_reads_names_from_nametable() {
+        let nametable = NameTable::new(NAMETABLE_BIN, 0, NAMETABLE_BIN.len()).unwrap();
+        let records = b"\x08\0\0\0\x02\0\0\0";
+        let publics: Vec<Public> = TableIterator::new(Some(records), Some(nametable), 8, 4)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(nametable.max_name_length(), 31);
+        assert_eq!(
+            publics,
+            [Public {
+                name: "func".to_owned(),
+                address: 8,
+            }]
+        );
+    }
+
+    #[test]
+    fn it_reads_inline_names() {
+        let mut records = [0u8; 24];
+        records[0] = 5;
+        records[3] = 0x40;
+        records[4..9].copy_from_slice(b"Float");
+
+        let tags: Vec<Tag> = TableIterator::new(Some(&records[..]), None, 24, 4)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(
+            tags,
+            [Tag {
+                name: "Float".to_owned(),
+                id: 0x4000_0005,
+            }]
+        );
+    }
+
+    #[test]
+    fn it_fails_on_partial_record() {
+        match TableIterator::<Public>::new(Some(&[0; 7]), None, 8, 4) {
+            Err(TableError::PublicsOutOfBounds) => (),
+            _ => panic!("Error should be TableError::PublicsOutOfBounds"),
+        }
+    }
+
+    #[test]
+    fn it_fails_on_name_outside_of_nametable() {
+        let nametable = NameTable::new(NAMETABLE_BIN, 0, NAMETABLE_BIN.len()).unwrap();
+        let records = b"\x08\0\0\0\x02\0\0\0\x08\0\0\0\xff\0\0\0";
+        let mut publics =
+            TableIterator::<Public>::new(Some(records), Some(nametable), 8, 4).unwrap();
+
+        assert!(publics.next().unwrap().is_ok());
+        match publics.next() {
+            Some(Err(TableError::InvalidPublicName(1))) => (),
+            _ => panic!("Error should be TableError::InvalidPublicName"),
+        }
+        assert!(publics.next().is_none());
+    }
+}
@@ -0,0 +1,192 @@
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+use bytes::BufMut;
+use failure::Fail;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use super::parser::{HEADER_SIZE, MAGIC, SUPPORTED_VERSION};
+use super::section::HEADER_SIZE as SECTION_HEADER_SIZE;
+
+#[derive(Debug, Fail)]
+pub enum BuildError {
+    #[fail(display = "File must contain at least one section")]
+    NoSections,
+    #[fail(display = "Too many sections, maximum is {}, got: {}", _0, _1)]
+    TooManySections(usize, usize),
+    #[fail(display = "Invalid section cellsize, must be 4 or 8, got: {}", _0)]
+    InvalidCellSize(u8),
+    #[fail(display = "Section does not fit into 32 bit offsets")]
+    SectionTooLarge,
+    #[fail(display = "Failed to compress section: {}", _0)]
+    Compression(#[cause] io::Error),
+}
+
+#[derive(Debug)]
+struct SectionImage {
+    cellsize: u8,
+    memsize: u32,
+    image: Vec<u8>,
+}
+
+/// Assembles `.amxx` container from unpacked amx images.
+///
+/// Every image is zlib compressed and placed right after the section headers,
+/// in the same order sections were added.
+#[derive(Debug, Default)]
+pub struct Builder {
+    sections: Vec<SectionImage>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Builder::default()
+    }
+
+    pub fn section(&mut self, cellsize: u8, memsize: u32, image: &[u8]) -> &mut Self {
+        self.sections.push(SectionImage {
+            cellsize,
+            memsize,
+            image: image.to_owned(),
+        });
+
+        self
+    }
+
+    pub fn build(&self) -> Result<Vec<u8>, BuildError> {
+        if self.sections.is_empty() {
+            return Err(BuildError::NoSections);
+        }
+
+        let sections_count = u8::try_from(self.sections.len())
+            .map_err(|_| BuildError::TooManySections(usize::from(u8::MAX), self.sections.len()))?;
+
+        let mut compressed_bodies = Vec::with_capacity(self.sections.len());
+        for section in self.sections.iter() {
+            if !(section.cellsize == 4 || section.cellsize == 8) {
+                return Err(BuildError::InvalidCellSize(section.cellsize));
+            }
+
+            let mut encoder = ZlibEncoder::new(vec![], Compression::best());
+            encoder
+                .write_all(&section.image)
+                .map_err(BuildError::Compression)?;
+            compressed_bodies.push(encoder.finish().map_err(BuildError::Compression)?);
+        }
+
+        let headers_size = HEADER_SIZE + SECTION_HEADER_SIZE * self.sections.len();
+        let mut bin = Vec::with_capacity(
+            headers_size + compressed_bodies.iter().map(Vec::len).sum::<usize>(),
+        );
+
+        bin.put_u32_le(MAGIC);
+        bin.put_u16_le(SUPPORTED_VERSION);
+        bin.put_u8(sections_count);
+
+        let mut offset = headers_size;
+        for (section, body) in self.sections.iter().zip(compressed_bodies.iter()) {
+            bin.put_u8(section.cellsize);
+            bin.put_u32_le(to_u32(body.len())?);
+            bin.put_u32_le(to_u32(section.image.len())?);
+            bin.put_u32_le(section.memsize);
+            bin.put_u32_le(to_u32(offset)?);
+
+            offset += body.len();
+        }
+
+        for body in compressed_bodies.iter() {
+            bin.put_slice(body);
+        }
+
+        Ok(bin)
+    }
+}
+
+fn to_u32(value: usize) -> Result<u32, BuildError> {
+    u32::try_from(value).map_err(|_| BuildError::SectionTooLarge)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use std::fs::File as IoFile;
+    use std::io::{self, Read};
+
+    use super::{BuildError, Builder};
+    use crate::amxx::file::section::Metadata;
+    use crate::amxx::File as AmxxFile;
+
+    fn _read_file(path: &str) -> io::Result<Vec<u8>> {
+        let mut file = IoFile::open(path)?;
+        let mut plugin = vec![];
+        file.read_to_end(&mut plugin)?;
+
+        Ok(plugin)
+    }
+
+    fn read_file(path: &str) -> Vec<u8> {
+        _read_file(path).expect(&format!("Could not read {} file", path))
+    }
+
+    #[test]
+    fn it_builds_file_readable_by_parser() {
+        let image = read_file("test/fixtures/amxx/simple.cellsize4.amx183");
+        let bin = Builder::new()
+            .section(4, 16680, &image)
+            .section(8, 33360, b"fake 64 bit image")
+            .build()
+            .expect("File should be built");
+
+        let file = AmxxFile::try_from(&bin[..]).expect("Built file should be parsed");
+        assert_eq!(file.sections_count(), 2);
+
+        let sections: Vec<_> = file
+            .sections()
+            .collect::<Result<_, _>>()
+            .expect("Sections should be correctly parsed");
+
+        let metadata = sections[0].metadata();
+        assert_eq!(
+            metadata,
+            Metadata::new(4, metadata.disksize, image.len() as u32, 16680)
+        );
+        assert_eq!(sections[0].unpack_body().unwrap(), image);
+
+        assert_eq!(sections[1].metadata().cellsize, 8);
+        assert_eq!(sections[1].metadata().memsize, 33360);
+        assert_eq!(sections[1].unpack_body().unwrap(), b"fake 64 bit image");
+    }
+
+    #[test]
+    fn it_lays_out_section_headers() {
+        let bin = Builder::new().section(4, 3, b"body").build().unwrap();
+
+        let mut disksize = [0; 4];
+        disksize.copy_from_slice(&bin[8..12]);
+        let disksize = u32::from_le_bytes(disksize) as usize;
+
+        assert_eq!(&bin[0..8], b"XXMA\0\x03\x01\x04");
+        assert_eq!(&bin[12..16], &4u32.to_le_bytes());
+        assert_eq!(&bin[16..20], &3u32.to_le_bytes());
+        // Section body starts right after amxx header and single section header
+        assert_eq!(&bin[20..24], &24u32.to_le_bytes());
+        assert_eq!(bin.len(), 24 + disksize);
+    }
+
+    #[test]
+    fn it_fails_without_sections() {
+        match Builder::new().build() {
+            Err(BuildError::NoSections) => (),
+            _ => panic!("Error should be BuildError::NoSections"),
+        }
+    }
+
+    #[test]
+    fn it_fails_with_invalid_cellsize() {
+        match Builder::new().section(2, 0, b"").build() {
+            Err(BuildError::InvalidCellSize(2)) => (),
+            _ => panic!("Error should be BuildError::InvalidCellSize"),
+        }
+    }
+}
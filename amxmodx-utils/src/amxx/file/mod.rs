@@ -1,9 +1,11 @@
+mod builder;
 mod errors;
 mod parser;
 pub mod section;
 
+pub use builder::{BuildError, Builder};
 pub use errors::ParseError;
-pub use section::{Section, SectionsIterator};
+pub use section::{Section, SectionsIterator, UnpackError};
 
 #[derive(Debug)]
 pub struct File {
@@ -25,9 +27,21 @@ impl File {
 #[cfg(test)]
 mod tests {
     use std::convert::TryFrom;
+    use std::path::Path;
 
     use super::{File as AmxxFile, SectionsIterator};
 
+    #[test]
+    fn it_unpacks_sections_of_each_cellsize() {
+        let amxx_file = AmxxFile::try_from(Path::new("test/fixtures/amxx/simple.amxx181")).unwrap();
+        let cellsizes: Vec<usize> = amxx_file
+            .sections()
+            .map(|s| s.unwrap().unpack().unwrap().cellsize())
+            .collect();
+
+        assert_eq!(cellsizes, [4, 8]);
+    }
+
     #[test]
     fn it_returns_sections_iterator() {
         const HEADER: &[u8] = b"XXMA\0\x03\x01\x01";
@@ -14,8 +14,8 @@ const SECTIONS_FIELD_SIZE: usize = size_of::<u8>();
 // TODO: Use raw C structure and calculate size based on it
 pub(crate) const HEADER_SIZE: usize = MAGIC_FIELD_SIZE + VERSION_FIELD_SIZE + SECTIONS_FIELD_SIZE;
 
-const MAGIC: u32 = 0x414d5858;
-const SUPPORTED_VERSION: u16 = 768;
+pub(crate) const MAGIC: u32 = 0x414d5858;
+pub(crate) const SUPPORTED_VERSION: u16 = 768;
 
 impl TryFrom<&[u8]> for File {
     type Error = ParseError;
@@ -2,13 +2,34 @@ mod sections_iterator;
 
 pub use sections_iterator::SectionsIterator;
 
+use failure::Fail;
 use flate2::read::ZlibDecoder;
+use std::convert::TryFrom;
 use std::io::{self, Read};
 
+use crate::amx::parser::HeaderParseError;
+use crate::amx::File as AmxFile;
+
 // TODO: Calculate it using C style header struct
 pub(crate) const HEADER_SIZE: usize = 17;
 
+#[derive(Debug, Fail)]
+pub enum UnpackError {
+    #[fail(display = "Failed to decompress section: {}", _0)]
+    Decompression(#[cause] io::Error),
+    #[fail(display = "Section imagesize mismatch, expected: {}, got: {}", _0, _1)]
+    ImageSizeMismatch(u32, usize),
+    #[fail(display = "Section contains invalid amx image: {}", _0)]
+    InvalidImage(#[cause] HeaderParseError),
+    #[fail(
+        display = "Section cellsize {} does not match amx image cellsize {}",
+        _0, _1
+    )]
+    CellSizeMismatch(u8, usize),
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Metadata {
     pub cellsize: u8,
     pub disksize: u32,
@@ -61,6 +82,27 @@ impl<'compressed_body> Section<'compressed_body> {
 
         Ok(unpacked_body)
     }
+
+    /// Unpacks amx image, making sure it matches section metadata.
+    pub fn unpack(&self) -> Result<AmxFile, UnpackError> {
+        let body = self.unpack_body().map_err(UnpackError::Decompression)?;
+        if body.len() != self.metadata.imagesize as usize {
+            return Err(UnpackError::ImageSizeMismatch(
+                self.metadata.imagesize,
+                body.len(),
+            ));
+        }
+
+        let amx_file = AmxFile::try_from(&body[..]).map_err(UnpackError::InvalidImage)?;
+        if amx_file.cellsize() != usize::from(self.metadata.cellsize) {
+            return Err(UnpackError::CellSizeMismatch(
+                self.metadata.cellsize,
+                amx_file.cellsize(),
+            ));
+        }
+
+        Ok(amx_file)
+    }
 }
 
 #[cfg(test)]
@@ -68,7 +110,7 @@ mod tests {
     use std::fs::File;
     use std::io::{self, Read};
 
-    use super::{Metadata, Section};
+    use super::{Metadata, Section, UnpackError};
 
     fn _read_file(path: &str) -> io::Result<Vec<u8>> {
         let mut file = File::open(path)?;
@@ -104,4 +146,35 @@ mod tests {
 
         assert_eq!(unpacked_body, expected_unpacked_body);
     }
+
+    #[test]
+    fn it_unpacks_amx_file() {
+        let bin = read_file("test/fixtures/amxx/simple.cellsize4_section.amxx183");
+        let section = Section::new(Metadata::new(4, 330, 578, 16680), &bin);
+        let amx_file = section.unpack().expect("Section should contain amx file");
+
+        assert_eq!(amx_file.cellsize(), 4);
+    }
+
+    #[test]
+    fn it_fails_on_imagesize_mismatch() {
+        let bin = read_file("test/fixtures/amxx/simple.cellsize4_section.amxx183");
+        let section = Section::new(Metadata::new(4, 330, 100, 16680), &bin);
+
+        match section.unpack().err() {
+            Some(UnpackError::ImageSizeMismatch(100, 578)) => (),
+            _ => panic!("Error should be UnpackError::ImageSizeMismatch"),
+        }
+    }
+
+    #[test]
+    fn it_fails_on_cellsize_mismatch() {
+        let bin = read_file("test/fixtures/amxx/simple.cellsize4_section.amxx183");
+        let section = Section::new(Metadata::new(8, 330, 578, 16680), &bin);
+
+        match section.unpack().err() {
+            Some(UnpackError::CellSizeMismatch(8, 4)) => (),
+            _ => panic!("Error should be UnpackError::CellSizeMismatch"),
+        }
+    }
 }
@@ -57,9 +57,9 @@ impl<'sections_bin> Iterator for SectionsIterator<'sections_bin> {
         let memsize = header_reader.get_u32_le();
         let metadata = Metadata::new(cellsize, disksize, imagesize, memsize);
 
-        let offset = (header_reader.get_u32_le() as usize) - AMXX_HEADER_SIZE;
-        let section_compressed_body_range = offset..(offset + (disksize as usize));
-        let compressed_body = match self.bin.get(section_compressed_body_range) {
+        // Offset is absolute, while bin starts right after amxx header
+        let offset = (header_reader.get_u32_le() as usize).checked_sub(AMXX_HEADER_SIZE);
+        let compressed_body = match offset.and_then(|o| self.bin.get(o..(o + disksize as usize))) {
             Some(slice) => slice,
             None => {
                 self.stop_iteration = true;
@@ -78,7 +78,7 @@ mod tests {
     use std::fs::File;
     use std::io::{self, Read};
 
-    use super::{Metadata, SectionsIterator};
+    use super::{Metadata, ParseError, SectionsIterator};
 
     fn _read_file(path: &str) -> io::Result<Vec<u8>> {
         let mut file = File::open(path)?;
@@ -114,6 +114,19 @@ mod tests {
         );
     }
 
-    // TODO: Failing test cases
+    #[test]
+    fn it_fails_on_body_inside_of_amxx_header() {
+        let mut sections_bin = read_file("test/fixtures/amxx/simple.amxx183.only_sections.amxx");
+        // Section body offset
+        sections_bin[13..17].copy_from_slice(&[1, 0, 0, 0]);
+        let mut sections_iterator = SectionsIterator::new(1, &sections_bin);
+
+        match sections_iterator.next() {
+            Some(Err(ParseError::InvalidSection)) => (),
+            _ => panic!("Error should be ParseError::InvalidSection"),
+        }
+        assert!(sections_iterator.next().is_none());
+    }
+
     // TODO: Test correct body is extracted
 }
@@ -1,11 +0,0 @@
-mod native;
-mod opcode;
-mod opcode_type;
-pub mod plugin;
-mod public;
-pub use self::native::Native;
-pub use self::opcode::Opcode;
-pub use self::opcode_type::*;
-pub use self::plugin::Plugin;
-pub use self::plugin::CELLSIZE;
-pub use self::public::Public;
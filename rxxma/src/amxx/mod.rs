@@ -1,4 +0,0 @@
-mod file;
-mod section;
-pub use self::file::File;
-pub use self::section::Section;
@@ -0,0 +1,543 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use amxmodx_utils::amx::opcode::{CaseTable, Opcode};
+use amxmodx_utils::amx::opcode_type::OpcodeType::{self, *};
+use amxmodx_utils::amx::UCell;
+use failure::Fail;
+
+/// Index of the block in `Cfg::blocks`.
+pub type BlockId = usize;
+
+#[derive(Debug, Fail)]
+pub enum CfgError {
+    #[fail(
+        display = "Jump at 0x{:X} targets 0x{:X}, which is not an instruction of the function",
+        _0, _1
+    )]
+    InvalidJumpTarget(UCell, UCell),
+    #[fail(display = "Switch at 0x{:X} got no case table at 0x{:X}", _0, _1)]
+    MissingCaseTable(UCell, UCell),
+}
+
+const CONDITIONAL_JUMPS: &[OpcodeType] = &[
+    OpJzer, OpJnz, OpJeq, OpJneq, OpJless, OpJleq, OpJgrtr, OpJgeq, OpJsless, OpJsleq, OpJsgrtr,
+    OpJsgeq,
+];
+
+// Control never reaches the next instruction after these
+const TERMINATORS: &[OpcodeType] = &[OpJump, OpJrel, OpSwitch, OpRet, OpRetn, OpHalt, OpJumpPri];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeKind {
+    /// Execution continues to the next instruction.
+    Fallthrough,
+    /// Unconditional `jump`.
+    Jump,
+    /// Conditional jump is taken.
+    Branch,
+    /// `switch` case with the value.
+    Case(UCell),
+    /// `switch` default, none of the cases matched.
+    Default,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Edge {
+    pub target: BlockId,
+    pub kind: EdgeKind,
+}
+
+/// Straight sequence of opcodes, entered only at the first one and left only after the last.
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    pub opcodes: Vec<Opcode>,
+    pub successors: Vec<Edge>,
+    pub predecessors: Vec<BlockId>,
+}
+
+impl BasicBlock {
+    pub fn address(&self) -> UCell {
+        self.opcodes[0].address()
+    }
+
+    pub fn terminator(&self) -> &Opcode {
+        &self.opcodes[self.opcodes.len() - 1]
+    }
+}
+
+/// Control flow graph of a single function.
+///
+/// Blocks are ordered by address, so the entry block is always the first one.
+/// Case tables are data, they are not a part of any block.
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    blocks: Vec<BasicBlock>,
+}
+
+fn jump_target(opcode: &Opcode, next_address: Option<UCell>) -> Option<UCell> {
+    let argument = opcode.argument()?;
+    match opcode.code() {
+        // Relative to the next instruction
+        OpJrel => next_address.map(|a| a.wrapping_add(argument)),
+        OpJump | OpSwitch => Some(argument),
+        code if CONDITIONAL_JUMPS.contains(&code) => Some(argument),
+        _ => None,
+    }
+}
+
+impl Cfg {
+    /// Builds graph from opcodes of the function, see `analysis::functions`.
+    pub fn from_opcodes(opcodes: &[Opcode]) -> Result<Cfg, CfgError> {
+        let case_tables: HashMap<UCell, &CaseTable> = opcodes
+            .iter()
+            .filter_map(|o| o.case_table().map(|t| (o.address(), t)))
+            .collect();
+        let next_addresses: Vec<Option<UCell>> = opcodes
+            .iter()
+            .skip(1)
+            .map(|o| Some(o.address()))
+            .chain(Some(None))
+            .collect();
+
+        let mut leaders = BTreeSet::new();
+        if let Some(opcode) = opcodes.iter().find(|o| o.code() != OpCasetbl) {
+            leaders.insert(opcode.address());
+        }
+
+        for (opcode, &next_address) in opcodes.iter().zip(next_addresses.iter()) {
+            let code = opcode.code();
+            let ends_block = TERMINATORS.contains(&code)
+                || CONDITIONAL_JUMPS.contains(&code)
+                || code == OpCasetbl;
+
+            if let Some(next_address) = next_address.filter(|_| ends_block) {
+                leaders.insert(next_address);
+            }
+
+            if code == OpSwitch {
+                let table_address = opcode.argument().unwrap_or_default();
+                let case_table = case_tables
+                    .get(&table_address)
+                    .ok_or_else(|| CfgError::MissingCaseTable(opcode.address(), table_address))?;
+                leaders.insert(case_table.default);
+                leaders.extend(case_table.cases.iter().map(|c| c.address));
+            } else if let Some(target) = jump_target(opcode, next_address) {
+                leaders.insert(target);
+            }
+        }
+
+        // Split into blocks
+        let mut blocks: Vec<BasicBlock> = vec![];
+        let mut block_ends: Vec<Option<UCell>> = vec![];
+        for (opcode, &next_address) in opcodes.iter().zip(next_addresses.iter()) {
+            if opcode.code() == OpCasetbl {
+                continue;
+            }
+
+            if leaders.contains(&opcode.address()) || blocks.is_empty() {
+                blocks.push(BasicBlock {
+                    opcodes: vec![],
+                    successors: vec![],
+                    predecessors: vec![],
+                });
+                block_ends.push(None);
+            }
+
+            let last = blocks.len() - 1;
+            blocks[last].opcodes.push(opcode.clone());
+            block_ends[last] = next_address;
+        }
+
+        let block_ids: BTreeMap<UCell, BlockId> = blocks
+            .iter()
+            .enumerate()
+            .map(|(id, b)| (b.address(), id))
+            .collect();
+        let block_id = |from: &Opcode, target: UCell| {
+            block_ids
+                .get(&target)
+                .cloned()
+                .ok_or_else(|| CfgError::InvalidJumpTarget(from.address(), target))
+        };
+
+        for id in 0..blocks.len() {
+            let terminator = blocks[id].terminator().clone();
+            let next_address = block_ends[id];
+            let code = terminator.code();
+            let mut successors = vec![];
+
+            if code == OpSwitch {
+                let case_table = case_tables[&terminator.argument().unwrap_or_default()];
+                for case in case_table.cases.iter() {
+                    successors.push(Edge {
+                        target: block_id(&terminator, case.address)?,
+                        kind: EdgeKind::Case(case.value),
+                    });
+                }
+                successors.push(Edge {
+                    target: block_id(&terminator, case_table.default)?,
+                    kind: EdgeKind::Default,
+                });
+            } else if let Some(target) = jump_target(&terminator, next_address) {
+                let kind = if CONDITIONAL_JUMPS.contains(&code) {
+                    EdgeKind::Branch
+                } else {
+                    EdgeKind::Jump
+                };
+                successors.push(Edge {
+                    target: block_id(&terminator, target)?,
+                    kind,
+                });
+            }
+
+            if !TERMINATORS.contains(&code) {
+                // Falling into case table or out of the function is not a real edge
+                if let Some(&target) = next_address.and_then(|a| block_ids.get(&a)) {
+                    successors.push(Edge {
+                        target,
+                        kind: EdgeKind::Fallthrough,
+                    });
+                }
+            }
+
+            for edge in successors.iter() {
+                let predecessors = &mut blocks[edge.target].predecessors;
+                if !predecessors.contains(&id) {
+                    predecessors.push(id);
+                }
+            }
+            blocks[id].successors = successors;
+        }
+
+        Ok(Cfg { blocks })
+    }
+
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    pub fn entry(&self) -> BlockId {
+        0
+    }
+
+    /// Block starting at the address.
+    pub fn block_at(&self, address: UCell) -> Option<BlockId> {
+        self.blocks.iter().position(|b| b.address() == address)
+    }
+
+    /// Blocks reachable from the entry, in reverse postorder.
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut postorder = vec![];
+        if self.blocks.is_empty() {
+            return postorder;
+        }
+
+        let mut visited = vec![false; self.blocks.len()];
+        // Block and index of its next successor to visit
+        let mut stack = vec![(self.entry(), 0)];
+        visited[self.entry()] = true;
+
+        while let Some((block, successor)) = stack.pop() {
+            match self.blocks[block].successors.get(successor) {
+                Some(edge) => {
+                    stack.push((block, successor + 1));
+                    if !visited[edge.target] {
+                        visited[edge.target] = true;
+                        stack.push((edge.target, 0));
+                    }
+                }
+                None => postorder.push(block),
+            }
+        }
+
+        postorder.reverse();
+        postorder
+    }
+
+    /// Dominator tree, computed with Cooper, Harvey and Kennedy iterative algorithm.
+    pub fn dominators(&self) -> Dominators {
+        let rpo = self.reverse_postorder();
+        let mut order = vec![None; self.blocks.len()];
+        for (i, &block) in rpo.iter().enumerate() {
+            order[block] = Some(i);
+        }
+
+        let mut idom: Vec<Option<BlockId>> = vec![None; self.blocks.len()];
+        if let Some(&entry) = rpo.first() {
+            idom[entry] = Some(entry);
+        }
+
+        let intersect = |idom: &[Option<BlockId>], mut a: BlockId, mut b: BlockId| {
+            while a != b {
+                while order[a] > order[b] {
+                    a = idom[a].expect("processed blocks have dominator");
+                }
+                while order[b] > order[a] {
+                    b = idom[b].expect("processed blocks have dominator");
+                }
+            }
+            a
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in rpo.iter().skip(1) {
+                let new_idom = self.blocks[block]
+                    .predecessors
+                    .iter()
+                    .filter(|&&p| idom[p].is_some())
+                    .fold(None, |new_idom, &p| match new_idom {
+                        None => Some(p),
+                        Some(d) => Some(intersect(&idom, p, d)),
+                    });
+
+                if new_idom != idom[block] {
+                    idom[block] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        // Entry is not dominated by anyone
+        if let Some(&entry) = rpo.first() {
+            idom[entry] = None;
+        }
+
+        Dominators { idom }
+    }
+
+    /// Loops formed by back edges, edges to the block dominating their source.
+    ///
+    /// Back edges to the same header are merged into a single loop.
+    pub fn natural_loops(&self) -> Vec<NaturalLoop> {
+        let dominators = self.dominators();
+        let mut loops: BTreeMap<BlockId, NaturalLoop> = BTreeMap::new();
+
+        for (latch, block) in self.blocks.iter().enumerate() {
+            for edge in block.successors.iter() {
+                let header = edge.target;
+                if !dominators.dominates(header, latch) {
+                    continue;
+                }
+
+                let natural_loop = loops.entry(header).or_insert_with(|| NaturalLoop {
+                    header,
+                    latches: vec![],
+                    body: vec![header].into_iter().collect(),
+                });
+                if !natural_loop.latches.contains(&latch) {
+                    natural_loop.latches.push(latch);
+                }
+
+                let mut worklist = vec![latch];
+                while let Some(block) = worklist.pop() {
+                    if natural_loop.body.insert(block) {
+                        worklist.extend(self.blocks[block].predecessors.iter().cloned());
+                    }
+                }
+            }
+        }
+
+        loops.into_values().collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dominators {
+    idom: Vec<Option<BlockId>>,
+}
+
+impl Dominators {
+    /// Closest strict dominator, none for the entry and unreachable blocks.
+    pub fn immediate_dominator(&self, block: BlockId) -> Option<BlockId> {
+        self.idom[block]
+    }
+
+    /// Whether every path from the entry to `block` goes through `dominator`.
+    pub fn dominates(&self, dominator: BlockId, block: BlockId) -> bool {
+        let mut current = Some(block);
+        while let Some(b) = current {
+            if b == dominator {
+                return true;
+            }
+            current = self.idom[b];
+        }
+
+        false
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NaturalLoop {
+    pub header: BlockId,
+    /// Blocks jumping back to the header.
+    pub latches: Vec<BlockId>,
+    /// All blocks of the loop, including header and latches.
+    pub body: BTreeSet<BlockId>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{Cfg, CfgError, Edge, EdgeKind};
+    use crate::analysis::functions;
+    use crate::util::tests::{decode, load_fixture};
+    use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+    use amxmodx_utils::amxx::File as AmxxFile;
+
+    #[test]
+    fn it_splits_if_else_into_blocks() {
+        // if (a) { a = 1 } else { a = 2 } return a
+        let opcodes = decode(&[
+            OpProc as u64,     // 0x00
+            OpLoadSPri as u64, // 0x04
+            12,
+            OpJzer as u64, // 0x0C
+            0x28,
+            OpConstPri as u64, // 0x14
+            1,
+            OpJump as u64, // 0x1C
+            0x30,
+            OpNop as u64,      // 0x24
+            OpConstPri as u64, // 0x28
+            2,
+            OpRetn as u64, // 0x30
+        ]);
+        let cfg = Cfg::from_opcodes(&opcodes).unwrap();
+        let blocks = cfg.blocks();
+
+        let addresses: Vec<u64> = blocks.iter().map(|b| b.address()).collect();
+        assert_eq!(addresses, [0x00, 0x14, 0x24, 0x28, 0x30]);
+        assert_eq!(
+            blocks[0].successors,
+            [
+                Edge {
+                    target: 3,
+                    kind: EdgeKind::Branch,
+                },
+                Edge {
+                    target: 1,
+                    kind: EdgeKind::Fallthrough,
+                },
+            ]
+        );
+        assert_eq!(blocks[1].successors[0].kind, EdgeKind::Jump);
+        // Unreachable nop falls through into else branch
+        assert_eq!(blocks[3].predecessors, [0, 2]);
+        assert_eq!(blocks[4].predecessors, [1, 3]);
+
+        let dominators = cfg.dominators();
+        assert_eq!(dominators.immediate_dominator(0), None);
+        assert_eq!(dominators.immediate_dominator(4), Some(0));
+        assert_eq!(dominators.immediate_dominator(2), None);
+        assert!(dominators.dominates(0, 3));
+        assert!(!dominators.dominates(1, 4));
+        assert!(cfg.natural_loops().is_empty());
+    }
+
+    #[test]
+    fn it_finds_natural_loops() {
+        // while (i < 10) { i++ }
+        let opcodes = decode(&[
+            OpProc as u64,     // 0x00
+            OpLoadSPri as u64, // 0x04
+            12,
+            OpConstAlt as u64, // 0x0C
+            10,
+            OpJsgeq as u64, // 0x14
+            0x2C,
+            OpIncS as u64, // 0x1C
+            12,
+            OpJump as u64, // 0x24
+            0x04,
+            OpRetn as u64, // 0x2C
+        ]);
+        let cfg = Cfg::from_opcodes(&opcodes).unwrap();
+        let loops = cfg.natural_loops();
+
+        assert_eq!(cfg.blocks().len(), 4);
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].header, 1);
+        assert_eq!(loops[0].latches, [2]);
+        assert_eq!(loops[0].body.iter().cloned().collect::<Vec<_>>(), [1, 2]);
+    }
+
+    #[test]
+    fn it_follows_case_table() {
+        // switch (a) { case 1: a = 5; default: a = 6; }
+        let opcodes = decode(&[
+            OpProc as u64,     // 0x00
+            OpLoadSPri as u64, // 0x04
+            12,
+            OpSwitch as u64, // 0x0C
+            0x34,
+            OpConstPri as u64, // 0x14
+            5,
+            OpJump as u64, // 0x1C
+            0x48,
+            OpConstPri as u64, // 0x24
+            6,
+            OpJump as u64, // 0x2C
+            0x48,
+            OpCasetbl as u64, // 0x34
+            1,
+            0x24,
+            1,
+            0x14,
+            OpRetn as u64, // 0x48
+        ]);
+        let cfg = Cfg::from_opcodes(&opcodes).unwrap();
+        let blocks = cfg.blocks();
+
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(
+            blocks[0].successors,
+            [
+                Edge {
+                    target: 1,
+                    kind: EdgeKind::Case(1),
+                },
+                Edge {
+                    target: 2,
+                    kind: EdgeKind::Default,
+                },
+            ]
+        );
+        // Case table is skipped, jumps land right after it
+        assert_eq!(blocks[3].address(), 0x48);
+        assert_eq!(blocks[3].predecessors, [1, 2]);
+    }
+
+    #[test]
+    fn it_fails_on_jump_outside_of_function() {
+        let opcodes = decode(&[OpProc as u64, OpJump as u64, 0x100, OpRetn as u64]);
+
+        match Cfg::from_opcodes(&opcodes) {
+            Err(CfgError::InvalidJumpTarget(0x04, 0x100)) => (),
+            _ => panic!("Error should be CfgError::InvalidJumpTarget"),
+        }
+    }
+
+    #[test]
+    fn it_builds_cfg_for_plugin_functions() {
+        let amxx_bin = load_fixture("shl_minimal_case.amxx");
+        let amxx_file = AmxxFile::try_from(&amxx_bin[..]).unwrap();
+        let amx_file = amxx_file
+            .sections()
+            .next()
+            .unwrap()
+            .unwrap()
+            .unpack()
+            .unwrap();
+        let opcodes: Vec<_> = amx_file.opcodes().unwrap().map(Result::unwrap).collect();
+
+        for function in functions(&opcodes) {
+            let cfg = Cfg::from_opcodes(function).unwrap();
+            // Both functions contain a single `if`
+            assert_eq!(cfg.blocks()[0].successors.len(), 2);
+            assert!(cfg.natural_loops().is_empty());
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+use amxmodx_utils::amx::UCell;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GlobalKind {
+    Variable,
+    Array {
+        /// Sizes of the dimensions in elements, rows of two-dimensional arrays go first.
+        dimensions: Vec<usize>,
+        /// Size of the element in bytes, arrays are indexed by cells unless packed.
+        element_size: usize,
+    },
+    /// String literal, one character per cell.
+    String(String),
+}
+
+/// Item of the DAT section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Global {
+    /// DAT relative address.
+    pub address: UCell,
+    /// Size in bytes.
+    pub size: usize,
+    /// Variables and arrays are named, literals are not.
+    pub name: Option<String>,
+    pub kind: GlobalKind,
+    /// Initial values of the cells.
+    pub cells: Vec<i64>,
+}
+
+/// Way the code refers to DAT address.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Reference {
+    /// Loaded or stored as a single cell.
+    Cell,
+    /// Base of the indexed array, with the highest index checked by `bounds`.
+    Array {
+        element_size: usize,
+        bounds: Option<usize>,
+    },
+    /// Plain constant, might be an address of the string or the array passed by reference.
+    Constant,
+}
+
+/// Globals, arrays and string literals the DAT section is split into.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataLayout {
+    pub globals: Vec<Global>,
+}
+
+impl DataLayout {
+    /// Splits DAT section at the addresses code refers to.
+    pub fn analyze(opcodes: &[Opcode], dat: &[u8], cellsize: usize) -> DataLayout {
+        let references = references(opcodes, dat.len(), cellsize);
+
+        // Arrays with known bounds swallow their elements accessed with constant index
+        let mut starts: BTreeMap<UCell, Reference> = BTreeMap::new();
+        let mut covered_until = 0;
+        for (&address, &reference) in references.iter() {
+            let is_string =
+                reference == Reference::Constant && read_string(dat, address, cellsize).is_some();
+            if address < covered_until || (reference == Reference::Constant && !is_string) {
+                continue;
+            }
+
+            if let Reference::Array {
+                element_size,
+                bounds: Some(bounds),
+            } = reference
+            {
+                covered_until = address + ((bounds + 1) * element_size) as UCell;
+            }
+            starts.insert(address, reference);
+        }
+
+        let mut globals = vec![];
+        let mut variables = 0;
+        let addresses: Vec<UCell> = starts.keys().cloned().collect();
+        for (i, &address) in addresses.iter().enumerate() {
+            let next = addresses.get(i + 1).cloned().unwrap_or(dat.len() as UCell);
+
+            let (size, kind) = match starts[&address] {
+                Reference::Cell => (cellsize, GlobalKind::Variable),
+                Reference::Array {
+                    element_size,
+                    bounds,
+                } => {
+                    let size = match bounds {
+                        Some(bounds) => (bounds + 1) * element_size,
+                        None => (next - address) as usize,
+                    };
+                    let dimensions = dimensions(dat, address, size, cellsize)
+                        .unwrap_or_else(|| vec![size / element_size]);
+                    (
+                        size,
+                        GlobalKind::Array {
+                            dimensions,
+                            element_size,
+                        },
+                    )
+                }
+                Reference::Constant => {
+                    let string = read_string(dat, address, cellsize).unwrap_or_default();
+                    let gap = (next - address) as usize;
+                    if string.is_empty() && gap > cellsize {
+                        // Zeroed array passed by reference, literal `""` takes a single cell
+                        let dimensions = vec![gap / cellsize];
+                        let element_size = cellsize;
+                        (
+                            gap,
+                            GlobalKind::Array {
+                                dimensions,
+                                element_size,
+                            },
+                        )
+                    } else {
+                        ((string.len() + 1) * cellsize, GlobalKind::String(string))
+                    }
+                }
+            };
+
+            let name = match kind {
+                GlobalKind::String(_) => None,
+                _ => {
+                    variables += 1;
+                    Some(format!("g_var{}", variables - 1))
+                }
+            };
+
+            let cells = (0..size / cellsize)
+                .filter_map(|i| read_cell(dat, address as usize + i * cellsize, cellsize))
+                .collect();
+
+            globals.push(Global {
+                address,
+                size,
+                name,
+                kind,
+                cells,
+            });
+        }
+
+        DataLayout { globals }
+    }
+
+    /// Turns the literal at the address into a named variable, a zeroed cell passed
+    /// by reference looks like `""`. Arrays take the whole literal, variables its first cell.
+    pub fn promote(&mut self, address: UCell, is_array: bool, cellsize: usize) -> Option<String> {
+        let variables = self.globals.iter().filter(|g| g.name.is_some()).count();
+        let global = self.globals.iter_mut().find(|g| g.address == address)?;
+        if global.name.is_some() {
+            return global.name.clone();
+        }
+
+        if is_array {
+            global.kind = GlobalKind::Array {
+                dimensions: vec![global.size / cellsize],
+                element_size: cellsize,
+            };
+        } else {
+            global.kind = GlobalKind::Variable;
+            global.size = cellsize;
+            global.cells.truncate(1);
+        }
+        global.name = Some(format!("g_var{}", variables));
+        global.name.clone()
+    }
+
+    /// Item the address points into.
+    pub fn global_at(&self, address: UCell) -> Option<&Global> {
+        self.globals
+            .iter()
+            .find(|g| address >= g.address && address < g.address + g.size as UCell)
+    }
+}
+
+/// Addresses referred to by the code, stronger references override weaker ones.
+fn references(opcodes: &[Opcode], dat_size: usize, cellsize: usize) -> BTreeMap<UCell, Reference> {
+    let mut references: BTreeMap<UCell, Reference> = BTreeMap::new();
+
+    for (i, opcode) in opcodes.iter().enumerate() {
+        let address = match opcode.argument() {
+            Some(a) if a < dat_size as UCell && a.is_multiple_of(cellsize as UCell) => a,
+            _ => continue,
+        };
+
+        let reference = match opcode.code() {
+            OpLoadPri | OpLoadAlt | OpLrefPri | OpLrefAlt | OpStorPri | OpStorAlt | OpSrefPri
+            | OpSrefAlt | OpInc | OpDec | OpZero | OpPush => Reference::Cell,
+            // Array base is kept in ALT when PRI holds index
+            OpConstAlt => match indexing(&opcodes[i + 1..], cellsize) {
+                Some(element_size) => {
+                    let bounds = match i.checked_sub(1).map(|p| &opcodes[p]) {
+                        // Bounds not fitting into the DAT section are not trusted
+                        Some(p) if p.code() == OpBounds => p.argument().and_then(|b| {
+                            let b = usize::try_from(b).ok()?;
+                            let size = b.checked_add(1)?.checked_mul(element_size)?;
+                            Some(b).filter(|_| size <= dat_size - address as usize)
+                        }),
+                        _ => None,
+                    };
+                    Reference::Array {
+                        element_size,
+                        bounds,
+                    }
+                }
+                None => Reference::Constant,
+            },
+            OpConstPri | OpPushC => Reference::Constant,
+            _ => continue,
+        };
+
+        let known = references.entry(address).or_insert(reference);
+        match (*known, reference) {
+            (_, Reference::Array { .. }) => *known = reference,
+            (Reference::Constant, Reference::Cell) => *known = reference,
+            _ => (),
+        }
+    }
+
+    references
+}
+
+/// Element size of the array indexed right after its base is loaded.
+fn indexing(rest: &[Opcode], cellsize: usize) -> Option<usize> {
+    let opcode = rest.first()?;
+    match opcode.code() {
+        OpLidx | OpIdxaddr => Some(cellsize),
+        OpLidxB | OpIdxaddrB => 1usize.checked_shl(u32::try_from(opcode.argument()?).ok()?),
+        _ => None,
+    }
+}
+
+/// Cell at the DAT address, sign extended.
+pub(crate) fn read_cell(dat: &[u8], address: usize, cellsize: usize) -> Option<i64> {
+    let bytes = dat.get(address..address + cellsize)?;
+    let mut cell = [0u8; 8];
+    cell[..cellsize].copy_from_slice(bytes);
+    let value = u64::from_le_bytes(cell);
+
+    // Sign extension of the narrower cells
+    let shift = 64 - 8 * cellsize as u32;
+    Some(((value << shift) as i64) >> shift)
+}
+
+/// Printable character or a byte of UTF-8 sequence.
+pub(crate) fn is_text(c: i64) -> bool {
+    matches!(c, 0x20..=0x7E | 0x09 | 0x0A | 0x0D | 0x80..=0xFF)
+}
+
+/// Zero terminated string of printable characters, one per cell.
+fn read_string(dat: &[u8], address: UCell, cellsize: usize) -> Option<String> {
+    let mut string = String::new();
+    let mut offset = address as usize;
+
+    loop {
+        let c = read_cell(dat, offset, cellsize)?;
+        match c {
+            0 => return Some(string),
+            c if is_text(c) => string.push(c as u8 as char),
+            _ => return None,
+        }
+        offset += cellsize;
+    }
+}
+
+/// Dimensions of two-dimensional array, recognized by its indirection vector.
+///
+/// Each cell of the vector holds offset from itself to the row, rows follow the vector.
+fn dimensions(dat: &[u8], address: UCell, size: usize, cellsize: usize) -> Option<Vec<usize>> {
+    let address = address as usize;
+    let first = read_cell(dat, address, cellsize)?;
+    if first <= 0 || !(first as usize).is_multiple_of(cellsize) || first as usize >= size {
+        return None;
+    }
+
+    let rows = first as usize / cellsize;
+    let row_size = (size - first as usize) / rows;
+    if row_size == 0 || !row_size.is_multiple_of(cellsize) {
+        return None;
+    }
+
+    for row in 0..rows {
+        let offset = read_cell(dat, address + row * cellsize, cellsize)?;
+        if offset != ((rows - row) * cellsize + row * row_size) as i64 {
+            return None;
+        }
+    }
+
+    Some(vec![rows, row_size / cellsize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DataLayout, GlobalKind};
+    use crate::util::tests::{cells, decode};
+    use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+
+    #[test]
+    fn it_splits_globals_strings_and_arrays() {
+        let mut dat = cells(&[5]);
+        dat.extend(cells(&[b'h' as u32, b'i' as u32, 0]));
+        dat.extend(cells(&[0; 4]));
+        dat.extend(cells(&[0; 3]));
+
+        let opcodes = decode(&[
+            OpLoadPri as u64, // 0x00
+            0,
+            OpPushC as u64, // 0x08
+            4,
+            OpLoadSPri as u64, // 0x10
+            12,
+            OpBounds as u64, // 0x18
+            3,
+            OpConstAlt as u64, // 0x20
+            16,
+            OpLidx as u64,  // 0x28
+            OpPushC as u64, // 0x2C
+            32,
+        ]);
+        let layout = DataLayout::analyze(&opcodes, &dat, 4);
+
+        let kinds: Vec<_> = layout.globals.iter().map(|g| g.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                GlobalKind::Variable,
+                GlobalKind::String("hi".to_owned()),
+                GlobalKind::Array {
+                    dimensions: vec![4],
+                    element_size: 4
+                },
+                GlobalKind::Array {
+                    dimensions: vec![3],
+                    element_size: 4
+                },
+            ]
+        );
+        assert_eq!(layout.globals[0].cells, vec![5]);
+        assert_eq!(layout.globals[2].name, Some("g_var1".to_owned()));
+        assert_eq!(layout.global_at(24).map(|g| g.address), Some(16));
+    }
+
+    #[test]
+    fn it_recognizes_two_dimensional_arrays() {
+        // Two rows of three cells after the indirection vector
+        let dat = cells(&[8, 16, 1, 2, 3, 4, 5, 6]);
+        let opcodes = decode(&[
+            OpConstAlt as u64, // 0x00
+            0,
+            OpIdxaddr as u64, // 0x08
+        ]);
+        let layout = DataLayout::analyze(&opcodes, &dat, 4);
+
+        assert_eq!(
+            layout.globals[0].kind,
+            GlobalKind::Array {
+                dimensions: vec![2, 3],
+                element_size: 4
+            }
+        );
+    }
+
+    #[test]
+    fn it_ignores_indexing_with_shift_out_of_cell() {
+        let dat = cells(&[1, 2]);
+        let opcodes = decode(&[
+            OpConstAlt as u64, // 0x00
+            0,
+            OpLidxB as u64, // 0x08
+            200,
+        ]);
+        let layout = DataLayout::analyze(&opcodes, &dat, 4);
+
+        assert!(layout
+            .globals
+            .iter()
+            .all(|g| !matches!(g.kind, GlobalKind::Array { .. })));
+    }
+
+    #[test]
+    fn it_ignores_bounds_out_of_dat() {
+        let dat = cells(&[1, 2, 3, 4]);
+        let opcodes = decode(&[
+            OpBounds as u64, // 0x00
+            0xFFFFFFFF,
+            OpConstAlt as u64, // 0x08
+            0,
+            OpLidx as u64, // 0x10
+        ]);
+        let layout = DataLayout::analyze(&opcodes, &dat, 4);
+
+        assert_eq!(
+            layout.globals[0].kind,
+            GlobalKind::Array {
+                dimensions: vec![4],
+                element_size: 4
+            }
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use serde::Serialize;
+
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+use amxmodx_utils::amx::tables::{Native, Public};
+use amxmodx_utils::amx::UCell;
+
+use super::functions;
+use super::strings::read_string;
+
+/// What the plugin claims to be, arguments of `register_plugin`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PluginInfo {
+    pub name: String,
+    pub version: String,
+    pub author: String,
+}
+
+/// Finds `register_plugin` call of `plugin_init` and reads its string arguments from DAT.
+///
+/// Only arguments pushed as constant addresses are resolved, which is what
+/// the compiler emits for literals and global arrays.
+pub fn plugin_info(
+    opcodes: &[Opcode],
+    publics: &[Public],
+    natives: &[Native],
+    dat: &[u8],
+    cellsize: usize,
+) -> Option<PluginInfo> {
+    let plugin_init = publics.iter().find(|p| p.name == "plugin_init")?;
+    let native = natives.iter().position(|n| n.name == "register_plugin")? as UCell;
+    let function = functions(opcodes)
+        .into_iter()
+        .find(|f| f[0].address() == plugin_init.address)?;
+
+    let call = function
+        .iter()
+        .position(|o| o.code() == OpSysreqC && o.argument() == Some(native))?;
+    let addresses = arguments(&function[..call], 3, cellsize)?;
+
+    let string = |address: UCell| {
+        let (_, bytes, _) = read_string(dat, address as usize, dat.len(), cellsize)?;
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    };
+
+    Some(PluginInfo {
+        name: string(addresses[0])?,
+        version: string(addresses[1])?,
+        author: string(addresses[2])?,
+    })
+}
+
+/// First `count` constant arguments of the native call, the first argument is pushed last.
+///
+/// More arguments might be pushed, such as defaults of the parameters added in later versions.
+fn arguments(before_call: &[Opcode], count: usize, cellsize: usize) -> Option<Vec<UCell>> {
+    let mut pushes = before_call.iter().rev().filter(|o| {
+        !matches!(
+            o.code(),
+            OpBreak | OpLine | OpFile | OpSymbol | OpSrange | OpSymtag | OpNop
+        )
+    });
+
+    // Size of the arguments in bytes goes right before the call
+    let size = pushes.next()?;
+    let pushed = match size.argument() {
+        Some(size) if size.is_multiple_of(cellsize as UCell) => size / cellsize as UCell,
+        _ => return None,
+    };
+    if size.code() != OpPushC || pushed < count as UCell {
+        return None;
+    }
+
+    let mut arguments = vec![];
+    while arguments.len() < count {
+        let push = pushes.next()?;
+        let argument = match push.code() {
+            OpPushC => push.argument()?,
+            OpPushPri => {
+                let constant = pushes.next()?;
+                match constant.code() {
+                    OpConstPri => constant.argument()?,
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        arguments.push(argument);
+    }
+    Some(arguments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plugin_info, PluginInfo};
+    use crate::asm::assemble;
+    use crate::util::tests::load_fixture;
+    use amxmodx_utils::amx::File as AmxPlugin;
+    use amxmodx_utils::amxx::File as AmxmodxFile;
+    use std::convert::TryFrom;
+
+    fn info(plugin: &AmxPlugin) -> Option<PluginInfo> {
+        let opcodes = plugin
+            .opcodes()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let publics = plugin
+            .publics()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let natives = plugin
+            .natives()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        plugin_info(
+            &opcodes,
+            &publics,
+            &natives,
+            plugin.dat_slice().unwrap(),
+            plugin.cellsize(),
+        )
+    }
+
+    #[test]
+    fn it_reads_register_plugin_arguments() {
+        let bin = load_fixture("simple.amxx183");
+        let file = AmxmodxFile::try_from(&bin[..]).unwrap();
+        let section = file.sections().next().unwrap().unwrap();
+
+        assert_eq!(
+            info(&section.unpack().unwrap()),
+            Some(PluginInfo {
+                name: "simple plugin".to_owned(),
+                version: "0.1".to_owned(),
+                author: "Fedcomp".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn it_resolves_constants_and_packed_strings() {
+        let image = assemble(
+            r#"
+            .native register_plugin
+            .public plugin_init
+            .data
+            maxp: .cell 32
+            name: .string "Plugin"
+            version: .packed "1.0"
+            author: .string "me"
+            .code
+                    halt 0
+            plugin_init:
+                    proc
+                    load.pri maxp
+                    push.c author
+                    const.pri version
+                    push.pri
+                    break
+                    push.c name
+                    push.c 0xC
+                    sysreq.c register_plugin
+                    stack 0x10
+                    zero.pri
+                    retn
+            "#,
+        )
+        .unwrap();
+        let plugin = AmxPlugin::try_from(&image[..]).unwrap();
+        assert_eq!(
+            info(&plugin),
+            Some(PluginInfo {
+                name: "Plugin".to_owned(),
+                version: "1.0".to_owned(),
+                author: "me".to_owned(),
+            })
+        );
+
+        // Since AMX Mod X 1.9 url and description are pushed as well
+        let image = assemble(
+            r#"
+            .native register_plugin
+            .public plugin_init
+            .data
+            name: .string "My Plugin"
+            version: .string "1.0"
+            author: .string "me"
+            url: .string ""
+            description: .string ""
+            .code
+                    halt 0
+            plugin_init:
+                    proc
+                    push.c description
+                    push.c url
+                    push.c author
+                    push.c version
+                    push.c name
+                    push.c 0x14
+                    sysreq.c register_plugin
+                    stack 0x18
+                    zero.pri
+                    retn
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            info(&AmxPlugin::try_from(&image[..]).unwrap()),
+            Some(PluginInfo {
+                name: "My Plugin".to_owned(),
+                version: "1.0".to_owned(),
+                author: "me".to_owned(),
+            })
+        );
+
+        let image =
+            assemble(".public plugin_init\n.code\nhalt 0\nplugin_init:\nproc\nretn").unwrap();
+        assert_eq!(info(&AmxPlugin::try_from(&image[..]).unwrap()), None);
+    }
+}
@@ -0,0 +1,59 @@
+pub mod cfg;
+pub mod data;
+pub mod metadata;
+pub mod strings;
+pub mod xref;
+
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::opcode_type::OpcodeType;
+
+pub use self::cfg::Cfg;
+pub use self::data::DataLayout;
+pub use self::xref::{CallGraph, Xrefs};
+
+/// Splits code into functions, each one starts with `proc` and lasts until the next one.
+///
+/// Opcodes before the first `proc` (`halt` at the start of COD) belong to no function.
+pub fn functions(opcodes: &[Opcode]) -> Vec<&[Opcode]> {
+    let starts: Vec<usize> = opcodes
+        .iter()
+        .enumerate()
+        .filter(|(_, o)| o.code() == OpcodeType::OpProc)
+        .map(|(i, _)| i)
+        .collect();
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).cloned().unwrap_or(opcodes.len());
+            &opcodes[start..end]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::functions;
+    use crate::util::tests::decode;
+    use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+
+    #[test]
+    fn it_splits_functions_at_proc() {
+        let opcodes = decode(&[
+            OpHalt as u64,
+            0,
+            OpProc as u64,
+            OpZeroPri as u64,
+            OpRetn as u64,
+            OpProc as u64,
+            OpRetn as u64,
+        ]);
+        let functions = functions(&opcodes);
+
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].len(), 3);
+        assert_eq!(functions[0][0].address(), 8);
+        assert_eq!(functions[1][0].address(), 20);
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+use amxmodx_utils::amx::UCell;
+use serde::Serialize;
+
+use super::data::{is_text, read_cell};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StringKind {
+    /// One character per cell, Pawn default.
+    Unpacked,
+    /// Characters packed into cells, the first one in the highest byte, `!"text"`.
+    Packed,
+}
+
+impl fmt::Display for StringKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StringKind::Unpacked => write!(f, "unpacked"),
+            StringKind::Packed => write!(f, "packed"),
+        }
+    }
+}
+
+/// Zero terminated string found in DAT section.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StringLiteral {
+    /// DAT relative address.
+    pub address: UCell,
+    pub kind: StringKind,
+    pub value: String,
+    /// Addresses of the instructions taking the string address.
+    pub references: Vec<UCell>,
+}
+
+/// Zero terminated string at the DAT address, ending before `end`.
+///
+/// Returns its kind, characters and size in bytes, empty strings are not told from zeroed cells.
+pub(crate) fn read_string(
+    dat: &[u8],
+    address: usize,
+    end: usize,
+    cellsize: usize,
+) -> Option<(StringKind, Vec<u8>, usize)> {
+    // Unpacked strings got the upper bytes of their cells zeroed
+    let mut bytes = vec![];
+    let mut offset = address;
+    while offset < end {
+        let c = read_cell(dat, offset, cellsize)?;
+        offset += cellsize;
+        if c == 0 && !bytes.is_empty() {
+            return Some((StringKind::Unpacked, bytes, offset - address));
+        }
+        if !is_text(c) {
+            break;
+        }
+        bytes.push(c as u8);
+    }
+
+    read_packed(dat, address, end, cellsize)
+        .filter(|(bytes, _)| !bytes.is_empty())
+        .map(|(bytes, size)| (StringKind::Packed, bytes, size))
+}
+
+/// Characters of the packed string at the address and its size in bytes.
+fn read_packed(
+    dat: &[u8],
+    address: usize,
+    end: usize,
+    cellsize: usize,
+) -> Option<(Vec<u8>, usize)> {
+    let mut bytes = vec![];
+    let mut offset = address;
+
+    while offset < end {
+        let cell = read_cell(dat, offset, cellsize)?;
+        offset += cellsize;
+        for shift in (0..cellsize).rev() {
+            let c = (cell >> (shift * 8)) & 0xFF;
+            if c == 0 {
+                return Some((bytes, offset - address));
+            }
+            if !is_text(c) {
+                return None;
+            }
+            bytes.push(c as u8);
+        }
+    }
+    None
+}
+
+/// Scans DAT section for unpacked and packed strings.
+///
+/// Strings start at the addresses code refers to or between them, never spanning across.
+/// Strings shorter than `min_length` are reported only if code refers to them,
+/// referenced zeroed cells are reported as empty strings.
+pub fn strings(
+    dat: &[u8],
+    cellsize: usize,
+    opcodes: &[Opcode],
+    min_length: usize,
+) -> Vec<StringLiteral> {
+    // Size of the arguments pushed right before the call is not an address
+    let constants: Vec<&Opcode> = opcodes
+        .iter()
+        .enumerate()
+        .filter(|&(i, o)| match o.code() {
+            OpConstPri | OpConstAlt => true,
+            OpPushC => !opcodes
+                .get(i + 1)
+                .is_some_and(|n| matches!(n.code(), OpCall | OpCallPri | OpSysreqC | OpSysreqPri)),
+            _ => false,
+        })
+        .map(|(_, o)| o)
+        .collect();
+    let referenced: BTreeSet<usize> = constants
+        .iter()
+        .filter_map(|o| o.argument())
+        .map(|a| a as usize)
+        .filter(|a| a.is_multiple_of(cellsize))
+        .collect();
+    let references = |address: UCell| -> Vec<UCell> {
+        constants
+            .iter()
+            .filter(|o| o.argument() == Some(address))
+            .map(|o| o.address())
+            .collect()
+    };
+
+    let mut found = vec![];
+    let mut address = 0;
+    while address + cellsize <= dat.len() {
+        let end = referenced
+            .range(address + 1..)
+            .next()
+            .cloned()
+            .unwrap_or(dat.len());
+
+        let string = read_string(dat, address, end, cellsize).or_else(|| {
+            let empty =
+                referenced.contains(&address) && read_cell(dat, address, cellsize) == Some(0);
+            Some((StringKind::Unpacked, vec![], cellsize)).filter(|_| empty)
+        });
+        match string {
+            Some((kind, bytes, size)) => {
+                let references = references(address as UCell);
+                if bytes.len() >= min_length || !references.is_empty() {
+                    found.push(StringLiteral {
+                        address: address as UCell,
+                        kind,
+                        value: String::from_utf8_lossy(&bytes).into_owned(),
+                        references,
+                    });
+                }
+                address += size;
+            }
+            None => address += cellsize,
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strings, StringKind};
+    use crate::util::tests::{cells, decode};
+    use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+
+    #[test]
+    fn it_finds_unpacked_and_packed_strings() {
+        let mut dat = cells(&[5]);
+        dat.extend(cells(&[b'h' as u32, b'i' as u32, 0]));
+        // !"packed" takes two cells and the terminator
+        dat.extend(cells(&[0x7061_636B, 0x6564_0000]));
+        dat.extend(cells(&[
+            b'u' as u32,
+            b's' as u32,
+            b'e' as u32,
+            b'r' as u32,
+            0,
+        ]));
+
+        let opcodes = decode(&[
+            OpPushC as u64, // 0x00
+            4,
+        ]);
+        let found = strings(&dat, 4, &opcodes, 4);
+
+        let summary: Vec<_> = found
+            .iter()
+            .map(|s| (s.address, s.kind, s.value.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (4, StringKind::Unpacked, "hi"),
+                (16, StringKind::Packed, "packed"),
+                (24, StringKind::Unpacked, "user"),
+            ]
+        );
+        assert_eq!(found[0].references, vec![0]);
+    }
+
+    #[test]
+    fn it_splits_strings_at_referenced_addresses() {
+        // Global of 32, a space, right before the literal
+        let mut dat = cells(&[32]);
+        dat.extend(
+            "Plugin\0"
+                .bytes()
+                .map(u32::from)
+                .flat_map(|c| c.to_le_bytes().to_vec()),
+        );
+
+        let opcodes = decode(&[
+            OpPushC as u64, // 0x00
+            4,
+        ]);
+        let found = strings(&dat, 4, &opcodes, 4);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].address, 4);
+        assert_eq!(found[0].value, "Plugin");
+        assert_eq!(found[0].references, vec![0]);
+    }
+
+    #[test]
+    fn it_lists_referenced_empty_strings() {
+        // `""` passed to a native, followed by a zeroed static
+        let dat = cells(&[0, 0]);
+        let opcodes = decode(&[
+            OpPushC as u64, // 0x00
+            0,
+            OpPushC as u64, // 0x08
+            4,
+            OpSysreqC as u64, // 0x10
+            0,
+        ]);
+        let found = strings(&dat, 4, &opcodes, 4);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].address, 0);
+        assert_eq!(found[0].value, "");
+        assert_eq!(found[0].references, vec![0]);
+    }
+}
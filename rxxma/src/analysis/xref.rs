@@ -0,0 +1,288 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use amxmodx_utils::amx::debug::DebugInfo;
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+use amxmodx_utils::amx::tables::{Native, Public};
+use amxmodx_utils::amx::UCell;
+
+use super::data::DataLayout;
+use super::functions;
+
+/// Instructions referring to functions, natives and DAT addresses.
+///
+/// Every index maps the referenced item to the addresses of the referencing instructions,
+/// in the order they appear in the code.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Xrefs {
+    /// Function address to its `call` sites.
+    pub calls: BTreeMap<UCell, Vec<UCell>>,
+    /// Native index to its `sysreq.c` sites.
+    ///
+    /// `sysreq.d` holds the address of the native instead of its index,
+    /// its operand is used as is.
+    pub natives: BTreeMap<UCell, Vec<UCell>>,
+    /// DAT address to the instructions loading, storing or taking it.
+    pub data: BTreeMap<UCell, Vec<UCell>>,
+}
+
+impl Xrefs {
+    pub fn analyze(opcodes: &[Opcode], layout: &DataLayout) -> Xrefs {
+        let mut xrefs = Xrefs::default();
+
+        for opcode in opcodes.iter() {
+            let argument = match opcode.argument() {
+                Some(a) => a,
+                None => continue,
+            };
+
+            let index = match opcode.code() {
+                OpCall => &mut xrefs.calls,
+                OpSysreqC | OpSysreqD => &mut xrefs.natives,
+                OpLoadPri | OpLoadAlt | OpLrefPri | OpLrefAlt | OpStorPri | OpStorAlt
+                | OpSrefPri | OpSrefAlt | OpInc | OpDec | OpZero | OpPush
+                    if layout.global_at(argument).is_some() =>
+                {
+                    &mut xrefs.data
+                }
+                // Constants are addresses only when they point at the start of DAT item
+                OpConstPri | OpConstAlt | OpPushC
+                    if layout
+                        .global_at(argument)
+                        .is_some_and(|g| g.address == argument) =>
+                {
+                    &mut xrefs.data
+                }
+                _ => continue,
+            };
+            index.entry(argument).or_default().push(opcode.address());
+        }
+
+        xrefs
+    }
+}
+
+/// Function or native called from a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Callee {
+    /// Address of the function.
+    Function(UCell),
+    /// Index of the native.
+    Native(UCell),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallGraphFunction {
+    /// Address of the `proc` opcode.
+    pub address: UCell,
+    /// Name of the public or the debug symbol, stocks of plugins without debug info got none.
+    pub name: Option<String>,
+    pub is_public: bool,
+    pub callees: BTreeSet<Callee>,
+}
+
+/// Calls between publics, stocks and natives of the plugin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallGraph {
+    /// Functions ordered by address.
+    pub functions: Vec<CallGraphFunction>,
+    /// Native names by index.
+    pub natives: Vec<String>,
+}
+
+impl CallGraph {
+    pub fn build(
+        opcodes: &[Opcode],
+        publics: &[Public],
+        natives: &[Native],
+        debug_info: Option<&DebugInfo>,
+    ) -> CallGraph {
+        let functions = functions(opcodes)
+            .into_iter()
+            .map(|function| {
+                let address = function[0].address();
+                let public = publics.iter().find(|p| p.address == address);
+                let name = public
+                    .map(|p| p.name.clone())
+                    .or_else(|| debug_info?.function(address).map(|s| s.name.clone()));
+
+                let callees = function
+                    .iter()
+                    .filter_map(|o| match (o.code(), o.argument()) {
+                        (OpCall, Some(a)) => Some(Callee::Function(a)),
+                        (OpSysreqC, Some(a)) | (OpSysreqD, Some(a)) => Some(Callee::Native(a)),
+                        _ => None,
+                    })
+                    .collect();
+
+                CallGraphFunction {
+                    address,
+                    name,
+                    is_public: public.is_some(),
+                    callees,
+                }
+            })
+            .collect();
+
+        CallGraph {
+            functions,
+            natives: natives.iter().map(|n| n.name.clone()).collect(),
+        }
+    }
+
+    pub fn function(&self, address: UCell) -> Option<&CallGraphFunction> {
+        self.functions.iter().find(|f| f.address == address)
+    }
+
+    /// Function by its public or debug name.
+    pub fn function_by_name(&self, name: &str) -> Option<&CallGraphFunction> {
+        self.functions
+            .iter()
+            .find(|f| f.name.as_ref().is_some_and(|n| n == name))
+    }
+
+    pub fn native(&self, name: &str) -> Option<Callee> {
+        let index = self.natives.iter().position(|n| n == name)?;
+        Some(Callee::Native(index as UCell))
+    }
+
+    /// Name of the callee, functions without name are named after their address.
+    pub fn name(&self, callee: Callee) -> String {
+        match callee {
+            Callee::Function(address) => self
+                .function(address)
+                .and_then(|f| f.name.clone())
+                .unwrap_or_else(|| format!("sub_{:x}", address)),
+            Callee::Native(index) => self
+                .natives
+                .get(index as usize)
+                .cloned()
+                .unwrap_or_else(|| format!("native_{}", index)),
+        }
+    }
+
+    /// Functions calling the callee directly.
+    pub fn callers(&self, callee: Callee) -> Vec<&CallGraphFunction> {
+        self.functions
+            .iter()
+            .filter(|f| f.callees.contains(&callee))
+            .collect()
+    }
+
+    /// Functions and natives reachable from the function, itself included when recursive.
+    pub fn reachable(&self, address: UCell) -> BTreeSet<Callee> {
+        let mut reachable = BTreeSet::new();
+        let mut stack = vec![address];
+
+        while let Some(address) = stack.pop() {
+            let function = match self.function(address) {
+                Some(f) => f,
+                None => continue,
+            };
+            for &callee in function.callees.iter() {
+                if reachable.insert(callee) {
+                    if let Callee::Function(a) = callee {
+                        stack.push(a);
+                    }
+                }
+            }
+        }
+
+        reachable
+    }
+
+    /// Publics which reach the callee through any chain of calls.
+    pub fn publics_reaching(&self, callee: Callee) -> Vec<&CallGraphFunction> {
+        self.functions
+            .iter()
+            .filter(|f| f.is_public && self.reachable(f.address).contains(&callee))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CallGraph, Callee, Xrefs};
+    use crate::analysis::data::DataLayout;
+    use crate::util::tests::decode;
+    use amxmodx_utils::amx::opcode::Opcode;
+    use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+    use amxmodx_utils::amx::tables::{Native, Public};
+
+    /// `plugin_init` calls stock at 0x28, which calls `server_cmd` and reads the global.
+    fn opcodes() -> Vec<Opcode> {
+        decode(&[
+            OpHalt as u64, // 0x00
+            0,
+            OpProc as u64,  // 0x08
+            OpPushC as u64, // 0x0C
+            8,
+            OpCall as u64, // 0x14
+            0x28,
+            OpZeroPri as u64, // 0x1C
+            OpRetn as u64,    // 0x20
+            OpRetn as u64,    // 0x24
+            OpProc as u64,    // 0x28
+            OpLoadPri as u64, // 0x2C
+            4,
+            OpConstPri as u64, // 0x34
+            4,
+            OpSysreqC as u64, // 0x3C
+            1,
+            OpRetn as u64, // 0x44
+        ])
+    }
+
+    fn layout() -> DataLayout {
+        DataLayout::analyze(&opcodes(), &[0u8; 8], 4)
+    }
+
+    #[test]
+    fn it_indexes_references() {
+        let xrefs = Xrefs::analyze(&opcodes(), &layout());
+
+        assert_eq!(xrefs.calls.get(&0x28), Some(&vec![0x14]));
+        assert_eq!(xrefs.natives.get(&1), Some(&vec![0x3C]));
+        assert_eq!(xrefs.data.get(&4), Some(&vec![0x2C, 0x34]));
+        // Pushed constant is past the end of DAT
+        assert_eq!(xrefs.data.get(&8), None);
+    }
+
+    #[test]
+    fn it_builds_call_graph() {
+        let publics = vec![Public {
+            name: "plugin_init".to_owned(),
+            address: 0x08,
+        }];
+        let natives = vec![
+            Native {
+                name: "get_cvar_num".to_owned(),
+                address: 0,
+            },
+            Native {
+                name: "server_cmd".to_owned(),
+                address: 0,
+            },
+        ];
+        let graph = CallGraph::build(&opcodes(), &publics, &natives, None);
+
+        let server_cmd = graph.native("server_cmd").unwrap();
+        let callers: Vec<_> = graph
+            .callers(server_cmd)
+            .iter()
+            .map(|f| f.address)
+            .collect();
+        assert_eq!(callers, vec![0x28]);
+        assert_eq!(graph.name(Callee::Function(0x28)), "sub_28");
+
+        let publics: Vec<_> = graph
+            .publics_reaching(server_cmd)
+            .iter()
+            .map(|f| f.name.clone().unwrap())
+            .collect();
+        assert_eq!(publics, vec!["plugin_init".to_owned()]);
+        assert!(graph
+            .publics_reaching(graph.native("get_cvar_num").unwrap())
+            .is_empty());
+    }
+}
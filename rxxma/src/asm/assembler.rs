@@ -0,0 +1,678 @@
+//! Assembler of the listing format, see `listing` module.
+
+use std::collections::HashMap;
+
+use failure::Fail;
+
+use super::encode_string;
+use crate::analysis::strings::StringKind;
+use amxmodx_utils::amx::builder::{BuildError, Builder};
+use amxmodx_utils::amx::opcode_type::{OpcodeType, OperandsSchema};
+use amxmodx_utils::amx::UCell;
+
+const DEFAULT_CELLSIZE: usize = 4;
+const DEFAULT_STACK_SIZE: u32 = 16384;
+
+#[derive(Debug, Fail)]
+pub enum AssembleError {
+    #[fail(display = "Line {}: {}", _0, _1)]
+    Syntax(usize, String),
+    #[fail(display = "Line {}: undefined label {}", _0, _1)]
+    UndefinedLabel(usize, String),
+    #[fail(display = "Line {}: label {} is already defined", _0, _1)]
+    DuplicateLabel(usize, String),
+    #[fail(display = "Line {}: value {} does not fit into a cell", _0, _1)]
+    Overflow(usize, String),
+    #[fail(display = "Failed to build image: {}", _0)]
+    Build(#[cause] BuildError),
+}
+
+fn syntax(line: usize, message: &str) -> AssembleError {
+    AssembleError::Syntax(line, message.to_owned())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    Header,
+    Data,
+    Code,
+}
+
+/// Operand as written, resolved once all labels are known.
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Number(i128),
+    Name(String),
+}
+
+/// Line of `.code` or `.data` section.
+#[derive(Debug)]
+enum Line {
+    Label(String),
+    Statement(String),
+}
+
+#[derive(Debug)]
+enum Statement {
+    Instruction(OpcodeType, Vec<Operand>),
+    /// `casetbl` default and its cases.
+    CaseTable(Operand, Vec<(Operand, Operand)>),
+    Payload(OpcodeType, Vec<u8>),
+    Cells(Vec<Operand>),
+    Bytes(Vec<u8>),
+}
+
+struct Assembler {
+    cellsize: usize,
+    stack_size: u32,
+    main: Option<(usize, Operand)>,
+    natives: Vec<String>,
+    libraries: Vec<String>,
+    publics: Vec<(usize, String, Operand)>,
+    pubvars: Vec<(usize, String, Operand)>,
+    tags: Vec<(usize, String, Operand)>,
+    /// Label values, COD or DAT relative addresses.
+    labels: HashMap<String, UCell>,
+    code: Vec<(usize, Statement)>,
+    data: Vec<(usize, Statement)>,
+}
+
+/// Splits the line at the first `;` outside of quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Splits operands at commas outside of quotes.
+fn split_operands(text: &str) -> Vec<&str> {
+    if text.trim().is_empty() {
+        return vec![];
+    }
+
+    let mut operands = vec![];
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                operands.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    operands.push(text[start..].trim());
+    operands
+}
+
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '@')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '@' || c == '.')
+}
+
+fn parse_operand(text: &str, line: usize) -> Result<Operand, AssembleError> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i128::from_str_radix(hex, 16).ok(),
+        None if digits.starts_with(|c: char| c.is_ascii_digit()) => digits.parse().ok(),
+        None if !negative && is_name(text) => return Ok(Operand::Name(text.to_owned())),
+        None => None,
+    };
+
+    match value {
+        Some(value) if negative => Ok(Operand::Number(-value)),
+        Some(value) => Ok(Operand::Number(value)),
+        None => Err(AssembleError::Syntax(
+            line,
+            format!("invalid operand {}", text),
+        )),
+    }
+}
+
+/// Bytes of the quoted string, escapes are the ones `escape` writes.
+fn parse_string(text: &str, line: usize) -> Result<Vec<u8>, AssembleError> {
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .filter(|_| text.len() >= 2)
+        .ok_or_else(|| syntax(line, "expected quoted string"))?;
+
+    let mut bytes = vec![];
+    let mut chars = inner.bytes();
+    while let Some(b) = chars.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        match chars.next() {
+            Some(b'n') => bytes.push(b'\n'),
+            Some(b'r') => bytes.push(b'\r'),
+            Some(b't') => bytes.push(b'\t'),
+            Some(b'"') => bytes.push(b'"'),
+            Some(b'\\') => bytes.push(b'\\'),
+            Some(b'x') => {
+                let hex: Vec<u8> = chars.by_ref().take(2).collect();
+                let value = std::str::from_utf8(&hex)
+                    .ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .filter(|_| hex.len() == 2)
+                    .ok_or_else(|| syntax(line, "invalid \\x escape"))?;
+                bytes.push(value);
+            }
+            _ => return Err(syntax(line, "invalid escape")),
+        }
+    }
+    Ok(bytes)
+}
+
+fn parse_operands(text: &str, line: usize) -> Result<Vec<Operand>, AssembleError> {
+    split_operands(text)
+        .into_iter()
+        .map(|o| parse_operand(o, line))
+        .collect()
+}
+
+/// Name and value of `.public`, `.pubvar` and `.tag` directives.
+fn named_value(
+    text: &str,
+    line: usize,
+    default: Option<Operand>,
+) -> Result<(String, Operand), AssembleError> {
+    let mut words = text.split_whitespace();
+    let name = words
+        .next()
+        .filter(|n| is_name(n))
+        .ok_or_else(|| syntax(line, "expected name"))?;
+    let value = match (words.next(), default) {
+        (Some(value), _) => parse_operand(value, line)?,
+        (None, Some(default)) => default,
+        (None, None) => return Err(syntax(line, "expected value")),
+    };
+    if words.next().is_some() {
+        return Err(syntax(line, "unexpected text after value"));
+    }
+    Ok((name.to_owned(), value))
+}
+
+fn single_name(text: &str, line: usize) -> Result<String, AssembleError> {
+    Some(text.trim())
+        .filter(|n| is_name(n))
+        .map(str::to_owned)
+        .ok_or_else(|| syntax(line, "expected name"))
+}
+
+impl Assembler {
+    fn new() -> Assembler {
+        Assembler {
+            cellsize: DEFAULT_CELLSIZE,
+            stack_size: DEFAULT_STACK_SIZE,
+            main: None,
+            natives: vec![],
+            libraries: vec![],
+            publics: vec![],
+            pubvars: vec![],
+            tags: vec![],
+            labels: HashMap::new(),
+            code: vec![],
+            data: vec![],
+        }
+    }
+
+    fn parse(&mut self, source: &str) -> Result<Vec<(usize, Section, Line)>, AssembleError> {
+        let mut section = Section::Header;
+        let mut lines = vec![];
+
+        for (i, line) in source.lines().enumerate() {
+            let line_no = i + 1;
+            let mut text = strip_comment(line).trim();
+
+            if let Some(colon) = text.find(':') {
+                let label = &text[..colon];
+                if is_name(label) {
+                    if section == Section::Header {
+                        return Err(syntax(line_no, "labels belong to .code or .data"));
+                    }
+                    lines.push((line_no, section, Line::Label(label.to_owned())));
+                    text = text[colon + 1..].trim();
+                }
+            }
+            if text.is_empty() {
+                continue;
+            }
+
+            let (word, rest) = match text.find(char::is_whitespace) {
+                Some(i) => (&text[..i], text[i..].trim()),
+                None => (text, ""),
+            };
+
+            match word {
+                ".data" => section = Section::Data,
+                ".code" => section = Section::Code,
+                _ if section != Section::Header => {
+                    lines.push((line_no, section, Line::Statement(text.to_owned())))
+                }
+                ".cellsize" => {
+                    self.cellsize = match rest {
+                        "4" => 4,
+                        "8" => 8,
+                        _ => return Err(syntax(line_no, "cellsize must be 4 or 8")),
+                    };
+                }
+                ".stack" => {
+                    self.stack_size = rest
+                        .parse()
+                        .map_err(|_| syntax(line_no, "invalid stack size"))?;
+                }
+                ".main" => self.main = Some((line_no, parse_operand(rest, line_no)?)),
+                ".native" => self.natives.push(single_name(rest, line_no)?),
+                ".library" => self.libraries.push(single_name(rest, line_no)?),
+                ".public" => {
+                    // Public is the label of the same name, unless told otherwise
+                    let default = rest
+                        .split_whitespace()
+                        .next()
+                        .map(|n| Operand::Name(n.into()));
+                    let (name, value) = named_value(rest, line_no, default)?;
+                    self.publics.push((line_no, name, value));
+                }
+                ".pubvar" => {
+                    let (name, value) = named_value(rest, line_no, None)?;
+                    self.pubvars.push((line_no, name, value));
+                }
+                ".tag" => {
+                    let (name, value) = named_value(rest, line_no, None)?;
+                    self.tags.push((line_no, name, value));
+                }
+                _ => {
+                    return Err(AssembleError::Syntax(
+                        line_no,
+                        format!("unexpected {} before .code or .data", word),
+                    ))
+                }
+            }
+        }
+
+        Ok(lines)
+    }
+
+    fn define(&mut self, label: &str, value: UCell, line: usize) -> Result<(), AssembleError> {
+        if self.labels.insert(label.to_owned(), value).is_some() {
+            return Err(AssembleError::DuplicateLabel(line, label.to_owned()));
+        }
+        Ok(())
+    }
+
+    fn statement(&self, text: &str, line: usize) -> Result<Statement, AssembleError> {
+        let (word, rest) = match text.find(char::is_whitespace) {
+            Some(i) => (&text[..i], text[i..].trim()),
+            None => (text, ""),
+        };
+
+        match word {
+            ".cell" => Ok(Statement::Cells(parse_operands(rest, line)?)),
+            ".zero" => {
+                let count: usize = rest
+                    .parse()
+                    .map_err(|_| syntax(line, "invalid count of zero cells"))?;
+                Ok(Statement::Bytes(vec![0; count * self.cellsize]))
+            }
+            ".string" => Ok(Statement::Bytes(encode_string(
+                StringKind::Unpacked,
+                &parse_string(rest, line)?,
+                self.cellsize,
+            ))),
+            ".packed" => Ok(Statement::Bytes(encode_string(
+                StringKind::Packed,
+                &parse_string(rest, line)?,
+                self.cellsize,
+            ))),
+            _ => {
+                let code = OpcodeType::from_name(word).ok_or_else(|| {
+                    AssembleError::Syntax(line, format!("unknown instruction {}", word))
+                })?;
+                match code.operands() {
+                    OperandsSchema::Cells(count) => {
+                        let operands = parse_operands(rest, line)?;
+                        if operands.len() != count {
+                            return Err(AssembleError::Syntax(
+                                line,
+                                format!("{} takes {} operands", code, count),
+                            ));
+                        }
+                        Ok(Statement::Instruction(code, operands))
+                    }
+                    OperandsSchema::CaseTable => {
+                        Ok(Statement::CaseTable(parse_operand(rest, line)?, vec![]))
+                    }
+                    OperandsSchema::Sized => {
+                        Ok(Statement::Payload(code, parse_string(rest, line)?))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Size of the statement in bytes.
+    fn size(&self, statement: &Statement) -> usize {
+        let cellsize = self.cellsize;
+        match *statement {
+            Statement::Instruction(_, ref operands) => (1 + operands.len()) * cellsize,
+            Statement::CaseTable(_, ref cases) => (3 + 2 * cases.len()) * cellsize,
+            Statement::Payload(_, ref payload) => 2 * cellsize + payload.len(),
+            Statement::Cells(ref cells) => cells.len() * cellsize,
+            Statement::Bytes(ref bytes) => bytes.len(),
+        }
+    }
+
+    /// Lays out statements of the section, defining their labels.
+    fn layout(
+        &mut self,
+        lines: &[(usize, Section, Line)],
+        section: Section,
+    ) -> Result<Vec<(usize, Statement)>, AssembleError> {
+        let mut statements: Vec<(usize, Statement)> = vec![];
+        let mut address = 0;
+
+        for &(line, _, ref content) in lines.iter().filter(|(_, s, _)| *s == section) {
+            let text = match *content {
+                Line::Label(ref label) => {
+                    self.define(label, address as UCell, line)?;
+                    continue;
+                }
+                Line::Statement(ref text) => text,
+            };
+
+            // Cases extend the case table right before them
+            if let Some(rest) = text.strip_prefix("case ") {
+                let case = match split_operands(rest).as_slice() {
+                    [value, target] => (parse_operand(value, line)?, parse_operand(target, line)?),
+                    _ => return Err(syntax(line, "case takes a value and a target")),
+                };
+                match statements.last_mut() {
+                    Some((_, Statement::CaseTable(_, ref mut cases)))
+                        if section == Section::Code =>
+                    {
+                        cases.push(case);
+                        address += 2 * self.cellsize;
+                        continue;
+                    }
+                    _ => return Err(syntax(line, "case must follow casetbl")),
+                }
+            }
+
+            let statement = self.statement(text, line)?;
+            match (section, &statement) {
+                (Section::Code, Statement::Cells(_)) | (Section::Code, Statement::Bytes(_)) => {
+                    return Err(syntax(line, "data directive in .code"))
+                }
+                (Section::Data, Statement::Cells(_)) | (Section::Data, Statement::Bytes(_)) => {}
+                (Section::Data, _) => return Err(syntax(line, "instruction in .data")),
+                _ => {}
+            }
+            address += self.size(&statement);
+            statements.push((line, statement));
+        }
+
+        Ok(statements)
+    }
+
+    fn resolve(&self, operand: &Operand, line: usize) -> Result<UCell, AssembleError> {
+        let bits = self.cellsize * 8;
+        match *operand {
+            Operand::Name(ref name) => self
+                .labels
+                .get(name)
+                .cloned()
+                .ok_or_else(|| AssembleError::UndefinedLabel(line, name.clone())),
+            Operand::Number(value) => {
+                let min = -(1i128 << (bits - 1));
+                let max = (1i128 << bits) - 1;
+                if value < min || value > max {
+                    return Err(AssembleError::Overflow(line, value.to_string()));
+                }
+                // Two's complement of the cell width
+                Ok((value & max) as UCell)
+            }
+        }
+    }
+
+    fn native(&self, operand: &Operand, line: usize) -> Result<UCell, AssembleError> {
+        match *operand {
+            Operand::Name(ref name) => self
+                .natives
+                .iter()
+                .position(|n| n == name)
+                .map(|i| i as UCell)
+                .ok_or_else(|| AssembleError::UndefinedLabel(line, name.clone())),
+            _ => self.resolve(operand, line),
+        }
+    }
+
+    fn emit(&self, statements: &[(usize, Statement)]) -> Result<Vec<u8>, AssembleError> {
+        let mut bin = vec![];
+        let cell = |bin: &mut Vec<u8>, value: UCell| {
+            bin.extend_from_slice(&value.to_le_bytes()[..self.cellsize]);
+        };
+
+        for &(line, ref statement) in statements.iter() {
+            match *statement {
+                Statement::Instruction(code, ref operands) => {
+                    cell(&mut bin, code as UCell);
+                    for operand in operands.iter() {
+                        let value = match code {
+                            OpcodeType::OpSysreqC => self.native(operand, line)?,
+                            _ => self.resolve(operand, line)?,
+                        };
+                        cell(&mut bin, value);
+                    }
+                }
+                Statement::CaseTable(ref default, ref cases) => {
+                    cell(&mut bin, OpcodeType::OpCasetbl as UCell);
+                    cell(&mut bin, cases.len() as UCell);
+                    cell(&mut bin, self.resolve(default, line)?);
+                    for (value, target) in cases.iter() {
+                        cell(&mut bin, self.resolve(value, line)?);
+                        cell(&mut bin, self.resolve(target, line)?);
+                    }
+                }
+                Statement::Payload(code, ref payload) => {
+                    cell(&mut bin, code as UCell);
+                    cell(&mut bin, payload.len() as UCell);
+                    bin.extend_from_slice(payload);
+                }
+                Statement::Cells(ref cells) => {
+                    for operand in cells.iter() {
+                        cell(&mut bin, self.resolve(operand, line)?);
+                    }
+                }
+                Statement::Bytes(ref bytes) => bin.extend_from_slice(bytes),
+            }
+        }
+
+        Ok(bin)
+    }
+
+    fn build(mut self, source: &str) -> Result<Vec<u8>, AssembleError> {
+        let lines = self.parse(source)?;
+        self.data = self.layout(&lines, Section::Data)?;
+        self.code = self.layout(&lines, Section::Code)?;
+
+        let cod = self.emit(&self.code)?;
+        let dat = self.emit(&self.data)?;
+
+        let mut builder = Builder::new(self.cellsize);
+        builder.code(&cod).data(&dat).stack_size(self.stack_size);
+        if let Some((line, ref main)) = self.main {
+            builder.main(self.resolve(main, line)?);
+        }
+        for (line, name, value) in self.publics.iter() {
+            builder.public(name, self.resolve(value, *line)?);
+        }
+        for name in self.natives.iter() {
+            builder.native(name);
+        }
+        for name in self.libraries.iter() {
+            builder.library(name);
+        }
+        for (line, name, value) in self.pubvars.iter() {
+            builder.pubvar(name, self.resolve(value, *line)?);
+        }
+        for (line, name, value) in self.tags.iter() {
+            builder.tag(name, self.resolve(value, *line)?);
+        }
+
+        builder.build().map_err(AssembleError::Build)
+    }
+}
+
+/// Assembles the listing into amx image.
+///
+/// Labels of `.code` are COD relative addresses and labels of `.data` are
+/// DAT relative ones, both may be used as operands. `sysreq.c` takes native names.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssembleError> {
+    Assembler::new().build(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assemble, AssembleError};
+    use crate::asm::listing;
+    use crate::util::tests::load_fixture;
+    use amxmodx_utils::amx::opcode::{Case, CaseTable, Operands};
+    use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+    use amxmodx_utils::amx::File as AmxPlugin;
+    use amxmodx_utils::amxx::File as AmxmodxFile;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn it_assembles_listing_back() {
+        for &filename in [
+            "simple.amxx183",
+            "shl_minimal_case.amxx",
+            "two_natives.amxx",
+        ]
+        .iter()
+        {
+            let bin = load_fixture(filename);
+            let file = AmxmodxFile::try_from(&bin[..]).unwrap();
+            for section in file.sections() {
+                let original = section.unwrap().unpack().unwrap();
+                let source = listing(&original, None).unwrap();
+
+                let image = assemble(&source).unwrap();
+                let plugin = AmxPlugin::try_from(&image[..]).unwrap();
+
+                assert_eq!(plugin.cod_slice().unwrap(), original.cod_slice().unwrap());
+                assert_eq!(plugin.dat_slice().unwrap(), original.dat_slice().unwrap());
+                let header = (plugin.header(), original.header());
+                assert_eq!(header.0.stp - header.0.hea, header.1.stp - header.1.hea);
+                assert_eq!(listing(&plugin, None).unwrap(), source, "{}", filename);
+            }
+        }
+    }
+
+    #[test]
+    fn it_resolves_labels_and_names() {
+        let source = r#"
+            .cellsize 4
+            .stack 1024
+            .native print
+            .public main_func main
+            .pubvar counter count
+
+            .data
+            count:  .cell -1
+            text:   .packed "hi\x21"
+                    .zero 2
+
+            .code
+                    halt 0
+            main:
+                    proc
+                    push.c text             ; "hi!"
+                    sysreq.c print
+                    load.pri count
+                    switch cases
+            cases:
+                    casetbl done
+                    case 1, main
+            done:
+                    retn
+        "#;
+
+        let image = assemble(source).unwrap();
+        let plugin = AmxPlugin::try_from(&image[..]).unwrap();
+
+        let publics: Vec<_> = plugin.publics().unwrap().map(Result::unwrap).collect();
+        assert_eq!(
+            (publics[0].name.as_str(), publics[0].address),
+            ("main_func", 8)
+        );
+        assert_eq!(
+            plugin.pubvars().unwrap().next().unwrap().unwrap().address,
+            0
+        );
+        assert_eq!(
+            plugin.dat_slice().unwrap(),
+            &[255, 255, 255, 255, 0, b'!', b'i', b'h', 0, 0, 0, 0, 0, 0, 0, 0][..]
+        );
+
+        let opcodes: Vec<_> = plugin.opcodes().unwrap().map(Result::unwrap).collect();
+        let codes: Vec<_> = opcodes.iter().map(|o| o.code()).collect();
+        assert_eq!(
+            codes,
+            [OpHalt, OpProc, OpPushC, OpSysreqC, OpLoadPri, OpSwitch, OpCasetbl, OpRetn]
+        );
+        assert_eq!(opcodes[2].argument(), Some(4));
+        assert_eq!(opcodes[5].argument(), Some(opcodes[6].address()));
+        assert_eq!(
+            *opcodes[6].operands(),
+            Operands::CaseTable(CaseTable {
+                default: opcodes[7].address(),
+                cases: vec![Case {
+                    value: 1,
+                    address: 8
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn it_reports_errors_with_lines() {
+        match assemble(".code\n  jump nowhere\n") {
+            Err(AssembleError::UndefinedLabel(2, ref label)) if label == "nowhere" => (),
+            e => panic!("Error should be UndefinedLabel, got {:?}", e),
+        }
+        match assemble(".code\n  proc 1\n") {
+            Err(AssembleError::Syntax(2, _)) => (),
+            e => panic!("Error should be Syntax, got {:?}", e),
+        }
+        match assemble(".code\n  const.pri 0x100000000\n") {
+            Err(AssembleError::Overflow(2, _)) => (),
+            e => panic!("Error should be Overflow, got {:?}", e),
+        }
+    }
+}
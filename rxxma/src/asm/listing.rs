@@ -0,0 +1,434 @@
+//! Listing which the assembler reads back.
+//!
+//! ```text
+//! .cellsize 4
+//! .stack 16384                    ; stack and heap size, stp - hea
+//! .native register_plugin
+//! .public plugin_init             ; label of the public, when it differs from the name
+//!
+//! .data
+//!         .string "simple plugin" ; unpacked, `.packed` for `!"packed"` strings
+//!         .cell 0x1, -0x1
+//!         .zero 64                ; cells
+//!
+//! .code
+//!         halt 0x0
+//!
+//! plugin_init:
+//!         proc
+//!         push.c 0x0              ; "simple plugin", `hits 1` is added with the coverage
+//!         sysreq.c register_plugin
+//!         jzer l_54
+//!         casetbl l_90            ; default, followed by the cases
+//!         case 0x1, l_80
+//! ```
+//!
+//! Comments start with `;`, code labels name functions and branch targets,
+//! DAT addresses stay numbers.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+use failure::Error;
+
+use super::{encode_string, escape};
+use crate::analysis::strings::{strings, StringKind, StringLiteral};
+use crate::analysis::xref::{CallGraph, Callee};
+use amxmodx_utils::amx::opcode::{Opcode, Operands};
+use amxmodx_utils::amx::opcode_type::OpcodeType::{self, *};
+use amxmodx_utils::amx::{File as AmxPlugin, UCell};
+
+/// Shorter unreferenced strings of DAT section are written as cells.
+const MIN_STRING_LENGTH: usize = 4;
+/// Runs of zero cells at least this long are written with `.zero`.
+const MIN_ZERO_RUN: usize = 8;
+const CELLS_PER_LINE: usize = 8;
+const COMMENT_COLUMN: usize = 40;
+const INDENT: &str = "        ";
+
+/// Opcodes taking code address of the branch target.
+const BRANCHES: &[OpcodeType] = &[
+    OpJump, OpJzer, OpJnz, OpJeq, OpJneq, OpJless, OpJleq, OpJgrtr, OpJgeq, OpJsless, OpJsleq,
+    OpJsgrtr, OpJsgeq, OpSwitch,
+];
+
+/// Cell as a signed hex number, e.g. `0x10` or `-0x4`.
+fn number(value: UCell, cellsize: usize) -> String {
+    let bits = cellsize * 8;
+    let mask = if bits == 64 {
+        UCell::MAX
+    } else {
+        (1 << bits) - 1
+    };
+    let value = value & mask;
+
+    if value >> (bits - 1) == 1 {
+        format!("-0x{:X}", value.wrapping_neg() & mask)
+    } else {
+        format!("0x{:X}", value)
+    }
+}
+
+fn branch_targets(opcode: &Opcode) -> Vec<UCell> {
+    match *opcode.operands() {
+        Operands::CaseTable(ref table) => {
+            let mut targets = vec![table.default];
+            targets.extend(table.cases.iter().map(|c| c.address));
+            targets
+        }
+        _ if BRANCHES.contains(&opcode.code()) => opcode.argument().into_iter().collect(),
+        _ => vec![],
+    }
+}
+
+/// Labels of the code addresses.
+struct Names {
+    cellsize: usize,
+    /// Function names, unique within the listing.
+    functions: HashMap<UCell, String>,
+    /// Branch targets inside of the functions.
+    labels: HashMap<UCell, String>,
+    natives: Vec<String>,
+}
+
+impl Names {
+    fn new(graph: &CallGraph, opcodes: &[Opcode], cellsize: usize) -> Names {
+        let mut used = HashSet::new();
+        let mut functions = HashMap::new();
+        for function in graph.functions.iter() {
+            let mut name = graph.name(Callee::Function(function.address));
+            // Static functions of different files may share the name
+            if !used.insert(name.clone()) {
+                name = format!("{}_{:x}", name, function.address);
+                used.insert(name.clone());
+            }
+            functions.insert(function.address, name);
+        }
+
+        let starts: HashSet<UCell> = opcodes.iter().map(Opcode::address).collect();
+        let labels = opcodes
+            .iter()
+            .flat_map(branch_targets)
+            .filter(|a| starts.contains(a) && !functions.contains_key(a))
+            .map(|a| (a, format!("l_{:x}", a)))
+            .collect();
+
+        Names {
+            cellsize,
+            functions,
+            labels,
+            natives: graph.natives.clone(),
+        }
+    }
+
+    /// Label of the code address, the number itself when nothing starts there.
+    fn target(&self, address: UCell) -> String {
+        self.functions
+            .get(&address)
+            .or_else(|| self.labels.get(&address))
+            .cloned()
+            .unwrap_or_else(|| number(address, self.cellsize))
+    }
+
+    /// Lines of the instruction, case tables take a line per case.
+    fn instruction(&self, opcode: &Opcode) -> Vec<String> {
+        let code = opcode.code();
+        match *opcode.operands() {
+            Operands::Cells(ref cells) if cells.is_empty() => vec![code.to_string()],
+            Operands::Cells(ref cells) => {
+                let operands: Vec<String> = cells
+                    .iter()
+                    .map(|&cell| match code {
+                        OpCall => self.target(cell),
+                        OpSysreqC => self
+                            .natives
+                            .get(cell as usize)
+                            .cloned()
+                            .unwrap_or_else(|| number(cell, self.cellsize)),
+                        _ if BRANCHES.contains(&code) => self.target(cell),
+                        _ => number(cell, self.cellsize),
+                    })
+                    .collect();
+                vec![format!("{} {}", code, operands.join(", "))]
+            }
+            Operands::CaseTable(ref table) => {
+                let mut lines = vec![format!("{} {}", code, self.target(table.default))];
+                lines.extend(table.cases.iter().map(|case| {
+                    format!(
+                        "case {}, {}",
+                        number(case.value, self.cellsize),
+                        self.target(case.address)
+                    )
+                }));
+                lines
+            }
+            Operands::Payload(ref payload) => vec![format!("{} \"{}\"", code, escape(payload))],
+        }
+    }
+}
+
+fn line_with_comment(out: &mut String, line: &str, comment: Option<&str>) -> Result<(), Error> {
+    match comment {
+        Some(comment) => writeln!(
+            out,
+            "{:<width$} ; {}",
+            line,
+            comment,
+            width = COMMENT_COLUMN - 1
+        )?,
+        None => writeln!(out, "{}", line)?,
+    }
+    Ok(())
+}
+
+/// Writes cells of DAT section, strings are written as text when they encode back the same.
+fn data(
+    out: &mut String,
+    dat: &[u8],
+    cellsize: usize,
+    found: &[StringLiteral],
+) -> Result<(), Error> {
+    let strings: BTreeMap<UCell, &StringLiteral> = found.iter().map(|s| (s.address, s)).collect();
+    let cells: Vec<UCell> = dat
+        .chunks_exact(cellsize)
+        .map(|c| {
+            let mut cell = [0u8; 8];
+            cell[..cellsize].copy_from_slice(c);
+            UCell::from_le_bytes(cell)
+        })
+        .collect();
+
+    // Start of the pending run of plain cells
+    let mut run = 0;
+    let mut index = 0;
+    while index <= cells.len() {
+        let address = (index * cellsize) as UCell;
+        let string = strings.get(&address).and_then(|s| {
+            let encoded = encode_string(s.kind, s.value.as_bytes(), cellsize);
+            let original = dat.get(index * cellsize..index * cellsize + encoded.len())?;
+            Some((s, encoded.len() / cellsize)).filter(|_| encoded == original)
+        });
+
+        if string.is_none() && index < cells.len() {
+            index += 1;
+            continue;
+        }
+
+        plain_cells(out, &cells[run..index], run, cellsize)?;
+        if let Some((string, size)) = string {
+            let directive = match string.kind {
+                StringKind::Unpacked => ".string",
+                StringKind::Packed => ".packed",
+            };
+            line_with_comment(
+                out,
+                &format!(
+                    "{}{} \"{}\"",
+                    INDENT,
+                    directive,
+                    escape(string.value.as_bytes())
+                ),
+                Some(&format!("0x{:08X}", address)),
+            )?;
+            index += size;
+            run = index;
+        } else {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn plain_cells(
+    out: &mut String,
+    cells: &[UCell],
+    first: usize,
+    cellsize: usize,
+) -> Result<(), Error> {
+    let mut index = 0;
+    while index < cells.len() {
+        let address = format!("0x{:08X}", (first + index) * cellsize);
+        let zeros = cells[index..].iter().take_while(|&&c| c == 0).count();
+
+        if zeros >= MIN_ZERO_RUN {
+            line_with_comment(out, &format!("{}.zero {}", INDENT, zeros), Some(&address))?;
+            index += zeros;
+            continue;
+        }
+
+        // Line lasts until the next long run of zeros
+        let mut end = index;
+        while end < cells.len() && end - index < CELLS_PER_LINE {
+            let zeros = cells[end..].iter().take_while(|&&c| c == 0).count();
+            if zeros >= MIN_ZERO_RUN {
+                break;
+            }
+            end += 1;
+        }
+        let values: Vec<String> = cells[index..end]
+            .iter()
+            .map(|&c| number(c, cellsize))
+            .collect();
+        line_with_comment(
+            out,
+            &format!("{}.cell {}", INDENT, values.join(", ")),
+            Some(&address),
+        )?;
+        index = end;
+    }
+
+    Ok(())
+}
+
+/// Listing of the plugin with labeled branch targets, named calls and natives.
+pub fn listing(plugin: &AmxPlugin, hits: Option<&BTreeMap<UCell, u64>>) -> Result<String, Error> {
+    let cellsize = plugin.cellsize();
+    let header = plugin.header();
+    let opcodes = plugin.opcodes()?.collect::<Result<Vec<_>, _>>()?;
+    let publics = plugin.publics()?.collect::<Result<Vec<_>, _>>()?;
+    let natives = plugin.natives()?.collect::<Result<Vec<_>, _>>()?;
+    let debug_info = plugin.debug_info().ok();
+    let dat = plugin.dat_slice()?;
+
+    let graph = CallGraph::build(&opcodes, &publics, &natives, debug_info.as_ref());
+    let names = Names::new(&graph, &opcodes, cellsize);
+    let found = strings(dat, cellsize, &opcodes, MIN_STRING_LENGTH);
+
+    let mut out = String::new();
+    writeln!(out, ".cellsize {}", cellsize)?;
+    writeln!(out, ".stack {}", header.stp - header.hea)?;
+    if header.cip != 0xFFFF_FFFF {
+        writeln!(out, ".main {}", names.target(UCell::from(header.cip)))?;
+    }
+    for native in natives.iter() {
+        writeln!(out, ".native {}", native.name)?;
+    }
+    for library in plugin.libraries()? {
+        writeln!(out, ".library {}", library?.name)?;
+    }
+    for public in publics.iter() {
+        match names.target(public.address) {
+            ref label if *label == public.name => writeln!(out, ".public {}", public.name)?,
+            label => writeln!(out, ".public {} {}", public.name, label)?,
+        }
+    }
+    for pubvar in plugin.pubvars()? {
+        let pubvar = pubvar?;
+        writeln!(
+            out,
+            ".pubvar {} {}",
+            pubvar.name,
+            number(pubvar.address, cellsize)
+        )?;
+    }
+    for tag in plugin.tags()? {
+        let tag = tag?;
+        writeln!(out, ".tag {} 0x{:X}", tag.name, tag.id)?;
+    }
+
+    writeln!(out, "\n.data")?;
+    data(&mut out, dat, cellsize, &found)?;
+
+    // Strings the instructions refer to
+    let annotations: HashMap<UCell, String> = found
+        .iter()
+        .flat_map(|s| {
+            s.references
+                .iter()
+                .map(move |&r| (r, format!("\"{}\"", escape(s.value.as_bytes()))))
+        })
+        .collect();
+
+    writeln!(out, "\n.code")?;
+    for opcode in opcodes.iter() {
+        let address = opcode.address();
+        if let Some(name) = names.functions.get(&address) {
+            writeln!(out, "\n{}:", name)?;
+        } else if let Some(label) = names.labels.get(&address) {
+            writeln!(out, "{}:", label)?;
+        }
+
+        // Coverage hits follow the string, never executed instructions got zero
+        let hits = hits.map(|h| format!("hits {}", h.get(&address).cloned().unwrap_or(0)));
+        let annotation = match (annotations.get(&address), hits) {
+            (Some(string), Some(hits)) => Some(format!("{}, {}", string, hits)),
+            (string, hits) => string.cloned().or(hits),
+        };
+        for (i, line) in names.instruction(opcode).iter().enumerate() {
+            let comment = annotation.as_deref().filter(|_| i == 0);
+            line_with_comment(&mut out, &format!("{}{}", INDENT, line), comment)?;
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{listing, number};
+    use crate::util::tests::load_fixture;
+    use amxmodx_utils::amxx::File as AmxmodxFile;
+    use std::collections::BTreeMap;
+    use std::convert::TryFrom;
+
+    fn fixture_listing(filename: &str) -> String {
+        let bin = load_fixture(filename);
+        let file = AmxmodxFile::try_from(&bin[..]).unwrap();
+        let section = file.sections().next().unwrap().unwrap();
+        listing(&section.unpack().unwrap(), None).unwrap()
+    }
+
+    #[test]
+    fn it_writes_signed_numbers() {
+        assert_eq!(number(0x10, 4), "0x10");
+        assert_eq!(number(0xFFFF_FFFC, 4), "-0x4");
+        assert_eq!(number(0xFFFF_FFFC, 8), "0xFFFFFFFC");
+        assert_eq!(number(u64::MAX, 8), "-0x1");
+    }
+
+    #[test]
+    fn it_names_natives_and_annotates_strings() {
+        let listing = fixture_listing("simple.amxx183");
+
+        assert!(listing.starts_with(".cellsize 4\n.stack 16384\n.native register_plugin\n"));
+        assert!(listing.contains(".public plugin_init\n"));
+        assert!(listing.contains(".string \"simple plugin\""));
+        assert!(listing.contains("\nplugin_init:\n        proc\n"));
+        assert!(listing.contains("sysreq.c register_plugin\n"));
+
+        let annotated = listing
+            .lines()
+            .find(|l| l.ends_with("; \"simple plugin\""))
+            .unwrap();
+        assert!(annotated.trim_start().starts_with("push.c 0x"));
+    }
+
+    #[test]
+    fn it_labels_branch_targets() {
+        let listing = fixture_listing("shl_minimal_case.amxx");
+
+        assert!(listing.contains("        jzer l_54\n"));
+        assert!(listing.contains("\nl_54:\n        stack 0x4\n"));
+        assert!(listing.contains("        load.s.pri -0x4\n"));
+        assert!(listing.contains("        sysreq.c nfunc\n"));
+    }
+
+    #[test]
+    fn it_writes_coverage_hits() {
+        let bin = load_fixture("simple.amxx183");
+        let file = AmxmodxFile::try_from(&bin[..]).unwrap();
+        let plugin = file.sections().next().unwrap().unwrap().unpack().unwrap();
+        let opcodes = plugin
+            .opcodes()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let hits: BTreeMap<_, _> = opcodes.iter().map(|o| (o.address(), 3)).take(1).collect();
+        let listing = listing(&plugin, Some(&hits)).unwrap();
+
+        assert!(listing.contains("; hits 3\n"));
+        assert!(listing.contains("; hits 0\n"));
+        assert!(listing.contains("; \"simple plugin\", hits 0\n"));
+    }
+}
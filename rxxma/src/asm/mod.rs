@@ -0,0 +1,71 @@
+//! Textual listing of the plugin and its assembler, see `listing` for the format.
+
+pub mod assembler;
+pub mod listing;
+
+pub use self::assembler::{assemble, AssembleError};
+pub use self::listing::listing;
+
+use crate::analysis::strings::StringKind;
+
+/// Escapes bytes for a quoted string of the listing.
+pub fn escape(bytes: &[u8]) -> String {
+    let mut escaped = String::new();
+    for &b in bytes {
+        match b {
+            b'"' => escaped.push_str("\\\""),
+            b'\\' => escaped.push_str("\\\\"),
+            b'\n' => escaped.push_str("\\n"),
+            b'\r' => escaped.push_str("\\r"),
+            b'\t' => escaped.push_str("\\t"),
+            0x20..=0x7E => escaped.push(b as char),
+            _ => escaped.push_str(&format!("\\x{:02X}", b)),
+        }
+    }
+    escaped
+}
+
+/// DAT bytes of the zero terminated string, packed strings are padded to whole cells.
+pub fn encode_string(kind: StringKind, bytes: &[u8], cellsize: usize) -> Vec<u8> {
+    let mut encoded = vec![];
+    match kind {
+        StringKind::Unpacked => {
+            for &b in bytes.iter().chain(&[0]) {
+                encoded.push(b);
+                encoded.extend(vec![0; cellsize - 1]);
+            }
+        }
+        StringKind::Packed => {
+            let mut chars = bytes.to_vec();
+            chars.push(0);
+            while !chars.len().is_multiple_of(cellsize) {
+                chars.push(0);
+            }
+            // The first character goes to the highest byte of the cell
+            for cell in chars.chunks(cellsize) {
+                encoded.extend(cell.iter().rev());
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_string, escape};
+    use crate::analysis::strings::StringKind;
+
+    #[test]
+    fn it_escapes_and_encodes_strings() {
+        assert_eq!(escape(b"say \"hi\"\n\x01"), "say \\\"hi\\\"\\n\\x01");
+
+        assert_eq!(
+            encode_string(StringKind::Unpacked, b"ab", 4),
+            [b'a', 0, 0, 0, b'b', 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            encode_string(StringKind::Packed, b"abcd", 4),
+            [b'd', b'c', b'b', b'a', 0, 0, 0, 0]
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use super::expression::{BinaryOperator, Expression, Register};
+use super::TreeElement;
+use super::TreeElementType;
+use amxmodx_utils::amx::opcode_type::OpcodeType::{self, *};
+use amxmodx_utils::amx::UCell;
+use serde::Serialize;
+
+/// Check made by a conditional jump on the PRI and ALT registers.
+#[derive(Debug, Clone, Serialize)]
+pub struct Condition {
+    /// Statements evaluated right before every check, e.g. loading operands.
+    pub setup: Vec<TreeElementType>,
+    /// Conditional jump opcode.
+    pub jump: OpcodeType,
+    /// Condition holds when the jump is not taken.
+    pub negated: bool,
+    /// Content of PRI register at the jump.
+    pub pri: Expression,
+    /// Content of ALT register at the jump.
+    pub alt: Expression,
+    /// Check made next, when this one holds, as in `a && b`.
+    pub and: Option<Box<Condition>>,
+}
+
+impl Condition {
+    /// Check of registers with unknown content.
+    pub fn new(jump: OpcodeType, negated: bool) -> Condition {
+        Condition {
+            setup: vec![],
+            jump,
+            negated,
+            pri: Expression::Register(Register::Pri),
+            alt: Expression::Register(Register::Alt),
+            and: None,
+        }
+    }
+
+    /// Check of this jump alone, without the following ones.
+    fn test(&self) -> Result<Expression, &'static str> {
+        let pri = self.pri.clone();
+        let compare = |operator| Ok(Expression::binary(operator, pri.clone(), self.alt.clone()));
+
+        let taken = match self.jump {
+            OpJzer => Ok(pri.clone().negate()),
+            OpJnz => Ok(pri.clone()),
+            OpJeq => compare(BinaryOperator::Eq),
+            OpJneq => compare(BinaryOperator::Neq),
+            OpJless | OpJsless => compare(BinaryOperator::Less),
+            OpJleq | OpJsleq => compare(BinaryOperator::Leq),
+            OpJgrtr | OpJsgrtr => compare(BinaryOperator::Greater),
+            OpJgeq | OpJsgeq => compare(BinaryOperator::Geq),
+            _ => Err("Condition got no conditional jump"),
+        }?;
+
+        Ok(if self.negated { taken.negate() } else { taken })
+    }
+
+    /// Whether the whole condition fits into an expression, without setup statements.
+    pub fn is_expression(&self) -> bool {
+        self.setup.is_empty() && self.and.as_ref().is_none_or(|c| c.is_expression())
+    }
+
+    pub fn expression(&self) -> Result<Expression, &'static str> {
+        let test = self.test()?;
+        match self.and {
+            Some(ref next) => Ok(Expression::binary(
+                BinaryOperator::LogicalAnd,
+                test,
+                next.expression()?,
+            )),
+            None => Ok(test),
+        }
+    }
+
+    /// Condition with its setup statements, separated by commas.
+    pub fn to_source(&self) -> Result<String, &'static str> {
+        let mut parts = inline(&self.setup)?;
+        let test = match self.and {
+            Some(ref next) if !next.is_expression() => {
+                format!("({}) && ({})", self.test()?, next.to_source()?)
+            }
+            _ => self.expression()?.to_string(),
+        };
+        parts.push(test);
+        Ok(parts.join(", "))
+    }
+}
+
+/// Renders statements on a single line, to fit them into a statement header.
+fn inline(elements: &[TreeElementType]) -> Result<Vec<String>, &'static str> {
+    elements
+        .iter()
+        .map(|e| {
+            let source = e.to_string(0)?;
+            Ok(source.trim().trim_end_matches(';').replace('\t', " "))
+        })
+        .collect()
+}
+
+fn body_to_string(elements: &[TreeElementType], ident: usize) -> Result<String, &'static str> {
+    let mut source = String::new();
+    for element in elements.iter() {
+        source.push_str(&element.to_string(ident)?);
+    }
+    Ok(source)
+}
+
+fn pad(ident: usize) -> String {
+    format!("{:>width$}", "", width = (2 * ident))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct If {
+    pub condition: Condition,
+    pub then_elements: Vec<TreeElementType>,
+    pub else_elements: Option<Vec<TreeElementType>>,
+}
+
+impl If {
+    fn to_string_after_else(&self, ident: usize) -> Result<String, &'static str> {
+        let mut source = format!("if ({}) {{\n", self.condition.to_source()?);
+        source.push_str(&body_to_string(&self.then_elements, ident + 1)?);
+
+        match self.else_elements.as_deref() {
+            None => {}
+            // Collapse `else { if }` into `else if`
+            Some([TreeElementType::IfType(ref nested)]) => {
+                source.push_str(&format!("{}}} else ", pad(ident)));
+                source.push_str(&nested.to_string_after_else(ident)?);
+                return Ok(source);
+            }
+            Some(else_elements) => {
+                source.push_str(&format!("{}}} else {{\n", pad(ident)));
+                source.push_str(&body_to_string(else_elements, ident + 1)?);
+            }
+        }
+
+        source.push_str(&format!("{}}}\n", pad(ident)));
+        Ok(source)
+    }
+}
+
+impl TreeElement for If {
+    fn to_string(&self, ident: usize) -> Result<String, &'static str> {
+        Ok(format!(
+            "{}{}",
+            pad(ident),
+            self.to_string_after_else(ident)?
+        ))
+    }
+}
+
+/// `while` loop, without condition it is an endless `while (true)`.
+#[derive(Debug, Clone, Serialize)]
+pub struct While {
+    pub condition: Option<Condition>,
+    pub body: Vec<TreeElementType>,
+}
+
+impl TreeElement for While {
+    fn to_string(&self, ident: usize) -> Result<String, &'static str> {
+        let condition = match self.condition {
+            Some(ref c) => c.to_source()?,
+            None => "true".to_owned(),
+        };
+
+        let mut source = format!("{}while ({}) {{\n", pad(ident), condition);
+        source.push_str(&body_to_string(&self.body, ident + 1)?);
+        source.push_str(&format!("{}}}\n", pad(ident)));
+        Ok(source)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DoWhile {
+    pub body: Vec<TreeElementType>,
+    pub condition: Condition,
+}
+
+impl TreeElement for DoWhile {
+    fn to_string(&self, ident: usize) -> Result<String, &'static str> {
+        let mut source = format!("{}do {{\n", pad(ident));
+        source.push_str(&body_to_string(&self.body, ident + 1)?);
+        source.push_str(&format!(
+            "{}}} while ({});\n",
+            pad(ident),
+            self.condition.to_source()?
+        ));
+        Ok(source)
+    }
+}
+
+/// `for` loop, initialization is left before the loop as a regular statement.
+#[derive(Debug, Clone, Serialize)]
+pub struct For {
+    pub condition: Option<Condition>,
+    pub increment: Vec<TreeElementType>,
+    pub body: Vec<TreeElementType>,
+}
+
+impl TreeElement for For {
+    fn to_string(&self, ident: usize) -> Result<String, &'static str> {
+        let condition = match self.condition {
+            Some(ref c) => c.to_source()?,
+            None => String::new(),
+        };
+        let increment = inline(&self.increment)?.join(", ");
+
+        let mut source = format!("{}for (; {}; {}) {{\n", pad(ident), condition, increment);
+        source.push_str(&body_to_string(&self.body, ident + 1)?);
+        source.push_str(&format!("{}}}\n", pad(ident)));
+        Ok(source)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Case {
+    /// Values sharing the same body, e.g. `case 1, 2:`.
+    pub values: Vec<UCell>,
+    pub body: Vec<TreeElementType>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Switch {
+    /// Switched value, content of PRI register.
+    pub value: Expression,
+    pub cases: Vec<Case>,
+    pub default: Option<Vec<TreeElementType>>,
+}
+
+impl TreeElement for Switch {
+    fn to_string(&self, ident: usize) -> Result<String, &'static str> {
+        let mut source = format!("{}switch ({}) {{\n", pad(ident), self.value);
+
+        for case in self.cases.iter() {
+            let values: Vec<String> = case.values.iter().map(|v| format!("{}", v)).collect();
+            source.push_str(&format!(
+                "{}case {}: {{\n",
+                pad(ident + 1),
+                values.join(", ")
+            ));
+            source.push_str(&body_to_string(&case.body, ident + 2)?);
+            source.push_str(&format!("{}}}\n", pad(ident + 1)));
+        }
+
+        if let Some(ref default) = self.default {
+            source.push_str(&format!("{}default: {{\n", pad(ident + 1)));
+            source.push_str(&body_to_string(default, ident + 2)?);
+            source.push_str(&format!("{}}}\n", pad(ident + 1)));
+        }
+
+        source.push_str(&format!("{}}}\n", pad(ident)));
+        Ok(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Case, Condition, If, Switch};
+    use crate::ast::expression::{Expression, Register};
+    use crate::ast::TreeElement;
+    use crate::ast::TreeElementType::*;
+    use amxmodx_utils::amx::opcode::{Opcode, Operands};
+    use amxmodx_utils::amx::opcode_type::OpcodeType;
+
+    fn condition(jump: OpcodeType, negated: bool) -> Condition {
+        Condition::new(jump, negated)
+    }
+
+    #[test]
+    fn it_renders_negated_conditions() {
+        assert_eq!(
+            condition(OpcodeType::OpJsgeq, true).to_source().unwrap(),
+            "pri < alt"
+        );
+        assert_eq!(
+            condition(OpcodeType::OpJzer, false).to_source().unwrap(),
+            "!pri"
+        );
+
+        let with_setup = Condition {
+            setup: vec![OpcodeType(Opcode::new(
+                0,
+                OpcodeType::OpLoadSPri,
+                Operands::Cells(vec![0xC]),
+            ))],
+            ..condition(OpcodeType::OpJzer, true)
+        };
+        assert_eq!(with_setup.to_source().unwrap(), "#emit load.s.pri 0xC, pri");
+    }
+
+    #[test]
+    fn it_renders_else_if() {
+        let nested = If {
+            condition: condition(OpcodeType::OpJeq, true),
+            then_elements: vec![BreakType],
+            else_elements: Some(vec![ContinueType]),
+        };
+        let statement = If {
+            condition: condition(OpcodeType::OpJzer, true),
+            then_elements: vec![ReturnType(None)],
+            else_elements: Some(vec![IfType(nested)]),
+        };
+
+        assert_eq!(
+            statement.to_string(1).unwrap(),
+            "  if (pri) {\n    return;\n  } else if (pri != alt) {\n    break;\n  } else {\n    continue;\n  }\n"
+        );
+    }
+
+    #[test]
+    fn it_renders_switch_cases() {
+        let statement = Switch {
+            value: Expression::Register(Register::Pri),
+            cases: vec![Case {
+                values: vec![1, 2],
+                body: vec![BreakType],
+            }],
+            default: Some(vec![]),
+        };
+
+        assert_eq!(
+            statement.to_string(0).unwrap(),
+            "switch (pri) {\n  case 1, 2: {\n    break;\n  }\n  default: {\n  }\n}\n"
+        );
+    }
+}
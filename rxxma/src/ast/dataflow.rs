@@ -0,0 +1,1123 @@
+use log::trace;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use super::control_flow::{Condition, DoWhile, For, If, Switch, While};
+use super::expression::{BinaryOperator, Expression, Register, UnaryOperator};
+use super::function_call::FunctionCall;
+use super::variable::Declaration;
+use super::TreeElementType;
+use super::TreeElementType::*;
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+use amxmodx_utils::amx::UCell;
+
+/// Plugin wide information needed to name calls and constants.
+pub struct Context<'a> {
+    pub cellsize: usize,
+    /// Native names by their index in the natives table.
+    pub natives: &'a [String],
+    /// Function names by their address.
+    pub functions: &'a HashMap<UCell, String>,
+    /// Variable or string literal the constant points to in DAT section.
+    pub dat_constant: &'a dyn Fn(UCell) -> Option<Expression>,
+}
+
+/// Rebuilds expressions from opcodes of the function body and statements nested into it.
+///
+/// Opcodes are executed symbolically, tracking content of PRI, ALT and the stack.
+/// Parts which do not fit into an expression keep their opcodes.
+pub fn build_expressions(elements: &[TreeElementType], context: &Context) -> Vec<TreeElementType> {
+    // Stack pointer is at the frame after `proc`
+    let mut builder = Builder::new(context, Some(0));
+    builder.build(elements);
+    builder.settle();
+
+    let value = builder.return_value();
+    let mut elements = builder.finish();
+    elements.extend(value.map(|v| ReturnType(Some(v))));
+    elements
+}
+
+/// Cell pushed onto the stack.
+#[derive(Debug, Clone)]
+enum Entry {
+    /// Pushed by `push.c`, might be an address of the string.
+    Constant(UCell),
+    Value(Expression),
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    pri: Expression,
+    alt: Expression,
+    /// Register holds value with side effects, which is not used yet.
+    pri_pending: bool,
+    alt_pending: bool,
+    /// Register got its value from opcodes of the chunk and nothing has read it yet,
+    /// opcodes left as is might do, e.g. `#emit` after the load.
+    pri_loaded: bool,
+    alt_loaded: bool,
+    /// Function arguments and other temporaries, locals are not tracked here.
+    stack: Vec<Entry>,
+    /// Values stored into temporary heap cells, by address of the `heap` opcode.
+    heap: HashMap<UCell, Expression>,
+    /// Arguments of the native call are released by the following `stack`.
+    cleanup: bool,
+}
+
+impl State {
+    fn new() -> State {
+        State {
+            pri: Expression::Register(Register::Pri),
+            alt: Expression::Register(Register::Alt),
+            pri_pending: false,
+            alt_pending: false,
+            pri_loaded: false,
+            alt_loaded: false,
+            stack: vec![],
+            heap: HashMap::new(),
+            cleanup: false,
+        }
+    }
+}
+
+struct Builder<'a> {
+    context: &'a Context<'a>,
+    state: State,
+    /// Offset of the stack top from the frame, unknown after `sctrl` and alike.
+    sp: Option<i64>,
+    /// Argument count pushed by the previous opcode, if it was `push.c`.
+    last_push_c: Option<UCell>,
+    output: Vec<TreeElementType>,
+    /// Source elements since the last point where the state got settled.
+    chunk: Vec<TreeElementType>,
+    /// Output length at the start of the chunk.
+    chunk_start: usize,
+}
+
+impl<'a> Builder<'a> {
+    fn new(context: &'a Context<'a>, sp: Option<i64>) -> Builder<'a> {
+        Builder {
+            context,
+            state: State::new(),
+            sp,
+            last_push_c: None,
+            output: vec![],
+            chunk: vec![],
+            chunk_start: 0,
+        }
+    }
+
+    /// Builder for the statement list nested into the current statement.
+    fn nested(&self) -> Builder<'a> {
+        Builder::new(self.context, self.sp)
+    }
+
+    fn build(&mut self, elements: &[TreeElementType]) {
+        for (position, element) in elements.iter().enumerate() {
+            match *element {
+                OpcodeType(ref opcode) => {
+                    self.chunk.push(element.clone());
+                    let result = self.opcode(opcode, &elements[position + 1..]);
+                    self.update_sp(opcode);
+
+                    match result {
+                        Err(e) => {
+                            trace!("Opcode at 0x{:X} is left as is: {}", opcode.address(), e);
+                            self.flush();
+                        }
+                        Ok(()) if self.is_settled() => self.commit(),
+                        Ok(()) => (),
+                    }
+                }
+                IfType(ref statement) => self.statement_if(element, statement),
+                WhileType(ref statement) => self.statement_while(statement),
+                DoWhileType(ref statement) => self.statement_do_while(statement),
+                ForType(ref statement) => self.statement_for(statement),
+                SwitchType(ref statement) => self.statement_switch(statement),
+                ReturnType(_) => {
+                    self.settle();
+                    let value = self.return_value();
+                    self.emit_pending();
+                    self.push_statement(ReturnType(value));
+                }
+                _ => {
+                    self.settle();
+                    self.emit_pending();
+                    self.push_statement(element.clone());
+                }
+            }
+        }
+    }
+
+    /// Statements built so far, unused values with side effects become statements.
+    fn finish(mut self) -> Vec<TreeElementType> {
+        self.settle();
+        self.emit_pending();
+        self.output
+    }
+
+    fn is_settled(&self) -> bool {
+        self.state.stack.is_empty()
+            && !self.state.cleanup
+            && !self.state.pri_pending
+            && !self.state.alt_pending
+            && !self.state.pri_loaded
+            && !self.state.alt_loaded
+    }
+
+    fn commit(&mut self) {
+        self.chunk.clear();
+        self.chunk_start = self.output.len();
+    }
+
+    /// Replaces statements built from the chunk with its source elements.
+    fn flush(&mut self) {
+        self.output.truncate(self.chunk_start);
+        self.output.append(&mut self.chunk);
+        self.chunk_start = self.output.len();
+        self.state = State::new();
+    }
+
+    /// Makes sure nothing is left on the stack before the statement.
+    fn settle(&mut self) {
+        if !self.state.stack.is_empty() || self.state.cleanup {
+            trace!("Stack is not balanced before the statement");
+            self.flush();
+        }
+    }
+
+    fn emit_pending(&mut self) {
+        if self.state.pri_pending {
+            let value = self.take(Register::Pri);
+            self.output.push(ExpressionType(value));
+        }
+        if self.state.alt_pending {
+            let value = self.take(Register::Alt);
+            self.output.push(ExpressionType(value));
+        }
+    }
+
+    /// Adds statement which does not depend on registers, e.g. `if`.
+    fn push_statement(&mut self, statement: TreeElementType) {
+        self.output.push(statement);
+        self.state = State::new();
+        self.commit();
+    }
+
+    fn get(&self, register: Register) -> &Expression {
+        match register {
+            Register::Pri => &self.state.pri,
+            Register::Alt => &self.state.alt,
+        }
+    }
+
+    /// Reads the register, its side effects are considered used.
+    fn take(&mut self, register: Register) -> Expression {
+        match register {
+            Register::Pri => {
+                self.state.pri_pending = false;
+                self.state.pri_loaded = false;
+            }
+            Register::Alt => {
+                self.state.alt_pending = false;
+                self.state.alt_loaded = false;
+            }
+        }
+        self.get(register).clone()
+    }
+
+    /// Overwrites the register, unused side effects of its previous value become a statement.
+    fn set(&mut self, register: Register, value: Expression) {
+        let pending = value.has_side_effects();
+        let loaded = !matches!(value, Expression::Register(_));
+        let state = &mut self.state;
+        let (current, current_pending) = match register {
+            Register::Pri => {
+                state.pri_loaded = loaded;
+                (&mut state.pri, &mut state.pri_pending)
+            }
+            Register::Alt => {
+                state.alt_loaded = loaded;
+                (&mut state.alt, &mut state.alt_pending)
+            }
+        };
+
+        let previous = std::mem::replace(current, value);
+        if std::mem::replace(current_pending, pending) {
+            self.output.push(ExpressionType(previous));
+        }
+    }
+
+    /// Adds statement modifying the variable, registers refer to its new value.
+    fn modify(
+        &mut self,
+        target: &Expression,
+        statement: TreeElementType,
+    ) -> Result<(), &'static str> {
+        let is_stale = |e: &Expression| e != target && e.reads(target);
+        let is_stack_stale = self.state.stack.iter().any(|entry| match *entry {
+            Entry::Value(ref e) => is_stale(e),
+            Entry::Constant(_) => false,
+        });
+        if is_stale(&self.state.pri) || is_stale(&self.state.alt) || is_stack_stale {
+            return Err("Variable is changed while its old value is in use");
+        }
+
+        self.output.push(statement);
+        Ok(())
+    }
+
+    fn store(&mut self, target: Expression, register: Register) -> Result<(), &'static str> {
+        let value = self.take(register);
+        let statement = Expression::Assign(Box::new(target.clone()), Box::new(value));
+        self.set(register, target.clone());
+        // Register is reproduced by the assignment
+        self.take(register);
+        self.modify(&target, ExpressionType(statement))
+    }
+
+    fn binary(&mut self, operator: BinaryOperator, lhs: Register, rhs: Register) {
+        let lhs = self.take(lhs);
+        let rhs = self.take(rhs);
+        self.set(Register::Pri, Expression::binary(operator, lhs, rhs));
+    }
+
+    fn binary_constant(&mut self, operator: BinaryOperator, register: Register, value: i64) {
+        let lhs = self.take(register);
+        self.set(
+            register,
+            Expression::binary(operator, lhs, Expression::Cell(value)),
+        );
+    }
+
+    fn signed(&self, value: UCell) -> i64 {
+        match self.context.cellsize {
+            2 => i64::from(value as u16 as i16),
+            4 => i64::from(value as u32 as i32),
+            _ => value as i64,
+        }
+    }
+
+    fn cells(&self, bytes: UCell) -> i64 {
+        self.signed(bytes) / self.context.cellsize as i64
+    }
+
+    fn constant(&self, value: UCell) -> Expression {
+        (self.context.dat_constant)(value).unwrap_or_else(|| Expression::Cell(self.signed(value)))
+    }
+
+    fn pop(&mut self) -> Result<Expression, &'static str> {
+        match self.state.stack.pop() {
+            Some(Entry::Value(e)) => Ok(e),
+            Some(Entry::Constant(v)) => Ok(Expression::Cell(self.signed(v))),
+            None => Err("Stack underflow"),
+        }
+    }
+
+    /// Pops arguments of the call, pushed in reverse order after their count.
+    fn pop_arguments(&mut self) -> Result<Vec<Expression>, &'static str> {
+        let count = match self.state.stack.pop() {
+            Some(Entry::Constant(v)) => self.cells(v),
+            _ => return Err("Call got no argument count"),
+        };
+        if count < 0 || count as usize > self.state.stack.len() {
+            return Err("Call arguments are out of the statement");
+        }
+
+        let mut args = vec![];
+        for _ in 0..count {
+            let arg = match self.state.stack.pop() {
+                Some(Entry::Constant(v)) => self.constant(v),
+                Some(Entry::Value(Expression::Address(e))) => match *e {
+                    // Value passed by reference through the temporary cell
+                    Expression::Heap(address) => match self.state.heap.get(&address) {
+                        Some(value) => value.clone(),
+                        None => Expression::Address(e),
+                    },
+                    e => Expression::Address(Box::new(e)),
+                },
+                Some(Entry::Value(e)) => e,
+                None => unreachable!("count is checked above"),
+            };
+            args.push(arg);
+        }
+        Ok(args)
+    }
+
+    fn push(
+        &mut self,
+        opcode: &Opcode,
+        entry: Entry,
+        rest: &[TreeElementType],
+    ) -> Result<(), &'static str> {
+        if self.is_argument(opcode, rest) {
+            self.state.stack.push(entry);
+            return Ok(());
+        }
+
+        // Cell outliving the statement is a local variable
+        let cell = self.context.cellsize as i64;
+        let offset = self.sp.ok_or("Stack pointer is unknown")? - cell;
+        let value = match entry {
+            // Locals are zeroed anyway
+            Entry::Constant(0) => None,
+            Entry::Constant(v) => Some(Expression::Cell(self.signed(v))),
+            Entry::Value(e) => Some(e),
+        };
+        self.declare(offset, vec![], value)
+    }
+
+    fn declare(
+        &mut self,
+        offset: i64,
+        dimensions: Vec<usize>,
+        value: Option<Expression>,
+    ) -> Result<(), &'static str> {
+        let variable = Expression::Local(offset);
+        let declaration = Declaration {
+            variable: variable.clone(),
+            tag: None,
+            dimensions,
+            value,
+        };
+        self.modify(&variable, DeclarationType(declaration))
+    }
+
+    /// Whether the cell pushed by the opcode gets popped by the statement in `rest`.
+    fn is_argument(&self, push: &Opcode, rest: &[TreeElementType]) -> bool {
+        let mut depth = 1;
+        let mut previous = Some(push);
+
+        for element in rest.iter() {
+            let opcode = match *element {
+                OpcodeType(ref o) => o,
+                // Conditional value, e.g. argument `a ? b : c`, keeps the stack
+                IfType(If {
+                    else_elements: Some(_),
+                    ..
+                }) => {
+                    previous = None;
+                    continue;
+                }
+                _ => return false,
+            };
+
+            match opcode.code() {
+                OpPushPri | OpPushAlt | OpPushC | OpPush | OpPushS | OpPushaddr => depth += 1,
+                OpPopPri | OpPopAlt => depth -= 1,
+                OpCall => match previous {
+                    Some(p) if p.code() == OpPushC => {
+                        depth -= self.cells(p.argument().unwrap_or(0)) + 1
+                    }
+                    _ => return false,
+                },
+                OpStack => {
+                    depth -= self.cells(opcode.argument().unwrap_or(0));
+                    // Releasing locals is not a use of the value
+                    let is_cleanup = previous.is_some_and(|p| p.code() == OpSysreqC);
+                    if depth <= 0 {
+                        return is_cleanup;
+                    }
+                }
+                OpProc | OpRet | OpRetn | OpSctrl | OpCallPri | OpPushR => return false,
+                _ => (),
+            }
+
+            if depth <= 0 {
+                return true;
+            }
+            previous = Some(opcode);
+        }
+
+        false
+    }
+
+    fn update_sp(&mut self, opcode: &Opcode) {
+        let cell = self.context.cellsize as i64;
+        let argument = opcode.argument().unwrap_or(0);
+
+        self.sp = match opcode.code() {
+            OpPushPri | OpPushAlt | OpPushC | OpPush | OpPushS | OpPushaddr => {
+                self.sp.map(|sp| sp - cell)
+            }
+            OpPopPri | OpPopAlt => self.sp.map(|sp| sp + cell),
+            OpStack => self.sp.map(|sp| sp + self.signed(argument)),
+            // Callee releases its arguments
+            OpCall => match self.last_push_c {
+                Some(count) => self.sp.map(|sp| sp + self.signed(count) + cell),
+                None => None,
+            },
+            OpCallPri | OpSctrl | OpPushR => None,
+            _ => self.sp,
+        };
+
+        self.last_push_c = match opcode.code() {
+            OpPushC => Some(argument),
+            _ => None,
+        };
+    }
+
+    fn opcode(&mut self, opcode: &Opcode, rest: &[TreeElementType]) -> Result<(), &'static str> {
+        use self::BinaryOperator::*;
+        use self::Register::*;
+
+        let argument = opcode.argument().unwrap_or(0);
+        let value = self.signed(argument);
+        let cleanup = std::mem::replace(&mut self.state.cleanup, false);
+
+        match opcode.code() {
+            OpLoadPri | OpLrefPri => self.set(Pri, Expression::Global(argument)),
+            OpLoadAlt | OpLrefAlt => self.set(Alt, Expression::Global(argument)),
+            OpLoadSPri | OpLrefSPri => self.set(Pri, Expression::Local(value)),
+            OpLoadSAlt | OpLrefSAlt => self.set(Alt, Expression::Local(value)),
+            OpLoadI => {
+                let address = self.take(Pri);
+                self.set(Pri, dereference(address));
+            }
+            OpConstPri => self.set(Pri, Expression::Cell(value)),
+            OpConstAlt => self.set(Alt, Expression::Cell(value)),
+            OpAddrPri => self.set(Pri, address_of(Expression::Local(value))),
+            OpAddrAlt => self.set(Alt, address_of(Expression::Local(value))),
+            OpStorPri | OpSrefPri => self.store(Expression::Global(argument), Pri)?,
+            OpStorAlt | OpSrefAlt => self.store(Expression::Global(argument), Alt)?,
+            OpStorSPri | OpSrefSPri => self.store(Expression::Local(value), Pri)?,
+            OpStorSAlt | OpSrefSAlt => self.store(Expression::Local(value), Alt)?,
+            OpStorI => {
+                let address = self.take(Alt);
+                if let Expression::Address(ref e) = address {
+                    if let Expression::Heap(heap) = **e {
+                        let value = self.take(Pri);
+                        self.state.heap.insert(heap, value);
+                        return Ok(());
+                    }
+                }
+                self.store(dereference(address), Pri)?;
+            }
+            OpLidx => {
+                let index = self.take(Pri);
+                let array = array_of(self.take(Alt));
+                self.set(Pri, Expression::Index(Box::new(array), Box::new(index)));
+            }
+            OpIdxaddr => {
+                let index = self.take(Pri);
+                let array = array_of(self.take(Alt));
+                self.set(
+                    Pri,
+                    address_of(Expression::Index(Box::new(array), Box::new(index))),
+                );
+            }
+            OpLidxB | OpIdxaddrB if shifted(argument) == Some(self.context.cellsize) => {
+                let index = self.take(Pri);
+                let element =
+                    Expression::Index(Box::new(array_of(self.take(Alt))), Box::new(index));
+                let element = match opcode.code() {
+                    OpLidxB => element,
+                    _ => address_of(element),
+                };
+                self.set(Pri, element);
+            }
+            OpMovePri => {
+                let value = self.take(Alt);
+                self.set(Pri, value);
+            }
+            OpMoveAlt => {
+                let value = self.take(Pri);
+                self.set(Alt, value);
+            }
+            OpXchg => {
+                let state = &mut self.state;
+                std::mem::swap(&mut state.pri, &mut state.alt);
+                std::mem::swap(&mut state.pri_pending, &mut state.alt_pending);
+                std::mem::swap(&mut state.pri_loaded, &mut state.alt_loaded);
+            }
+            OpPushPri => {
+                let value = self.take(Pri);
+                self.push(opcode, Entry::Value(value), rest)?;
+            }
+            OpPushAlt => {
+                let value = self.take(Alt);
+                self.push(opcode, Entry::Value(value), rest)?;
+            }
+            OpPushC => self.push(opcode, Entry::Constant(argument), rest)?,
+            OpPush => self.push(opcode, Entry::Value(Expression::Global(argument)), rest)?,
+            OpPushS => self.push(opcode, Entry::Value(Expression::Local(value)), rest)?,
+            OpPushaddr => self.push(
+                opcode,
+                Entry::Value(address_of(Expression::Local(value))),
+                rest,
+            )?,
+            OpPopPri => {
+                let value = self.pop()?;
+                self.set(Pri, value);
+            }
+            OpPopAlt => {
+                let value = self.pop()?;
+                self.set(Alt, value);
+            }
+            // Arguments of the native are released
+            OpStack if cleanup => (),
+            // Uninitialized locals, arrays mostly
+            OpStack if value < 0 => {
+                let cells = (-value) as usize / self.context.cellsize;
+                let offset = self.sp.ok_or("Stack pointer is unknown")? + value;
+                let dimensions = if cells > 1 { vec![cells] } else { vec![] };
+                self.declare(offset, dimensions, None)?;
+            }
+            // Locals at the end of their scope get released
+            OpStack => (),
+            OpHeap if value > 0 => {
+                self.set(Alt, address_of(Expression::Heap(opcode.address())));
+            }
+            OpHeap => (),
+            OpSysreqC => {
+                let name = self
+                    .context
+                    .natives
+                    .get(argument as usize)
+                    .ok_or("Native index is out of the table")?
+                    .clone();
+                let args = self.pop_arguments()?;
+                self.set(Pri, Expression::Call(FunctionCall { name, args }));
+                self.set(Alt, Expression::Register(Alt));
+                self.state.cleanup = true;
+            }
+            OpCall => {
+                let name = match self.context.functions.get(&argument) {
+                    Some(name) => name.clone(),
+                    None => format!("sub_{:x}", argument),
+                };
+                let args = self.pop_arguments()?;
+                self.set(Pri, Expression::Call(FunctionCall { name, args }));
+                self.set(Alt, Expression::Register(Alt));
+            }
+            OpShl => self.binary(Shl, Pri, Alt),
+            OpShr => self.binary(Ushr, Pri, Alt),
+            OpSshr => self.binary(Shr, Pri, Alt),
+            OpShlCPri => self.binary_constant(Shl, Pri, value),
+            OpShlCAlt => self.binary_constant(Shl, Alt, value),
+            OpShrCPri => self.binary_constant(Ushr, Pri, value),
+            OpShrCAlt => self.binary_constant(Ushr, Alt, value),
+            OpSmul | OpUmul => self.binary(Mul, Pri, Alt),
+            OpSdiv | OpUdiv => self.divide(Pri, Alt),
+            OpSdivAlt | OpUdivAlt => self.divide(Alt, Pri),
+            OpAdd => self.binary(Add, Pri, Alt),
+            OpSub => self.binary(Sub, Pri, Alt),
+            OpSubAlt => self.binary(Sub, Alt, Pri),
+            OpAnd => self.binary(And, Pri, Alt),
+            OpOr => self.binary(Or, Pri, Alt),
+            OpXor => self.binary(Xor, Pri, Alt),
+            OpNot => {
+                let operand = self.take(Pri);
+                self.set(Pri, operand.negate());
+            }
+            OpNeg => {
+                let operand = self.take(Pri);
+                let result = match operand {
+                    Expression::Cell(v) => Expression::Cell(-v),
+                    operand => Expression::unary(UnaryOperator::Negate, operand),
+                };
+                self.set(Pri, result);
+            }
+            OpInvert => {
+                let operand = self.take(Pri);
+                self.set(Pri, Expression::unary(UnaryOperator::Invert, operand));
+            }
+            OpAddC if value < 0 => self.binary_constant(Sub, Pri, -value),
+            OpAddC => self.binary_constant(Add, Pri, value),
+            OpSmulC => self.binary_constant(Mul, Pri, value),
+            OpZeroPri => self.set(Pri, Expression::Cell(0)),
+            OpZeroAlt => self.set(Alt, Expression::Cell(0)),
+            OpZero => self.assign_zero(Expression::Global(argument))?,
+            OpZeroS => self.assign_zero(Expression::Local(value))?,
+            OpEq => self.binary(Eq, Pri, Alt),
+            OpNeq => self.binary(Neq, Pri, Alt),
+            OpLess | OpSless => self.binary(Less, Pri, Alt),
+            OpLeq | OpSleq => self.binary(Leq, Pri, Alt),
+            OpGrtr | OpSgrtr => self.binary(Greater, Pri, Alt),
+            OpGeq | OpSgeq => self.binary(Geq, Pri, Alt),
+            OpEqCPri => {
+                let lhs = self.take(Pri);
+                self.set(Pri, Expression::binary(Eq, lhs, Expression::Cell(value)));
+            }
+            OpEqCAlt => {
+                let lhs = self.take(Alt);
+                self.set(Pri, Expression::binary(Eq, lhs, Expression::Cell(value)));
+            }
+            OpIncPri => self.binary_constant(Add, Pri, 1),
+            OpIncAlt => self.binary_constant(Add, Alt, 1),
+            OpDecPri => self.binary_constant(Sub, Pri, 1),
+            OpDecAlt => self.binary_constant(Sub, Alt, 1),
+            OpInc => self.step(Expression::Global(argument), Expression::Increment)?,
+            OpIncS => self.step(Expression::Local(value), Expression::Increment)?,
+            OpIncI => {
+                let target = dereference(self.take(Pri));
+                self.step(target, Expression::Increment)?;
+            }
+            OpDec => self.step(Expression::Global(argument), Expression::Decrement)?,
+            OpDecS => self.step(Expression::Local(value), Expression::Decrement)?,
+            OpDecI => {
+                let target = dereference(self.take(Pri));
+                self.step(target, Expression::Decrement)?;
+            }
+            // Zeroing of the new array, its declaration stands for it
+            OpFill
+                if self.state.pri == Expression::Cell(0) && is_local_address(&self.state.alt) =>
+            {
+                self.take(Pri);
+                self.take(Alt);
+            }
+            // Debug information and runtime checks mean nothing for the source
+            OpBounds | OpBreak | OpNop | OpFile | OpLine | OpSymbol | OpSrange | OpSymtag
+            | OpSignPri | OpSignAlt => (),
+            code => {
+                trace!("Opcode {} got no expression", code);
+                return Err("Opcode got no expression");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Integer division, the quotient goes into PRI and the remainder into ALT.
+    fn divide(&mut self, dividend: Register, divisor: Register) {
+        let dividend = self.take(dividend);
+        let divisor = self.take(divisor);
+        self.set(
+            Register::Pri,
+            Expression::binary(BinaryOperator::Div, dividend.clone(), divisor.clone()),
+        );
+        self.set(
+            Register::Alt,
+            Expression::binary(BinaryOperator::Mod, dividend, divisor),
+        );
+    }
+
+    fn assign_zero(&mut self, target: Expression) -> Result<(), &'static str> {
+        let statement = Expression::Assign(Box::new(target.clone()), Box::new(Expression::Cell(0)));
+        self.modify(&target, ExpressionType(statement))
+    }
+
+    fn step(
+        &mut self,
+        target: Expression,
+        operator: fn(Box<Expression>) -> Expression,
+    ) -> Result<(), &'static str> {
+        let statement = operator(Box::new(target.clone()));
+        self.modify(&target, ExpressionType(statement))
+    }
+
+    /// Value returned by `retn`, zero is the default result of every function.
+    fn return_value(&mut self) -> Option<Expression> {
+        match self.state.pri {
+            Expression::Register(_) | Expression::Cell(0) => None,
+            _ => Some(self.take(Register::Pri)),
+        }
+    }
+
+    /// Check of the registers at the conditional jump, following checks are built already.
+    fn condition(&mut self, condition: &Condition, and: Option<Box<Condition>>) -> Condition {
+        let pri = self.take(Register::Pri);
+        let alt = match condition.jump {
+            OpJzer | OpJnz => self.get(Register::Alt).clone(),
+            _ => self.take(Register::Alt),
+        };
+        self.emit_pending();
+
+        Condition {
+            setup: vec![],
+            jump: condition.jump,
+            negated: condition.negated,
+            pri,
+            alt,
+            and,
+        }
+    }
+
+    /// Condition evaluated by its own setup statements.
+    fn nested_condition(&self, condition: &Condition) -> Condition {
+        let and = self.nested_chain(condition);
+
+        let mut builder = self.nested();
+        builder.build(&condition.setup);
+        builder.settle();
+        let test = builder.condition(condition, and);
+
+        Condition {
+            setup: builder.output,
+            ..test
+        }
+    }
+
+    fn nested_chain(&self, condition: &Condition) -> Option<Box<Condition>> {
+        condition
+            .and
+            .as_ref()
+            .map(|next| Box::new(self.nested_condition(next)))
+    }
+
+    fn nested_body(&self, elements: &[TreeElementType]) -> Vec<TreeElementType> {
+        let mut builder = self.nested();
+        builder.build(elements);
+        builder.finish()
+    }
+
+    /// Value left in PRI by the statement list without any statements, e.g. branch of `a ? b : c`.
+    fn nested_value(&self, elements: &[TreeElementType]) -> Option<Expression> {
+        let mut builder = self.nested();
+        builder.build(elements);
+
+        let state = &builder.state;
+        let is_value = builder.output.is_empty()
+            && state.stack.is_empty()
+            && !state.cleanup
+            && !state.alt_pending
+            && state.pri != Expression::Register(Register::Pri);
+        if is_value {
+            Some(builder.state.pri)
+        } else {
+            None
+        }
+    }
+
+    fn statement_if(&mut self, element: &TreeElementType, statement: &If) {
+        let and = self.nested_chain(&statement.condition);
+
+        let is_expression = and.as_ref().is_none_or(|c| c.is_expression());
+        let values = match statement.else_elements {
+            Some(ref else_elements) if is_expression => (
+                self.nested_value(&statement.then_elements),
+                self.nested_value(else_elements),
+            ),
+            _ => (None, None),
+        };
+
+        if let (Some(then_value), Some(else_value)) = values {
+            let condition = self.condition(&statement.condition, and);
+            let value = match condition.expression() {
+                Ok(test) => conditional(test, then_value, else_value),
+                Err(e) => {
+                    trace!("Conditional value is left as is: {}", e);
+                    self.chunk.push(element.clone());
+                    self.flush();
+                    return;
+                }
+            };
+
+            self.chunk.push(element.clone());
+            self.set(Register::Pri, value);
+            if self.is_settled() {
+                self.commit();
+            }
+            return;
+        }
+
+        self.settle();
+        let condition = self.condition(&statement.condition, and);
+        let statement = If {
+            condition,
+            then_elements: self.nested_body(&statement.then_elements),
+            else_elements: statement
+                .else_elements
+                .as_ref()
+                .map(|e| self.nested_body(e)),
+        };
+        self.push_statement(IfType(statement));
+    }
+
+    fn statement_while(&mut self, statement: &While) {
+        self.settle();
+        self.emit_pending();
+
+        let statement = While {
+            condition: statement
+                .condition
+                .as_ref()
+                .map(|c| self.nested_condition(c)),
+            body: self.nested_body(&statement.body),
+        };
+        self.push_statement(WhileType(statement));
+    }
+
+    fn statement_do_while(&mut self, statement: &DoWhile) {
+        self.settle();
+        self.emit_pending();
+
+        // Check setup is at the end of the body
+        let and = self.nested_chain(&statement.condition);
+        let mut builder = self.nested();
+        builder.build(&statement.body);
+        builder.settle();
+        let condition = builder.condition(&statement.condition, and);
+
+        let statement = DoWhile {
+            body: builder.output,
+            condition,
+        };
+        self.push_statement(DoWhileType(statement));
+    }
+
+    fn statement_for(&mut self, statement: &For) {
+        self.settle();
+        self.emit_pending();
+
+        let statement = For {
+            condition: statement
+                .condition
+                .as_ref()
+                .map(|c| self.nested_condition(c)),
+            increment: self.nested_body(&statement.increment),
+            body: self.nested_body(&statement.body),
+        };
+        self.push_statement(ForType(statement));
+    }
+
+    fn statement_switch(&mut self, statement: &Switch) {
+        self.settle();
+        let value = self.take(Register::Pri);
+        self.emit_pending();
+
+        let mut switch = statement.clone();
+        switch.value = value;
+        for case in switch.cases.iter_mut() {
+            case.body = self.nested_body(&case.body);
+        }
+        switch.default = switch.default.as_ref().map(|d| self.nested_body(d));
+        self.push_statement(SwitchType(switch));
+    }
+}
+
+/// Element size of `lidx.b` and `idxaddr.b`, none for shifts out of the cell.
+fn shifted(shift: UCell) -> Option<usize> {
+    1usize.checked_shl(u32::try_from(shift).ok()?)
+}
+
+/// Variable stored at the address.
+fn dereference(address: Expression) -> Expression {
+    match address {
+        Expression::Address(e) => *e,
+        address => Expression::Index(Box::new(address), Box::new(Expression::Cell(0))),
+    }
+}
+
+fn is_local_address(address: &Expression) -> bool {
+    match *address {
+        Expression::Address(ref e) => matches!(**e, Expression::Local(_)),
+        _ => false,
+    }
+}
+
+fn address_of(variable: Expression) -> Expression {
+    Expression::Address(Box::new(variable))
+}
+
+/// Array at the address kept in ALT.
+fn array_of(address: Expression) -> Expression {
+    match address {
+        Expression::Address(e) => *e,
+        // Global arrays are addressed by constants
+        Expression::Cell(v) => Expression::Global(v as UCell),
+        // Row of two-dimensional array: its address plus offset stored in it
+        Expression::Binary(BinaryOperator::Add, lhs, rhs) => match (*lhs, *rhs) {
+            (Expression::Address(row), offset) if *row == offset => *row,
+            (lhs, rhs) => Expression::binary(BinaryOperator::Add, lhs, rhs),
+        },
+        address => address,
+    }
+}
+
+/// Value of `test ? a : b`, Pawn computes booleans of `&&` and `||` this way.
+fn conditional(test: Expression, a: Expression, b: Expression) -> Expression {
+    match (a, b) {
+        (Expression::Cell(1), Expression::Cell(0)) if test.is_boolean() => test,
+        (Expression::Cell(0), Expression::Cell(1)) if test.is_boolean() => test.negate(),
+        (a, b) => Expression::Conditional(Box::new(test), Box::new(a), Box::new(b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_expressions, Context};
+    use crate::ast::expression::Expression;
+    use crate::ast::structure::structure;
+    use crate::ast::TreeElement;
+    use crate::ast::TreeElementType::{self, OpcodeType};
+    use crate::util::tests::decode;
+    use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+    use amxmodx_utils::amx::UCell;
+    use std::collections::HashMap;
+
+    fn build(elements: &[TreeElementType]) -> String {
+        let natives = vec!["foo".to_owned()];
+        let functions: HashMap<UCell, String> =
+            vec![(0x100, "bar".to_owned())].into_iter().collect();
+        let dat_constant = |address| match address {
+            0 => Some(Expression::String("hi".to_owned())),
+            _ => None,
+        };
+        let context = Context {
+            cellsize: 4,
+            natives: &natives,
+            functions: &functions,
+            dat_constant: &dat_constant,
+        };
+
+        build_expressions(elements, &context)
+            .iter()
+            .map(|e| e.to_string(0).unwrap())
+            .collect()
+    }
+
+    fn build_flat(cells: &[u64]) -> String {
+        let elements: Vec<_> = decode(cells).into_iter().map(OpcodeType).collect();
+        build(&elements)
+    }
+
+    #[test]
+    fn it_builds_computed_native_arguments() {
+        let source = build_flat(&[
+            OpPushC as u64, // 0x00
+            0,
+            OpLoadSPri as u64, // 0x08
+            12,
+            OpAddC as u64, // 0x10
+            1,
+            OpPushPri as u64, // 0x18
+            OpPushC as u64,   // 0x1C
+            8,
+            OpSysreqC as u64, // 0x24
+            0,
+            OpStack as u64, // 0x2C
+            12,
+            OpZeroPri as u64, // 0x34
+        ]);
+        assert_eq!(source, "foo(frame[12] + 1, \"hi\");\n");
+    }
+
+    #[test]
+    fn it_builds_array_assignment_and_return_value() {
+        let source = build_flat(&[
+            OpLoadSPri as u64, // 0x00
+            -4i32 as u32 as u64,
+            OpConstAlt as u64, // 0x08
+            0x10,
+            OpIdxaddr as u64,  // 0x10
+            OpPushPri as u64,  // 0x14
+            OpLoadSPri as u64, // 0x18
+            12,
+            OpSmulC as u64, // 0x20
+            2,
+            OpPopAlt as u64,   // 0x28
+            OpStorI as u64,    // 0x2C
+            OpLoadSPri as u64, // 0x30
+            12,
+            OpEqCPri as u64, // 0x38
+            3,
+        ]);
+        assert_eq!(
+            source,
+            "data[0x10][frame[-4]] = frame[12] * 2;\nreturn frame[12] == 3;\n"
+        );
+    }
+
+    #[test]
+    fn it_builds_locals_and_call_results() {
+        let source = build_flat(&[
+            OpPushC as u64, // 0x00
+            5,
+            OpPushS as u64, // 0x08
+            -4i32 as u32 as u64,
+            OpPushC as u64, // 0x10
+            4,
+            OpCall as u64, // 0x18
+            0x100,
+            OpStorSPri as u64, // 0x20
+            -4i32 as u32 as u64,
+            OpStack as u64, // 0x28
+            -8i32 as u32 as u64,
+            OpZeroPri as u64, // 0x30
+            OpAddrAlt as u64, // 0x34
+            -12i32 as u32 as u64,
+            OpFill as u64, // 0x3C
+            8,
+            OpStack as u64, // 0x44
+            12,
+            OpZeroPri as u64, // 0x4C
+        ]);
+        assert_eq!(
+            source,
+            "new frame[-4] = 5;\nframe[-4] = bar(frame[-4]);\nnew frame[-12][2];\n"
+        );
+    }
+
+    #[test]
+    fn it_builds_logical_and_value() {
+        let opcodes = decode(&[
+            OpLoadSPri as u64, // 0x00
+            12,
+            OpJzer as u64, // 0x08
+            0x30,
+            OpLoadSPri as u64, // 0x10
+            16,
+            OpJzer as u64, // 0x18
+            0x30,
+            OpConstPri as u64, // 0x20
+            1,
+            OpJump as u64, // 0x28
+            0x34,
+            OpZeroPri as u64,  // 0x30
+            OpStorSPri as u64, // 0x34
+            -4i32 as u32 as u64,
+            OpZeroPri as u64, // 0x3C
+        ]);
+        let source = build(&structure(&opcodes).unwrap());
+        assert_eq!(source, "frame[-4] = frame[12] && frame[16];\n");
+    }
+
+    #[test]
+    fn it_keeps_opcodes_without_expression() {
+        let source = build_flat(&[
+            OpLctrl as u64, // 0x00
+            4,
+            OpPushPri as u64, // 0x08
+            OpPushC as u64,   // 0x0C
+            4,
+            OpSysreqC as u64, // 0x14
+            0,
+            OpStack as u64, // 0x1C
+            8,
+            OpZeroPri as u64, // 0x24
+        ]);
+        assert_eq!(source, "#emit lctrl\t0x4\nfoo(pri);\n");
+    }
+
+    #[test]
+    fn it_keeps_indexing_with_shift_out_of_cell() {
+        let source = build_flat(&[
+            OpLidxB as u64, // 0x00
+            200,
+            OpZeroPri as u64, // 0x08
+        ]);
+        assert_eq!(source, "#emit lidx.b\t0xC8\n");
+    }
+
+    #[test]
+    fn it_keeps_loads_read_by_opcodes_without_expression() {
+        let source = build_flat(&[
+            OpLoadSPri as u64, // 0x00
+            12,
+            OpSctrl as u64, // 0x08
+            2,
+            OpConstPri as u64, // 0x10
+            7,
+            OpLctrl as u64, // 0x18
+            4,
+            OpZeroPri as u64, // 0x20
+        ]);
+        assert_eq!(
+            source,
+            "#emit load.s.pri\t0xC\n#emit sctrl\t0x2\n#emit const.pri\t0x7\n#emit lctrl\t0x4\n"
+        );
+    }
+}
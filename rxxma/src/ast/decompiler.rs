@@ -1,14 +1,28 @@
 use log::trace;
-
-use super::super::amx::Opcode;
-use super::super::amx::OpcodeType::*;
-use super::super::amx::Plugin as AmxPlugin;
-use super::super::amx::CELLSIZE;
-use super::function_call::{Argument, FunctionCall};
+use std::collections::{HashMap, HashSet};
+
+use super::dataflow::{build_expressions, Context};
+use super::expression::Expression;
+use super::float::infer_floats;
+use super::frame::name_variables;
+use super::globals::{declarations, name_globals};
+use super::structure::structure;
+use super::typing::{type_arguments, Data};
 use super::Function as AstFunction;
 use super::Plugin as AstPlugin;
 use super::TreeElementType;
 use super::TreeElementType::*;
+use crate::analysis::data::DataLayout;
+use crate::include::Database;
+use amxmodx_utils::amx::debug::{SymbolClass, SymbolKind};
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+use amxmodx_utils::amx::tables::{Native, Public, Tag};
+use amxmodx_utils::amx::File as AmxPlugin;
+use amxmodx_utils::amx::UCell;
+
+/// Tag ids without the strong and fixed tag flags.
+const TAG_ID_MASK: UCell = 0x3FFF_FFFF;
 
 pub struct Decompiler {
     pub amx_plugin: AmxPlugin,
@@ -17,7 +31,13 @@ pub struct Decompiler {
 
 impl Decompiler {
     pub fn from(amx_plugin: AmxPlugin) -> Decompiler {
-        let opcodes = amx_plugin.opcodes().unwrap();
+        // Code starts with `halt 0`, which is not a part of any function
+        let opcodes = amx_plugin
+            .opcodes()
+            .unwrap()
+            .skip(1)
+            .collect::<Result<_, _>>()
+            .unwrap();
 
         Decompiler {
             amx_plugin,
@@ -31,7 +51,14 @@ impl Decompiler {
 
     pub fn opcodes_into_functions(&mut self) {
         trace!("Pack opcodes into functions");
-        let public_list = self.amx_plugin.publics().unwrap();
+        let public_list = self
+            .amx_plugin
+            .publics()
+            .unwrap()
+            .collect::<Result<Vec<Public>, _>>()
+            .unwrap();
+        // Plugins without debug info simply get made up function names
+        let debug_info = self.amx_plugin.debug_info().ok();
 
         let mut new_tree: Vec<TreeElementType> = vec![];
         let mut current_function: Option<AstFunction> = None;
@@ -45,17 +72,19 @@ impl Decompiler {
                 }
             };
 
-            // Open function
-            if opcode.code == OP_PROC {
-                // TODO: Check if func already exist
-                current_function = Some(AstFunction::from(&opcode, &public_list));
-                continue;
-            }
+            // Close previous function and open the next one,
+            // function might return from the middle, so it lasts until the next one
+            if opcode.code() == OpProc {
+                if let Some(f) = current_function.take() {
+                    new_tree.push(FunctionType(close_function(f)));
+                }
 
-            // Close function
-            if opcode.code == OP_RETN && current_function.is_some() {
-                new_tree.push(FunctionType(current_function.unwrap()));
-                current_function = None;
+                // TODO: Check if func already exist
+                current_function = Some(AstFunction::from(
+                    &opcode,
+                    &public_list,
+                    debug_info.as_ref(),
+                ));
                 continue;
             }
 
@@ -69,12 +98,52 @@ impl Decompiler {
             new_tree.push(OpcodeType(opcode));
         }
 
+        if let Some(f) = current_function {
+            new_tree.push(FunctionType(close_function(f)));
+        }
+
         self.ast_plugin.tree_elements = new_tree;
     }
 
+    /// Replaces jumps with control flow statements.
+    ///
+    /// Functions with jumps not matching any statement keep their opcodes.
+    pub fn structure_control_flow(&mut self) {
+        trace!("Recover control flow statements");
+
+        for element in self.ast_plugin.tree_elements.iter_mut() {
+            let function = match *element {
+                FunctionType(ref mut f) => f,
+                _ => continue,
+            };
+
+            let opcodes: Vec<Opcode> = function
+                .tree_elements
+                .iter()
+                .filter_map(|e| match *e {
+                    OpcodeType(ref o) => Some(o.clone()),
+                    _ => None,
+                })
+                .collect();
+
+            // Already transformed
+            if opcodes.len() != function.tree_elements.len() {
+                continue;
+            }
+
+            match structure(&opcodes) {
+                Ok(tree_elements) => function.tree_elements = tree_elements,
+                Err(e) => trace!("Function {} is left unstructured: {}", function.name, e),
+            }
+        }
+    }
+
     pub fn decompile_opcodes_by_templates(&mut self) -> Result<(), &'static str> {
         self.clean_functions_break()?;
-        self.decompile_native_calls()?;
+        let layout = self.data_layout()?;
+        self.decompile_expressions(&layout)?;
+        self.name_variables()?;
+        self.name_globals(&layout);
         Ok(())
     }
 
@@ -104,11 +173,11 @@ impl Decompiler {
                 let position = addr - 1;
 
                 let opcode = match current_tree[position] {
-                    OpcodeType(o) => o,
+                    OpcodeType(ref o) => o,
                     _ => continue,
                 };
 
-                if opcode.code != OP_BREAK {
+                if opcode.code() != OpBreak {
                     break;
                 }
 
@@ -119,185 +188,199 @@ impl Decompiler {
         Ok(())
     }
 
-    pub fn decompile_native_calls(&mut self) -> Result<(), &'static str> {
-        trace!("Decompile native calls");
+    /// Rebuilds calls, assignments and return values from opcodes.
+    pub fn decompile_expressions(&mut self, layout: &DataLayout) -> Result<(), &'static str> {
+        trace!("Decompile expressions");
         let ast_plugin = &mut self.ast_plugin;
-        let amx_plugin = &mut self.amx_plugin;
+        let amx_plugin = &self.amx_plugin;
         // TODO: Error handling
-        let natives = amx_plugin.natives().unwrap();
-
-        let functions: Vec<_> = ast_plugin
+        let natives: Vec<String> = amx_plugin
+            .natives()
+            .unwrap()
+            .map(|n| n.map(|n: Native| n.name))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let functions: HashMap<UCell, String> = ast_plugin
             .tree_elements
-            .iter_mut()
-            .map(|e| match *e {
-                FunctionType(ref mut f) => Some(f),
+            .iter()
+            .filter_map(|e| match *e {
+                FunctionType(ref f) => Some((f.address, f.name.clone())),
                 _ => None,
             })
-            .filter(Option::is_some)
-            .map(Option::unwrap)
             .collect();
 
-        for function in functions {
-            let mut addr = 0;
-            let current_tree = &mut function.tree_elements;
+        // Literals stay addresses until typing tells them from variables passed by reference
+        let dat_constant = |address| {
+            layout.global_at(address).filter(|g| g.address == address)?;
+            Some(Expression::Address(Box::new(Expression::Global(address))))
+        };
+        let context = Context {
+            cellsize: amx_plugin.cellsize(),
+            natives: &natives,
+            functions: &functions,
+            dat_constant: &dat_constant,
+        };
+
+        for element in ast_plugin.tree_elements.iter_mut() {
+            if let FunctionType(ref mut f) = *element {
+                f.tree_elements = build_expressions(&f.tree_elements, &context);
+            }
+        }
+        Ok(())
+    }
 
-            // Iterate and modify over function tree
-            while addr < current_tree.len() {
-                addr += 1;
-                let mut position = addr - 1;
+    /// Names parameters and locals, parameters count is taken from callers too.
+    pub fn name_variables(&mut self) -> Result<(), &'static str> {
+        trace!("Name parameters and local variables");
+        let cellsize = self.amx_plugin.cellsize();
+        // TODO: Error handling
+        let opcodes: Vec<Opcode> = self
+            .amx_plugin
+            .opcodes()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        // Size of the arguments is pushed right before the call
+        let mut argument_counts: HashMap<UCell, usize> = HashMap::new();
+        for pair in opcodes.windows(2) {
+            if let (OpPushC, OpCall) = (pair[0].code(), pair[1].code()) {
+                let count = pair[0].argument().unwrap_or(0) as usize / cellsize;
+                let address = pair[1].argument().unwrap_or(0);
+                let known = argument_counts.entry(address).or_insert(0);
+                *known = (*known).max(count);
+            }
+        }
 
-                let opcode = match current_tree[position] {
-                    OpcodeType(o) => o,
-                    _ => continue,
-                };
+        for element in self.ast_plugin.tree_elements.iter_mut() {
+            if let FunctionType(ref mut f) = *element {
+                let count = argument_counts.get(&f.address).cloned().unwrap_or(0);
+                name_variables(f, cellsize, count);
+            }
+        }
+        Ok(())
+    }
 
-                if opcode.code == OP_SYSREQ_C {
-                    let sysreq_opcode = &opcode;
-                    // Take previous PUSH.C to get args count
-                    let native_arguments_count = {
-                        let element = &current_tree[position - 1];
-
-                        let opcode = match element {
-                            OpcodeType(o) => o,
-                            _ => continue,
-                        };
-
-                        // Weird native call, ignore
-                        if opcode.code != OP_PUSH_C {
-                            trace!("Native call got no arguments definition");
-                            continue;
-                        }
-
-                        opcode.param.unwrap() as usize / CELLSIZE
-                    };
-
-                    // Sysreq.c (current) - PUSH.c with args count - args count
-                    let args_start = position - 1 - native_arguments_count;
-                    // Sysreq.c (current) - PUSH.c with args count
-                    let args_end = position - 1;
-                    let raw_args: Vec<_> = current_tree.drain(args_start..args_end).collect();
-                    addr -= args_end - args_start;
-                    position -= args_end - args_start;
-
-                    // Remove push.c with num of args
-                    current_tree.remove(position - 1);
-                    addr -= 1;
-                    position -= 1;
-
-                    let is_having_non_opcodes = raw_args.iter().any(|e| match *e {
-                        OpcodeType(_) => false,
-                        _ => true,
-                    });
-
-                    // Internal error
-                    // Something else modified ast tree
-                    if is_having_non_opcodes {
-                        trace!(
-                            "Function tree was modified by bad AST transformation. Ignoring call"
-                        );
-                        continue;
-                    }
-
-                    let args_opcodes: Vec<Opcode> = raw_args
-                        .iter()
-                        .map(|e| match *e {
-                            OpcodeType(o) => Some(o),
-                            _ => None,
-                        })
-                        .filter(Option::is_some)
-                        .map(Option::unwrap)
-                        .collect();
-
-                    let is_having_non_push_c_opcodes =
-                        args_opcodes.iter().any(|o| o.code != OP_PUSH_C);
-                    if is_having_non_push_c_opcodes {
-                        trace!("Invalid native call arguments");
-                        continue;
-                    }
-
-                    let native_args: Vec<_> = args_opcodes
-                        .iter()
-                        .map(|o| o.param.unwrap())
-                        .map(|addr| amx_plugin.read_constant_auto_type(addr as usize).unwrap())
-                        .map(Argument::from)
-                        .rev()
-                        .collect();
-
-                    let native_index = sysreq_opcode.param.unwrap() as usize;
-                    let native_name = (&natives[native_index].name).clone().into_string().unwrap();
-
-                    let ast_function_call = FunctionCall {
-                        name: native_name,
-                        args: Some(native_args),
-                    };
-
-                    current_tree[position] = FunctionCallType(ast_function_call);
-
-                    // Check for trash OP_STACK
-                    {
-                        let opcode_position = position + 1;
-
-                        let opcode = {
-                            let element = match current_tree.get(opcode_position) {
-                                Some(e) => e,
-                                None => break,
-                            };
-
-                            match element {
-                                OpcodeType(o) => o,
-                                _ => break,
-                            }
-                        };
-
-                        if opcode.code == OP_STACK {
-                            current_tree.remove(opcode_position);
-                        }
-                    }
-
-                    // Check for trash OP_ZERO_PRI
-                    {
-                        let opcode_position = position + 1;
-
-                        let opcode = {
-                            let element = match current_tree.get(opcode_position) {
-                                Some(e) => e,
-                                None => break,
-                            };
-
-                            match element {
-                                OpcodeType(o) => o,
-                                _ => break,
-                            }
-                        };
-
-                        if opcode.code == OP_ZERO_PRI {
-                            current_tree.remove(opcode_position);
-                        }
-                    }
-
-                    // Check for trash OP_BREAK
-                    {
-                        let opcode_position = position + 1;
-
-                        let opcode = {
-                            let element = match current_tree.get(opcode_position) {
-                                Some(e) => e,
-                                None => break,
-                            };
-
-                            match element {
-                                OpcodeType(o) => o,
-                                _ => break,
-                            }
-                        };
-
-                        if opcode.code == OP_BREAK {
-                            current_tree.remove(opcode_position);
-                        }
-                    }
-                }
+    /// Splits DAT section into globals, arrays and string literals.
+    pub fn data_layout(&self) -> Result<DataLayout, &'static str> {
+        let opcodes: Vec<Opcode> = self
+            .amx_plugin
+            .opcodes()
+            .map_err(|_| "Plugin got no code")?
+            .collect::<Result<_, _>>()
+            .map_err(|_| "Plugin got invalid code")?;
+        let dat = self
+            .amx_plugin
+            .dat_slice()
+            .map_err(|_| "Plugin got no data")?;
+
+        Ok(DataLayout::analyze(
+            &opcodes,
+            dat,
+            self.amx_plugin.cellsize(),
+        ))
+    }
+
+    /// Declares globals and refers to them by name.
+    pub fn name_globals(&mut self, layout: &DataLayout) {
+        trace!("Name global variables");
+        let tree_elements = &mut self.ast_plugin.tree_elements;
+        for element in tree_elements.iter_mut() {
+            if let FunctionType(ref mut f) = *element {
+                name_globals(&mut f.tree_elements, layout);
             }
         }
+
+        let declarations = declarations(layout, self.amx_plugin.cellsize());
+        tree_elements.splice(0..0, declarations);
+    }
+
+    /// Types call arguments by native and stock signatures from include files.
+    ///
+    /// Literals passed by reference turn into globals, they get declared along the others.
+    pub fn type_arguments(&mut self, database: &Database) -> Result<(), &'static str> {
+        trace!("Type call arguments");
+        let mut layout = self.data_layout()?;
+        let cellsize = self.amx_plugin.cellsize();
+        let tree_elements = &mut self.ast_plugin.tree_elements;
+        let mut data = Data {
+            layout: &mut layout,
+            cellsize,
+        };
+        type_arguments(tree_elements, database, &mut data);
+
+        let declared = tree_elements
+            .iter()
+            .take_while(|e| matches!(e, DeclarationType(_)))
+            .count();
+        tree_elements.splice(0..declared, declarations(&layout, cellsize));
         Ok(())
     }
+
+    /// Tags floats and renders their values as float literals.
+    pub fn infer_floats(&mut self, database: &Database) -> Result<(), &'static str> {
+        trace!("Infer floats");
+        let layout = self.data_layout()?;
+        let float_globals = self.float_globals(&layout);
+        infer_floats(
+            &mut self.ast_plugin.tree_elements,
+            database,
+            &float_globals,
+            self.amx_plugin.cellsize(),
+        );
+        Ok(())
+    }
+
+    /// Names of the globals debug symbols tag with `Float:`.
+    fn float_globals(&self, layout: &DataLayout) -> HashSet<String> {
+        let debug_info = match self.amx_plugin.debug_info() {
+            Ok(d) => d,
+            Err(_) => return HashSet::new(),
+        };
+
+        // Tag table of the image got the ids with flags in the upper bits
+        let tag = debug_info
+            .tags
+            .iter()
+            .find(|t| t.name == "Float")
+            .map(|t| t.id as UCell)
+            .or_else(|| {
+                self.amx_plugin
+                    .tags()
+                    .ok()?
+                    .filter_map(Result::ok)
+                    .find(|t: &Tag| t.name == "Float")
+                    .map(|t| t.id & TAG_ID_MASK)
+            });
+        let tag = match tag {
+            Some(t) => t,
+            None => return HashSet::new(),
+        };
+
+        debug_info
+            .symbols
+            .iter()
+            .filter(|s| s.class == SymbolClass::Global && s.tag as UCell == tag)
+            .filter(|s| matches!(s.kind, SymbolKind::Variable | SymbolKind::Array))
+            .filter_map(|s| {
+                let global = layout.global_at(s.address)?;
+                global.name.clone().filter(|_| global.address == s.address)
+            })
+            .collect()
+    }
+}
+
+/// Drops the final `retn`, closing brace of the function stands for it.
+fn close_function(mut function: AstFunction) -> AstFunction {
+    let is_retn = match function.tree_elements.last() {
+        Some(OpcodeType(o)) => o.code() == OpRetn,
+        _ => false,
+    };
+
+    if is_retn {
+        function.tree_elements.pop();
+    }
+
+    function
 }
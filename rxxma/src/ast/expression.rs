@@ -0,0 +1,398 @@
+use super::function_call::FunctionCall;
+use amxmodx_utils::amx::constant::Constant;
+use amxmodx_utils::amx::UCell;
+use serde::Serialize;
+use std::convert::From;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Register {
+    Pri,
+    Alt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnaryOperator {
+    Not,
+    Negate,
+    Invert,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryOperator {
+    Mul,
+    Div,
+    Mod,
+    Add,
+    Sub,
+    Shl,
+    /// Arithmetic shift, `>>`.
+    Shr,
+    /// Logical shift, `>>>`.
+    Ushr,
+    Less,
+    Leq,
+    Greater,
+    Geq,
+    Eq,
+    Neq,
+    And,
+    Xor,
+    Or,
+    LogicalAnd,
+    LogicalOr,
+}
+
+impl BinaryOperator {
+    fn symbol(self) -> &'static str {
+        match self {
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Mod => "%",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Shl => "<<",
+            BinaryOperator::Shr => ">>",
+            BinaryOperator::Ushr => ">>>",
+            BinaryOperator::Less => "<",
+            BinaryOperator::Leq => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::Geq => ">=",
+            BinaryOperator::Eq => "==",
+            BinaryOperator::Neq => "!=",
+            BinaryOperator::And => "&",
+            BinaryOperator::Xor => "^",
+            BinaryOperator::Or => "|",
+            BinaryOperator::LogicalAnd => "&&",
+            BinaryOperator::LogicalOr => "||",
+        }
+    }
+
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::Mod => 12,
+            BinaryOperator::Add | BinaryOperator::Sub => 11,
+            BinaryOperator::Shl | BinaryOperator::Shr | BinaryOperator::Ushr => 10,
+            BinaryOperator::Less
+            | BinaryOperator::Leq
+            | BinaryOperator::Greater
+            | BinaryOperator::Geq => 9,
+            BinaryOperator::Eq | BinaryOperator::Neq => 8,
+            BinaryOperator::And => 7,
+            BinaryOperator::Xor => 6,
+            BinaryOperator::Or => 5,
+            BinaryOperator::LogicalAnd => 4,
+            BinaryOperator::LogicalOr => 3,
+        }
+    }
+
+    /// Operator giving the opposite result, for comparisons only.
+    fn inverse(self) -> Option<BinaryOperator> {
+        match self {
+            BinaryOperator::Less => Some(BinaryOperator::Geq),
+            BinaryOperator::Leq => Some(BinaryOperator::Greater),
+            BinaryOperator::Greater => Some(BinaryOperator::Leq),
+            BinaryOperator::Geq => Some(BinaryOperator::Less),
+            BinaryOperator::Eq => Some(BinaryOperator::Neq),
+            BinaryOperator::Neq => Some(BinaryOperator::Eq),
+            _ => None,
+        }
+    }
+}
+
+const PRECEDENCE_ASSIGN: u8 = 1;
+const PRECEDENCE_CONDITIONAL: u8 = 2;
+const PRECEDENCE_UNARY: u8 = 13;
+const PRECEDENCE_PRIMARY: u8 = 14;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expression {
+    Cell(i64),
+    Bool(bool),
+    /// Float literal, cells hold its bits.
+    Float(f64),
+    String(String),
+    /// Register with unknown content.
+    Register(Register),
+    /// Variable referred to by its name.
+    Variable(String),
+    /// Variable in DAT section at the address.
+    Global(UCell),
+    /// Variable at the offset from the frame, parameters got positive offsets.
+    Local(i64),
+    /// Temporary cell on the heap, e.g. value passed by reference, by address of its allocation.
+    Heap(UCell),
+    /// Address of the variable or array element, Pawn passes it implicitly to references.
+    Address(Box<Expression>),
+    Index(Box<Expression>, Box<Expression>),
+    Unary(UnaryOperator, Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
+    Assign(Box<Expression>, Box<Expression>),
+    Increment(Box<Expression>),
+    Decrement(Box<Expression>),
+    Call(FunctionCall),
+    /// Array initializer, `{1, 2, 3}`.
+    Array(Vec<Expression>),
+    /// Value with the tag, `Float:value`.
+    Tagged(String, Box<Expression>),
+}
+
+impl From<Constant> for Expression {
+    fn from(constant: Constant) -> Self {
+        match constant {
+            Constant::Cell(v) => Expression::Cell(v as i64),
+            Constant::String(v) => Expression::String(v),
+        }
+    }
+}
+
+impl Expression {
+    pub fn binary(operator: BinaryOperator, lhs: Expression, rhs: Expression) -> Expression {
+        Expression::Binary(operator, Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn unary(operator: UnaryOperator, operand: Expression) -> Expression {
+        Expression::Unary(operator, Box::new(operand))
+    }
+
+    /// Logical negation, comparisons get inverted instead of wrapped into `!`.
+    pub fn negate(self) -> Expression {
+        match self {
+            Expression::Unary(UnaryOperator::Not, operand) => *operand,
+            Expression::Binary(operator, lhs, rhs) => match operator {
+                BinaryOperator::LogicalAnd => {
+                    Expression::binary(BinaryOperator::LogicalOr, lhs.negate(), rhs.negate())
+                }
+                BinaryOperator::LogicalOr => {
+                    Expression::binary(BinaryOperator::LogicalAnd, lhs.negate(), rhs.negate())
+                }
+                _ => match operator.inverse() {
+                    Some(inverse) => Expression::Binary(inverse, lhs, rhs),
+                    None => Expression::unary(
+                        UnaryOperator::Not,
+                        Expression::Binary(operator, lhs, rhs),
+                    ),
+                },
+            },
+            expression => Expression::unary(UnaryOperator::Not, expression),
+        }
+    }
+
+    /// Whether value is always 0 or 1.
+    pub fn is_boolean(&self) -> bool {
+        match *self {
+            Expression::Unary(UnaryOperator::Not, _) => true,
+            Expression::Binary(operator, _, _) => {
+                operator.inverse().is_some()
+                    || operator == BinaryOperator::LogicalAnd
+                    || operator == BinaryOperator::LogicalOr
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether evaluation changes anything besides registers.
+    pub fn has_side_effects(&self) -> bool {
+        match *self {
+            Expression::Call(_)
+            | Expression::Assign(_, _)
+            | Expression::Increment(_)
+            | Expression::Decrement(_) => true,
+            Expression::Address(ref e)
+            | Expression::Unary(_, ref e)
+            | Expression::Tagged(_, ref e) => e.has_side_effects(),
+            Expression::Index(ref a, ref b) | Expression::Binary(_, ref a, ref b) => {
+                a.has_side_effects() || b.has_side_effects()
+            }
+            Expression::Conditional(ref c, ref a, ref b) => {
+                c.has_side_effects() || a.has_side_effects() || b.has_side_effects()
+            }
+            _ => false,
+        }
+    }
+
+    /// Direct subexpressions, e.g. operands.
+    pub fn children_mut(&mut self) -> Vec<&mut Expression> {
+        match *self {
+            Expression::Address(ref mut e)
+            | Expression::Unary(_, ref mut e)
+            | Expression::Tagged(_, ref mut e)
+            | Expression::Increment(ref mut e)
+            | Expression::Decrement(ref mut e) => vec![e],
+            Expression::Index(ref mut a, ref mut b)
+            | Expression::Binary(_, ref mut a, ref mut b)
+            | Expression::Assign(ref mut a, ref mut b) => vec![a, b],
+            Expression::Conditional(ref mut c, ref mut a, ref mut b) => vec![c, a, b],
+            Expression::Call(ref mut call) => call.args.iter_mut().collect(),
+            Expression::Array(ref mut items) => items.iter_mut().collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Whether evaluation reads the variable, taking its address is not a read.
+    pub fn reads(&self, variable: &Expression) -> bool {
+        if self == variable {
+            return true;
+        }
+
+        match *self {
+            Expression::Address(ref e) => **e != *variable && e.reads(variable),
+            Expression::Unary(_, ref e)
+            | Expression::Tagged(_, ref e)
+            | Expression::Increment(ref e)
+            | Expression::Decrement(ref e) => e.reads(variable),
+            Expression::Index(ref a, ref b)
+            | Expression::Binary(_, ref a, ref b)
+            | Expression::Assign(ref a, ref b) => a.reads(variable) || b.reads(variable),
+            Expression::Conditional(ref c, ref a, ref b) => {
+                c.reads(variable) || a.reads(variable) || b.reads(variable)
+            }
+            Expression::Call(ref call) => call.args.iter().any(|arg| arg.reads(variable)),
+            _ => false,
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match *self {
+            Expression::Cell(v) if v < 0 => PRECEDENCE_UNARY,
+            Expression::Float(v) if v.is_sign_negative() => PRECEDENCE_UNARY,
+            Expression::Address(ref e) => e.precedence(),
+            Expression::Unary(_, _)
+            | Expression::Tagged(_, _)
+            | Expression::Increment(_)
+            | Expression::Decrement(_) => PRECEDENCE_UNARY,
+            Expression::Binary(operator, _, _) => operator.precedence(),
+            Expression::Conditional(_, _, _) => PRECEDENCE_CONDITIONAL,
+            Expression::Assign(_, _) => PRECEDENCE_ASSIGN,
+            _ => PRECEDENCE_PRIMARY,
+        }
+    }
+
+    /// Writes the expression, wrapped into parentheses if it binds weaker than `precedence`.
+    fn fmt_operand(&self, f: &mut fmt::Formatter, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Expression::Cell(v) => write!(f, "{}", v),
+            Expression::Bool(v) => write!(f, "{}", v),
+            Expression::Float(v) => {
+                // Pawn literals got the point before the exponent, `1.0e20`
+                let literal = format!("{:?}", v);
+                match literal.find('e') {
+                    Some(e) if !literal[..e].contains('.') => {
+                        write!(f, "{}.0{}", &literal[..e], &literal[e..])
+                    }
+                    _ => write!(f, "{}", literal),
+                }
+            }
+            Expression::String(ref s) => write!(f, "{:?}", s),
+            Expression::Register(Register::Pri) => write!(f, "pri"),
+            Expression::Register(Register::Alt) => write!(f, "alt"),
+            Expression::Variable(ref name) => write!(f, "{}", name),
+            Expression::Global(address) => write!(f, "data[0x{:X}]", address),
+            Expression::Local(offset) => write!(f, "frame[{}]", offset),
+            Expression::Heap(address) => write!(f, "heap[0x{:X}]", address),
+            Expression::Address(ref e) => write!(f, "{}", e),
+            Expression::Index(ref array, ref index) => {
+                array.fmt_operand(f, PRECEDENCE_PRIMARY)?;
+                write!(f, "[{}]", index)
+            }
+            Expression::Unary(operator, ref operand) => {
+                let symbol = match operator {
+                    UnaryOperator::Not => "!",
+                    UnaryOperator::Negate => "-",
+                    UnaryOperator::Invert => "~",
+                };
+                write!(f, "{}", symbol)?;
+                operand.fmt_operand(f, PRECEDENCE_UNARY)
+            }
+            Expression::Binary(operator, ref lhs, ref rhs) => {
+                // Left associative
+                lhs.fmt_operand(f, operator.precedence())?;
+                write!(f, " {} ", operator.symbol())?;
+                rhs.fmt_operand(f, operator.precedence() + 1)
+            }
+            Expression::Conditional(ref condition, ref lhs, ref rhs) => {
+                condition.fmt_operand(f, PRECEDENCE_CONDITIONAL + 1)?;
+                write!(f, " ? ")?;
+                lhs.fmt_operand(f, PRECEDENCE_CONDITIONAL + 1)?;
+                write!(f, " : ")?;
+                rhs.fmt_operand(f, PRECEDENCE_CONDITIONAL)
+            }
+            Expression::Assign(ref target, ref value) => {
+                target.fmt_operand(f, PRECEDENCE_UNARY)?;
+                write!(f, " = ")?;
+                value.fmt_operand(f, PRECEDENCE_ASSIGN)
+            }
+            Expression::Increment(ref operand) => {
+                operand.fmt_operand(f, PRECEDENCE_PRIMARY)?;
+                write!(f, "++")
+            }
+            Expression::Decrement(ref operand) => {
+                operand.fmt_operand(f, PRECEDENCE_PRIMARY)?;
+                write!(f, "--")
+            }
+            Expression::Call(ref call) => write!(f, "{}", call),
+            Expression::Array(ref items) => {
+                let items: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+                write!(f, "{{{}}}", items.join(", "))
+            }
+            Expression::Tagged(ref tag, ref e) => {
+                write!(f, "{}:", tag)?;
+                e.fmt_operand(f, PRECEDENCE_UNARY)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BinaryOperator::*;
+    use super::{Expression, Register};
+
+    fn pri() -> Expression {
+        Expression::Register(Register::Pri)
+    }
+
+    #[test]
+    fn it_places_parentheses_by_precedence() {
+        let sum = Expression::binary(Add, Expression::Local(12), Expression::Cell(1));
+        let product = Expression::binary(Mul, sum.clone(), Expression::Cell(-2));
+        assert_eq!(product.to_string(), "(frame[12] + 1) * -2");
+
+        let difference = Expression::binary(Sub, pri(), sum);
+        assert_eq!(difference.to_string(), "pri - (frame[12] + 1)");
+
+        let index = Expression::Index(
+            Box::new(Expression::Global(0x10)),
+            Box::new(Expression::binary(Add, pri(), Expression::Cell(1))),
+        );
+        assert_eq!(
+            Expression::Assign(Box::new(index), Box::new(Expression::Cell(0))).to_string(),
+            "data[0x10][pri + 1] = 0"
+        );
+    }
+
+    #[test]
+    fn it_negates_conditions() {
+        let less = Expression::binary(Less, pri(), Expression::Cell(10));
+        assert_eq!(less.clone().negate().to_string(), "pri >= 10");
+
+        let both = Expression::binary(LogicalAnd, less, pri().negate());
+        assert_eq!(both.to_string(), "pri < 10 && !pri");
+        assert_eq!(both.negate().to_string(), "pri >= 10 || pri");
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::HashSet;
+
+use super::expression::{BinaryOperator, Expression, UnaryOperator};
+use super::function::Function;
+use super::TreeElementType::{self, DeclarationType, FunctionType};
+use crate::include::Database;
+
+const FLOAT_TAG: &str = "Float";
+
+/// Natives Pawn calls for the arithmetic operators on `Float:` values.
+const OPERATORS: &[(&str, BinaryOperator)] = &[
+    ("floatadd", BinaryOperator::Add),
+    ("floatsub", BinaryOperator::Sub),
+    ("floatmul", BinaryOperator::Mul),
+    ("floatdiv", BinaryOperator::Div),
+];
+
+/// Tags variables holding floats and renders their constants as float literals.
+///
+/// Floats are told by the tags of the natives they are passed to and returned from,
+/// `float_globals` come from the debug symbols. Cells of 8 bytes hold doubles.
+pub fn infer_floats(
+    elements: &mut [TreeElementType],
+    database: &Database,
+    float_globals: &HashSet<String>,
+    cellsize: usize,
+) {
+    // Functions might assign floats to globals used by the other functions
+    let mut globals = float_globals.clone();
+    loop {
+        let count = globals.len();
+        for element in elements.iter_mut() {
+            if let FunctionType(ref mut function) = *element {
+                let floats = Inference::new(function, database, &globals, cellsize).floats;
+                let locals = locals(function);
+                globals.extend(floats.into_iter().filter(|n| !locals.contains(n)));
+            }
+        }
+        if globals.len() == count {
+            break;
+        }
+    }
+
+    for element in elements.iter_mut() {
+        match *element {
+            FunctionType(ref mut function) => {
+                let inference = Inference::new(function, database, &globals, cellsize);
+                for parameter in function.parameters.iter_mut() {
+                    if inference.floats.contains(&parameter.name) {
+                        parameter.tag = Some(FLOAT_TAG.to_owned());
+                    }
+                }
+                for element in function.tree_elements.iter_mut() {
+                    each_element(element, &mut |e| inference.literals(e));
+                }
+            }
+            DeclarationType(_) => {
+                Inference::globals(database, &globals, cellsize).literals(element)
+            }
+            _ => (),
+        }
+
+        // Operators are rewritten last, natives tell the types of their operands
+        each_element(element, &mut |e| each_expression(e, &mut operators));
+    }
+}
+
+/// Float literal of the cell bits, none for infinities and NaNs which got no literal.
+pub fn float_literal(value: i64, cellsize: usize) -> Option<Expression> {
+    let float = match cellsize {
+        8 => f64::from_bits(value as u64),
+        // Shortest decimal of the single precision value, it reads as written
+        _ => f32::from_bits(value as u32).to_string().parse().ok()?,
+    };
+    Some(Expression::Float(float)).filter(|_| float.is_finite())
+}
+
+/// Calls the closure on the element and every element of its bodies.
+fn each_element(element: &mut TreeElementType, f: &mut dyn FnMut(&mut TreeElementType)) {
+    f(element);
+    for body in element.bodies_mut() {
+        for element in body.iter_mut() {
+            each_element(element, f);
+        }
+    }
+}
+
+/// Calls the closure on every expression of the element, subexpressions first.
+fn each_expression(element: &mut TreeElementType, f: &mut dyn FnMut(&mut Expression)) {
+    fn expression(e: &mut Expression, f: &mut dyn FnMut(&mut Expression)) {
+        for child in e.children_mut() {
+            expression(child, f);
+        }
+        f(e);
+    }
+
+    for e in element.expressions_mut() {
+        expression(e, f);
+    }
+}
+
+/// Rewrites calls of the operator natives back into the operators.
+fn operators(expression: &mut Expression) {
+    let replacement = match *expression {
+        Expression::Call(ref mut call) if call.args.len() == 2 => OPERATORS
+            .iter()
+            .find(|(name, _)| *name == call.name)
+            .map(|&(_, operator)| {
+                let rhs = call.args.pop().expect("checked above");
+                let lhs = call.args.pop().expect("checked above");
+                Expression::binary(operator, lhs, rhs)
+            }),
+        _ => None,
+    };
+
+    if let Some(replacement) = replacement {
+        *expression = replacement;
+    }
+}
+
+/// Names declared in the function, the rest of the variables are globals.
+fn locals(function: &mut Function) -> HashSet<String> {
+    fn declarations(elements: &mut [TreeElementType], locals: &mut HashSet<String>) {
+        for element in elements.iter_mut() {
+            if let DeclarationType(ref d) = *element {
+                locals.extend(name(&d.variable));
+            }
+            for body in element.bodies_mut() {
+                declarations(body, locals);
+            }
+        }
+    }
+
+    let mut locals: HashSet<String> = function.parameters.iter().map(|p| p.name.clone()).collect();
+    declarations(&mut function.tree_elements, &mut locals);
+    locals
+}
+
+/// Variable the expression refers to, whole arrays for their elements.
+fn name(expression: &Expression) -> Option<String> {
+    match *expression {
+        Expression::Variable(ref name) => Some(name.clone()),
+        Expression::Index(ref array, _) | Expression::Address(ref array) => self::name(array),
+        _ => None,
+    }
+}
+
+fn is_arithmetic(operator: BinaryOperator) -> bool {
+    matches!(
+        operator,
+        BinaryOperator::Add | BinaryOperator::Sub | BinaryOperator::Mul | BinaryOperator::Div
+    )
+}
+
+fn is_comparison(operator: BinaryOperator) -> bool {
+    matches!(
+        operator,
+        BinaryOperator::Eq
+            | BinaryOperator::Neq
+            | BinaryOperator::Less
+            | BinaryOperator::Leq
+            | BinaryOperator::Greater
+            | BinaryOperator::Geq
+    )
+}
+
+struct Inference<'a> {
+    database: &'a Database,
+    /// Variables holding floats, globals and locals of the function.
+    floats: HashSet<String>,
+    cellsize: usize,
+}
+
+impl<'a> Inference<'a> {
+    fn globals(
+        database: &'a Database,
+        globals: &HashSet<String>,
+        cellsize: usize,
+    ) -> Inference<'a> {
+        Inference {
+            database,
+            floats: globals.clone(),
+            cellsize,
+        }
+    }
+
+    /// Spreads floats over the function until they stop changing.
+    fn new(
+        function: &mut Function,
+        database: &'a Database,
+        globals: &HashSet<String>,
+        cellsize: usize,
+    ) -> Inference<'a> {
+        let mut inference = Inference::globals(database, globals, cellsize);
+        loop {
+            let mut found = vec![];
+            for element in function.tree_elements.iter_mut() {
+                each_element(element, &mut |element| {
+                    if let DeclarationType(ref d) = *element {
+                        if d.value.as_ref().is_some_and(|v| inference.is_float(v)) {
+                            found.extend(name(&d.variable));
+                        }
+                    }
+                    each_expression(element, &mut |e| found.extend(inference.assigned(e)));
+                });
+            }
+
+            let count = inference.floats.len();
+            inference.floats.extend(found);
+            if inference.floats.len() == count {
+                return inference;
+            }
+        }
+    }
+
+    fn is_float(&self, expression: &Expression) -> bool {
+        match *expression {
+            Expression::Float(_) => true,
+            Expression::Tagged(ref tag, _) => tag == FLOAT_TAG,
+            Expression::Variable(_) | Expression::Index(_, _) => {
+                name(expression).is_some_and(|n| self.floats.contains(&n))
+            }
+            Expression::Call(ref call) => self
+                .database
+                .get(&call.name)
+                .is_some_and(|s| s.tag.as_ref().is_some_and(|t| t == FLOAT_TAG)),
+            Expression::Binary(operator, ref lhs, ref rhs) if is_arithmetic(operator) => {
+                self.is_float(lhs) || self.is_float(rhs)
+            }
+            Expression::Unary(UnaryOperator::Negate, ref operand) => self.is_float(operand),
+            Expression::Conditional(_, ref lhs, ref rhs) => {
+                self.is_float(lhs) || self.is_float(rhs)
+            }
+            Expression::Assign(_, ref value) => self.is_float(value),
+            _ => false,
+        }
+    }
+
+    /// Variables the expression shows to be floats.
+    fn assigned(&self, expression: &Expression) -> Vec<String> {
+        match *expression {
+            Expression::Assign(ref target, ref value) if self.is_float(value) => {
+                name(target).into_iter().collect()
+            }
+            Expression::Binary(operator, ref lhs, ref rhs) if is_arithmetic(operator) => {
+                if self.is_float(expression) {
+                    name(lhs).into_iter().chain(name(rhs)).collect()
+                } else {
+                    vec![]
+                }
+            }
+            Expression::Call(ref call) => {
+                let signature = match self.database.get(&call.name) {
+                    Some(s) => s,
+                    None => return vec![],
+                };
+                call.args
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| signature.parameter(i).is_some_and(|p| p.has_tag(FLOAT_TAG)))
+                    .filter_map(|(_, arg)| name(arg))
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Renders constants of the element used as floats with float literals.
+    fn literals(&self, element: &mut TreeElementType) {
+        if let DeclarationType(ref mut d) = *element {
+            if name(&d.variable).is_some_and(|n| self.floats.contains(&n)) {
+                d.tag = Some(FLOAT_TAG.to_owned());
+                if let Some(ref mut value) = d.value {
+                    float_cells(value, self.cellsize);
+                }
+            }
+        }
+
+        each_expression(element, &mut |e| match *e {
+            Expression::Tagged(ref tag, ref value) if tag == FLOAT_TAG => {
+                if let Expression::Cell(v) = **value {
+                    if let Some(literal) = float_literal(v, self.cellsize) {
+                        *e = literal;
+                    }
+                }
+            }
+            Expression::Assign(ref target, ref mut value) if self.is_float(target) => {
+                float_cells(value, self.cellsize)
+            }
+            Expression::Call(ref mut call) => {
+                if let Some(signature) = self.database.get(&call.name) {
+                    for (i, arg) in call.args.iter_mut().enumerate() {
+                        if signature.parameter(i).is_some_and(|p| p.has_tag(FLOAT_TAG)) {
+                            float_cells(arg, self.cellsize);
+                        }
+                    }
+                }
+            }
+            Expression::Binary(operator, ref mut lhs, ref mut rhs)
+                if is_arithmetic(operator) || is_comparison(operator) =>
+            {
+                if self.is_float(lhs) {
+                    float_cells(rhs, self.cellsize);
+                } else if self.is_float(rhs) {
+                    float_cells(lhs, self.cellsize);
+                }
+            }
+            _ => (),
+        });
+    }
+}
+
+/// Replaces the cell, or cells of the array initializer, with float literals.
+fn float_cells(expression: &mut Expression, cellsize: usize) {
+    match *expression {
+        Expression::Cell(v) => {
+            if let Some(literal) = float_literal(v, cellsize) {
+                *expression = literal;
+            }
+        }
+        Expression::Array(ref mut items) => items.iter_mut().for_each(|i| float_cells(i, cellsize)),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::infer_floats;
+    use crate::ast::expression::{BinaryOperator, Expression};
+    use crate::ast::function::{Function, FunctionVisibility};
+    use crate::ast::function_call::FunctionCall;
+    use crate::ast::variable::{Declaration, Parameter};
+    use crate::ast::TreeElement;
+    use crate::ast::TreeElementType::{self, *};
+    use crate::include::{parse, Database};
+
+    fn variable(name: &str) -> Expression {
+        Expression::Variable(name.to_owned())
+    }
+
+    fn call(name: &str, args: Vec<Expression>) -> Expression {
+        Expression::Call(FunctionCall {
+            name: name.to_owned(),
+            args,
+        })
+    }
+
+    fn declaration(name: &str, value: Option<Expression>) -> TreeElementType {
+        DeclarationType(Declaration {
+            variable: variable(name),
+            tag: None,
+            dimensions: vec![],
+            value,
+        })
+    }
+
+    #[test]
+    fn it_infers_floats_from_natives() {
+        let mut elements = vec![
+            declaration("g_var0", Some(Expression::Cell(0x4348_0000))),
+            FunctionType(Function {
+                name: "speed".to_owned(),
+                address: 0x08,
+                parameters: vec![Parameter {
+                    name: "arg0".to_owned(),
+                    tag: None,
+                    is_array: false,
+                }],
+                tree_elements: vec![
+                    declaration(
+                        "var0",
+                        Some(call("floatmul", vec![variable("arg0"), variable("g_var0")])),
+                    ),
+                    ExpressionType(Expression::Assign(
+                        Box::new(variable("var0")),
+                        Box::new(Expression::Cell(0x3FC0_0000)),
+                    )),
+                    ReturnType(Some(Expression::binary(
+                        BinaryOperator::Greater,
+                        variable("var0"),
+                        Expression::Cell(0),
+                    ))),
+                ],
+                visibility: FunctionVisibility::Stock,
+            }),
+        ];
+
+        infer_floats(&mut elements, &Database::builtin(), &HashSet::new(), 4);
+        let source: String = elements.iter().map(|e| e.to_string(0).unwrap()).collect();
+        assert_eq!(
+            source,
+            "new Float:g_var0 = 200.0;\nspeed (Float:arg0) {\n  new Float:var0 = arg0 * g_var0;\n  var0 = 1.5;\n  return var0 > 0.0;\n}\n\n"
+        );
+    }
+
+    #[test]
+    fn it_tags_globals_from_debug_symbols() {
+        let mut elements = vec![declaration("g_var0", Some(Expression::Cell(0x3F80_0000)))];
+        let globals = vec!["g_var0".to_owned()].into_iter().collect();
+
+        infer_floats(&mut elements, &Database::new(), &globals, 4);
+        assert_eq!(
+            elements[0].to_string(0).unwrap(),
+            "new Float:g_var0 = 1.0;\n"
+        );
+    }
+
+    #[test]
+    fn it_reads_doubles_from_64_bit_cells() {
+        let mut database = Database::new();
+        database.extend(parse("native set_speed(index, Float:speed);").unwrap());
+        let speed = |cell, cellsize| {
+            let mut elements = vec![FunctionType(Function {
+                name: "plugin_init".to_owned(),
+                address: 0x08,
+                parameters: vec![],
+                tree_elements: vec![ExpressionType(call(
+                    "set_speed",
+                    vec![Expression::Cell(1), Expression::Cell(cell)],
+                ))],
+                visibility: FunctionVisibility::Public,
+            })];
+            infer_floats(&mut elements, &database, &HashSet::new(), cellsize);
+            elements[0].to_string(0).unwrap()
+        };
+
+        assert_eq!(
+            speed(0x4069_0000_0000_0000, 8),
+            "public plugin_init () {\n  set_speed(1, 200.0);\n}\n\n"
+        );
+        assert_eq!(
+            speed(0x3DCC_CCCD, 4),
+            "public plugin_init () {\n  set_speed(1, 0.1);\n}\n\n"
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashSet;
+
+use super::expression::Expression;
+use super::function::Function;
+use super::variable::Parameter;
+use super::TreeElementType::{self, DeclarationType};
+
+/// Cells between the frame and the first parameter:
+/// previous frame, return address and size of the arguments.
+const PARAMETERS_OFFSET: i64 = 3;
+
+/// Names parameters and locals of the function, `arg0` and `var0`, instead of frame offsets.
+///
+/// Callers pass at least `argument_count` arguments, parameters used at higher offsets
+/// are added to them.
+pub fn name_variables(function: &mut Function, cellsize: usize, argument_count: usize) {
+    let mut namer = Namer {
+        cellsize: cellsize as i64,
+        locals: 0,
+        parameters: argument_count,
+        arrays: HashSet::new(),
+    };
+    namer.body(&mut function.tree_elements, vec![]);
+
+    function.parameters = (0..namer.parameters)
+        .map(|i| Parameter {
+            name: format!("arg{}", i),
+            tag: None,
+            is_array: namer.arrays.contains(&i),
+        })
+        .collect();
+}
+
+/// Local declared in the enclosing statement lists.
+#[derive(Clone)]
+struct Local {
+    offset: i64,
+    /// Number of cells for arrays.
+    size: Option<usize>,
+    name: String,
+}
+
+struct Namer {
+    cellsize: i64,
+    /// Locals named so far.
+    locals: usize,
+    parameters: usize,
+    /// Parameters indexed as arrays.
+    arrays: HashSet<usize>,
+}
+
+impl Namer {
+    /// Locals declared in the statement list are only visible in it,
+    /// sibling lists might place their locals at the same offsets.
+    fn body(&mut self, elements: &mut [TreeElementType], mut scope: Vec<Local>) {
+        for element in elements.iter_mut() {
+            if let DeclarationType(ref mut declaration) = *element {
+                if let Some(ref mut value) = declaration.value {
+                    self.expression(value, &scope);
+                }
+
+                if let Expression::Local(offset) = declaration.variable {
+                    let name = format!("var{}", self.locals);
+                    self.locals += 1;
+                    scope.push(Local {
+                        offset,
+                        size: declaration.dimensions.first().cloned(),
+                        name: name.clone(),
+                    });
+                    declaration.variable = Expression::Variable(name);
+                }
+                continue;
+            }
+
+            for expression in element.expressions_mut() {
+                self.expression(expression, &scope);
+            }
+            for body in element.bodies_mut() {
+                self.body(body, scope.clone());
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &mut Expression, scope: &[Local]) {
+        let replacement = match *expression {
+            Expression::Local(offset) => self.variable(offset, scope),
+            Expression::Index(ref mut array, ref mut index) => {
+                if let Expression::Local(offset) = **array {
+                    if let Some(a) = self.array(offset, scope, true) {
+                        **array = a;
+                    }
+                }
+                self.expression(array, scope);
+                self.expression(index, scope);
+                None
+            }
+            Expression::Address(ref mut variable) => {
+                if let Expression::Local(offset) = **variable {
+                    if let Some(a) = self.array(offset, scope, false) {
+                        **variable = a;
+                    }
+                }
+                self.expression(variable, scope);
+                None
+            }
+            _ => {
+                for child in expression.children_mut() {
+                    self.expression(child, scope);
+                }
+                None
+            }
+        };
+
+        if let Some(replacement) = replacement {
+            *expression = replacement;
+        }
+    }
+
+    /// Whole array starting at the offset, parameters hold addresses of arrays.
+    fn array(&mut self, offset: i64, scope: &[Local], is_indexed: bool) -> Option<Expression> {
+        if let Some(index) = self.parameter(offset) {
+            if is_indexed {
+                self.arrays.insert(index);
+            }
+            return Some(Expression::Variable(format!("arg{}", index)));
+        }
+
+        scope
+            .iter()
+            .rev()
+            .find(|l| l.offset == offset && l.size.is_some())
+            .map(|l| Expression::Variable(l.name.clone()))
+    }
+
+    /// Variable stored at the offset, cells of the arrays are their elements.
+    fn variable(&mut self, offset: i64, scope: &[Local]) -> Option<Expression> {
+        if let Some(index) = self.parameter(offset) {
+            return Some(Expression::Variable(format!("arg{}", index)));
+        }
+
+        let local = scope.iter().rev().find(|l| {
+            let size = l.size.unwrap_or(1) as i64;
+            offset >= l.offset && offset < l.offset + size * self.cellsize
+        })?;
+        let variable = Expression::Variable(local.name.clone());
+
+        Some(match local.size {
+            Some(_) => Expression::Index(
+                Box::new(variable),
+                Box::new(Expression::Cell((offset - local.offset) / self.cellsize)),
+            ),
+            None => variable,
+        })
+    }
+
+    fn parameter(&mut self, offset: i64) -> Option<usize> {
+        if offset % self.cellsize != 0 || offset < PARAMETERS_OFFSET * self.cellsize {
+            return None;
+        }
+
+        let index = (offset / self.cellsize - PARAMETERS_OFFSET) as usize;
+        self.parameters = self.parameters.max(index + 1);
+        Some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::name_variables;
+    use crate::ast::expression::{BinaryOperator, Expression};
+    use crate::ast::function::{Function, FunctionVisibility};
+    use crate::ast::variable::Declaration;
+    use crate::ast::TreeElement;
+    use crate::ast::TreeElementType::*;
+
+    fn local(offset: i64) -> Box<Expression> {
+        Box::new(Expression::Local(offset))
+    }
+
+    #[test]
+    fn it_names_parameters_and_locals() {
+        let mut function = Function {
+            name: "give".to_owned(),
+            address: 0x20,
+            parameters: vec![],
+            tree_elements: vec![
+                DeclarationType(Declaration {
+                    variable: Expression::Local(-4),
+                    tag: None,
+                    dimensions: vec![],
+                    value: Some(Expression::Local(16)),
+                }),
+                DeclarationType(Declaration {
+                    variable: Expression::Local(-12),
+                    tag: None,
+                    dimensions: vec![2],
+                    value: None,
+                }),
+                ExpressionType(Expression::Assign(
+                    local(-8),
+                    Box::new(Expression::Index(local(12), local(-4))),
+                )),
+                ReturnType(Some(Expression::binary(
+                    BinaryOperator::Add,
+                    Expression::Address(local(-12)),
+                    Expression::Local(-12),
+                ))),
+            ],
+            visibility: FunctionVisibility::Stock,
+        };
+
+        name_variables(&mut function, 4, 0);
+        assert_eq!(
+            function.to_string(0).unwrap(),
+            "give (arg0[], arg1) {\n  new var0 = arg1;\n  new var1[2];\n  var1[1] = arg0[var0];\n  return var1 + var1[0];\n}\n\n"
+        );
+    }
+
+    #[test]
+    fn it_takes_parameters_count_from_callers() {
+        let mut function = Function {
+            name: "give".to_owned(),
+            address: 0x20,
+            parameters: vec![],
+            tree_elements: vec![],
+            visibility: FunctionVisibility::Stock,
+        };
+
+        name_variables(&mut function, 4, 2);
+        assert_eq!(function.to_string(0).unwrap(), "give (arg0, arg1) {\n}\n\n");
+    }
+}
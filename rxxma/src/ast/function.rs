@@ -1,10 +1,15 @@
-use super::super::amx::Opcode;
-use super::super::amx::Public;
+use super::variable::Parameter;
 use super::TreeElement;
 use super::TreeElementType;
+use amxmodx_utils::amx::debug::DebugInfo;
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::tables::Public;
+use amxmodx_utils::amx::UCell;
+use serde::Serialize;
 use std::fmt;
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum FunctionVisibility {
     Public,
     Stock,
@@ -20,17 +25,23 @@ impl fmt::Display for FunctionVisibility {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Function {
     pub name: String,
+    /// Address of the `proc` opcode, target of calls.
+    pub address: UCell,
+    pub parameters: Vec<Parameter>,
     pub tree_elements: Vec<TreeElementType>,
     pub visibility: FunctionVisibility,
 }
 
 impl Function {
-    pub fn from(opcode: &Opcode, public_list: &[Public]) -> Function {
-        static mut STOCK_FUNCTION_COUNTER: u32 = 0;
-        let opcode_public = public_list.iter().find(|x| x.address == opcode.address);
+    pub fn from(
+        opcode: &Opcode,
+        public_list: &[Public],
+        debug_info: Option<&DebugInfo>,
+    ) -> Function {
+        let opcode_public = public_list.iter().find(|x| x.address == opcode.address());
 
         let visibility = if opcode_public.is_some() {
             FunctionVisibility::Public
@@ -38,18 +49,21 @@ impl Function {
             FunctionVisibility::Stock
         };
 
+        let debug_symbol = debug_info.and_then(|d| d.function(opcode.address()));
+
         let name = if let Some(p) = opcode_public {
-            p.name.to_str().unwrap().to_string()
+            p.name.clone()
+        } else if let Some(s) = debug_symbol {
+            s.name.clone()
         } else {
-            // I like to live dangerously
-            unsafe {
-                STOCK_FUNCTION_COUNTER += 1;
-                format!("sub_{:x}", STOCK_FUNCTION_COUNTER - 1)
-            }
+            // Same as the call graph names it in listings
+            format!("sub_{:x}", opcode.address())
         };
 
         Function {
             name,
+            address: opcode.address(),
+            parameters: vec![],
             tree_elements: vec![],
             visibility,
         }
@@ -60,10 +74,12 @@ impl TreeElement for Function {
     fn to_string(&self, ident: usize) -> Result<String, &'static str> {
         let mut source = String::new();
 
+        let parameters: Vec<String> = self.parameters.iter().map(|p| p.to_string()).collect();
         source.push_str(&format!(
-            "{visibility}{fname} () {{\n",
+            "{visibility}{fname} ({parameters}) {{\n",
             visibility = self.visibility,
-            fname = self.name
+            fname = self.name,
+            parameters = parameters.join(", ")
         ));
 
         for element in self.tree_elements.iter() {
@@ -75,3 +91,41 @@ impl TreeElement for Function {
         Ok(source)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Function, FunctionVisibility};
+    use amxmodx_utils::amx::debug::{DebugInfo, Symbol, SymbolClass, SymbolKind};
+    use amxmodx_utils::amx::opcode::{Opcode, Operands};
+    use amxmodx_utils::amx::opcode_type::OpcodeType;
+
+    #[test]
+    fn it_takes_stock_name_from_debug_info() {
+        let opcode = Opcode::new(0x20, OpcodeType::OpProc, Operands::Cells(vec![]));
+        let debug_info = DebugInfo {
+            files: vec![],
+            lines: vec![],
+            symbols: vec![Symbol {
+                address: 0x20,
+                tag: 0,
+                codestart: 0x20,
+                codeend: 0x40,
+                kind: SymbolKind::Function,
+                class: SymbolClass::Global,
+                dimensions: vec![],
+                name: "give_weapon".to_owned(),
+            }],
+            tags: vec![],
+            automatons: vec![],
+            states: vec![],
+        };
+
+        let function = Function::from(&opcode, &[], Some(&debug_info));
+        assert_eq!(function.name, "give_weapon");
+        assert_eq!(function.address, 0x20);
+        assert_eq!(function.visibility, FunctionVisibility::Stock);
+
+        let function = Function::from(&opcode, &[], None);
+        assert_eq!(function.name, "sub_20");
+    }
+}
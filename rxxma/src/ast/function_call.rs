@@ -1,54 +1,16 @@
-use super::TreeElement;
-use crate::amx::plugin::ConstantParam;
-use std::convert::From;
-use std::ffi::CString;
+use super::expression::Expression;
+use serde::Serialize;
+use std::fmt;
 
-#[derive(Debug, Clone)]
-pub enum Argument {
-    String(CString),
-    Cell(u32),
-}
-
-impl From<ConstantParam> for Argument {
-    fn from(constant: ConstantParam) -> Self {
-        match constant {
-            ConstantParam::Cell(v) => Argument::Cell(v),
-            ConstantParam::String(v) => Argument::String(v),
-        }
-    }
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct FunctionCall {
     pub name: String,
-    pub args: Option<Vec<Argument>>,
+    pub args: Vec<Expression>,
 }
 
-impl TreeElement for FunctionCall {
-    fn to_string(&self, ident: usize) -> Result<String, &'static str> {
-        let mut source = String::new();
-
-        // Push ident
-        source.push_str(&format!("{:>width$}", "", width = (2 * ident)));
-
-        let args: String = self
-            .args
-            .clone()
-            .unwrap_or_else(|| vec![])
-            .into_iter()
-            .map(|arg| match arg {
-                Argument::String(s) => format!("{:?}", s),
-                Argument::Cell(n) => format!("{}", n),
-            })
-            .collect::<Vec<String>>()
-            .join(", ");
-
-        source.push_str(&format!(
-            "{native}({args});\n",
-            native = self.name,
-            args = args
-        ));
-
-        Ok(source)
+impl fmt::Display for FunctionCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let args: Vec<String> = self.args.iter().map(|arg| arg.to_string()).collect();
+        write!(f, "{}({})", self.name, args.join(", "))
     }
 }
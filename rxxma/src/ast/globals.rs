@@ -0,0 +1,209 @@
+use super::expression::Expression;
+use super::variable::Declaration;
+use super::TreeElementType;
+use crate::analysis::data::{DataLayout, Global, GlobalKind};
+use amxmodx_utils::amx::UCell;
+
+/// Declarations of the named globals with their initial values.
+pub fn declarations(layout: &DataLayout, cellsize: usize) -> Vec<TreeElementType> {
+    layout
+        .globals
+        .iter()
+        .filter_map(|global| {
+            let name = global.name.clone()?;
+            let (dimensions, value) = match global.kind {
+                GlobalKind::Variable => {
+                    let value = global.cells.first().cloned().unwrap_or(0);
+                    (vec![], Some(Expression::Cell(value)).filter(|_| value != 0))
+                }
+                GlobalKind::Array {
+                    ref dimensions,
+                    element_size,
+                } => {
+                    let value = if element_size == cellsize {
+                        initializer(global, dimensions)
+                    } else {
+                        None
+                    };
+                    (dimensions.clone(), value)
+                }
+                GlobalKind::String(_) => return None,
+            };
+
+            Some(TreeElementType::DeclarationType(Declaration {
+                variable: Expression::Variable(name),
+                tag: None,
+                dimensions,
+                value,
+            }))
+        })
+        .collect()
+}
+
+/// Initial value of the array, none for zeroed arrays.
+fn initializer(global: &Global, dimensions: &[usize]) -> Option<Expression> {
+    match *dimensions {
+        [_] => row(&global.cells),
+        [rows, size] => {
+            // Rows follow their indirection vector
+            let values: Vec<_> = global.cells.get(rows..)?.chunks(size).map(row).collect();
+            let used = values.iter().rposition(Option::is_some)? + 1;
+            let zeroed = || Expression::Array(vec![Expression::Cell(0)]);
+            Some(Expression::Array(
+                values
+                    .into_iter()
+                    .take(used)
+                    .map(|v| v.unwrap_or_else(zeroed))
+                    .collect(),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Initializer of one-dimensional array, a string when it holds printable characters.
+fn row(cells: &[i64]) -> Option<Expression> {
+    let length = cells.iter().position(|&c| c == 0).unwrap_or(cells.len());
+    let is_string = length > 0
+        && cells[length..].iter().all(|&c| c == 0)
+        && cells[..length]
+            .iter()
+            .all(|&c| (0x20..0x7F).contains(&c) || c == 0x09 || c == 0x0A);
+    if is_string {
+        let string = cells[..length].iter().map(|&c| c as u8 as char).collect();
+        return Some(Expression::String(string));
+    }
+
+    let used = cells.iter().rposition(|&c| c != 0)? + 1;
+    Some(Expression::Array(
+        cells[..used].iter().map(|&c| Expression::Cell(c)).collect(),
+    ))
+}
+
+/// Refers to globals by their names instead of DAT addresses.
+pub fn name_globals(elements: &mut [TreeElementType], layout: &DataLayout) {
+    for element in elements.iter_mut() {
+        for e in element.expressions_mut() {
+            expression(e, layout);
+        }
+        for body in element.bodies_mut() {
+            name_globals(body, layout);
+        }
+    }
+}
+
+fn expression(expression: &mut Expression, layout: &DataLayout) {
+    let replacement = match *expression {
+        Expression::Global(address) => element(address, layout),
+        Expression::Index(ref mut array, ref mut index) => {
+            whole_at(array, layout);
+            self::expression(array, layout);
+            self::expression(index, layout);
+            None
+        }
+        Expression::Address(ref mut variable) => {
+            whole_at(variable, layout);
+            self::expression(variable, layout);
+            None
+        }
+        _ => {
+            for child in expression.children_mut() {
+                self::expression(child, layout);
+            }
+            None
+        }
+    };
+
+    if let Some(replacement) = replacement {
+        *expression = replacement;
+    }
+}
+
+/// Names the global referred to as a whole, e.g. indexed array.
+fn whole_at(expression: &mut Expression, layout: &DataLayout) {
+    if let Expression::Global(address) = *expression {
+        let name = layout
+            .global_at(address)
+            .filter(|g| g.address == address)
+            .and_then(|g| g.name.clone());
+        if let Some(name) = name {
+            *expression = Expression::Variable(name);
+        }
+    }
+}
+
+/// Variable stored at the address, cells of the arrays are their elements.
+fn element(address: UCell, layout: &DataLayout) -> Option<Expression> {
+    let global = layout.global_at(address)?;
+    let variable = Expression::Variable(global.name.clone()?);
+
+    match global.kind {
+        GlobalKind::Array { element_size, .. } => {
+            let index = (address - global.address) as usize / element_size;
+            Some(Expression::Index(
+                Box::new(variable),
+                Box::new(Expression::Cell(index as i64)),
+            ))
+        }
+        _ => Some(variable),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{declarations, name_globals};
+    use crate::analysis::data::{DataLayout, Global, GlobalKind};
+    use crate::ast::expression::Expression;
+    use crate::ast::TreeElement;
+    use crate::ast::TreeElementType::{self, ExpressionType};
+
+    fn layout() -> DataLayout {
+        DataLayout {
+            globals: vec![
+                Global {
+                    address: 0,
+                    size: 4,
+                    name: Some("g_var0".to_owned()),
+                    kind: GlobalKind::Variable,
+                    cells: vec![5],
+                },
+                Global {
+                    address: 4,
+                    size: 12,
+                    name: Some("g_var1".to_owned()),
+                    kind: GlobalKind::Array {
+                        dimensions: vec![3],
+                        element_size: 4,
+                    },
+                    cells: vec![b'h' as i64, b'i' as i64, 0],
+                },
+            ],
+        }
+    }
+
+    fn render(elements: &[TreeElementType]) -> String {
+        elements.iter().map(|e| e.to_string(0).unwrap()).collect()
+    }
+
+    #[test]
+    fn it_declares_globals_with_initial_values() {
+        assert_eq!(
+            render(&declarations(&layout(), 4)),
+            "new g_var0 = 5;\nnew g_var1[3] = \"hi\";\n"
+        );
+    }
+
+    #[test]
+    fn it_refers_to_globals_by_name() {
+        let mut elements = vec![ExpressionType(Expression::Assign(
+            Box::new(Expression::Index(
+                Box::new(Expression::Global(4)),
+                Box::new(Expression::Global(0)),
+            )),
+            Box::new(Expression::Global(8)),
+        ))];
+        name_globals(&mut elements, &layout());
+
+        assert_eq!(render(&elements), "g_var1[g_var0] = g_var1[1];\n");
+    }
+}
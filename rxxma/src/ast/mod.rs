@@ -1,11 +1,24 @@
+mod control_flow;
+mod dataflow;
 mod decompiler;
+mod expression;
+mod float;
+mod frame;
 mod function;
 mod function_call;
+mod globals;
 mod plugin;
+mod structure;
 mod tree_element;
+mod typing;
+mod variable;
 
+pub use self::control_flow::*;
 pub use self::decompiler::Decompiler;
+pub use self::expression::*;
 pub use self::function::*;
+pub use self::function_call::FunctionCall;
 pub use self::plugin::Plugin;
 pub use self::tree_element::TreeElement;
 pub use self::tree_element::TreeElementType;
+pub use self::variable::{Declaration, Parameter};
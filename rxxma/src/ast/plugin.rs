@@ -1,8 +1,10 @@
-use super::super::amx::Opcode;
 use super::TreeElement;
 use super::TreeElementType;
 use super::TreeElementType::*;
+use amxmodx_utils::amx::opcode::Opcode;
+use serde::Serialize;
 
+#[derive(Serialize)]
 pub struct Plugin {
     pub tree_elements: Vec<TreeElementType>,
 }
@@ -24,8 +26,15 @@ impl TreeElement for Plugin {
     fn to_string(&self, ident: usize) -> Result<String, &'static str> {
         let mut source = String::from("// Plugin source approximation starts here\n\n");
 
+        let mut is_declaration = false;
         for tree_element in self.tree_elements.iter() {
-            let element_str = &tree_element.to_string(ident + 1)?;
+            // Globals are declared at the top level, separated from functions
+            let element_str = match *tree_element {
+                DeclarationType(_) => tree_element.to_string(ident)?,
+                _ if is_declaration => format!("\n{}", tree_element.to_string(ident + 1)?),
+                _ => tree_element.to_string(ident + 1)?,
+            };
+            is_declaration = matches!(*tree_element, DeclarationType(_));
             source.push_str(&element_str);
         }
 
@@ -0,0 +1,686 @@
+//! Recovery of control flow statements from jumps.
+//!
+//! Pawn compiler emits code in source order, so statements are recognized by
+//! the layout of basic blocks:
+//!
+//! * `if`: `jcc ELSE; then; jump END; ELSE: else; END:`
+//! * `while`: `LOOP: test; jcc EXIT; body; jump LOOP; EXIT:`
+//! * `do-while`: `LOOP: body; test; jcc LOOP`
+//! * `for`: `init; jump TEST; INC: increment; TEST: test; jcc EXIT; body; jump INC; EXIT:`
+//! * `switch`: `switch TABLE; cases each ending with jump EXIT; TABLE: casetbl; EXIT:`
+
+use std::collections::{BTreeMap, HashSet};
+
+use super::control_flow::{Case, Condition, DoWhile, For, If, Switch, While};
+use super::expression::{Expression, Register};
+use super::TreeElementType;
+use super::TreeElementType::*;
+use crate::analysis::cfg::{BlockId, Cfg, Dominators, EdgeKind};
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::opcode_type::OpcodeType::{self, *};
+use amxmodx_utils::amx::UCell;
+
+const CONDITIONAL_JUMPS: &[OpcodeType] = &[
+    OpJzer, OpJnz, OpJeq, OpJneq, OpJless, OpJleq, OpJgrtr, OpJgeq, OpJsless, OpJsleq, OpJsgrtr,
+    OpJsgeq,
+];
+
+/// Consecutive blocks forming a statement list.
+#[derive(Clone, Copy)]
+struct Region {
+    start: BlockId,
+    /// Exclusive.
+    end: BlockId,
+    /// Block where control goes after falling off the region.
+    follow: Option<BlockId>,
+    break_target: Option<BlockId>,
+    continue_target: Option<BlockId>,
+    /// Block ending with the `do-while` check, its jump is not a statement.
+    condition_block: Option<BlockId>,
+}
+
+struct Structurer<'a> {
+    cfg: &'a Cfg,
+    dominators: Dominators,
+    /// Latches of loops already recovered.
+    latches: HashSet<BlockId>,
+}
+
+/// Builds statements out of function opcodes, `proc` excluded.
+///
+/// Fails on code which does not look like Pawn compiler output.
+pub fn structure(opcodes: &[Opcode]) -> Result<Vec<TreeElementType>, &'static str> {
+    let cfg = Cfg::from_opcodes(opcodes).map_err(|_| "Invalid control flow graph")?;
+    let mut structurer = Structurer {
+        cfg: &cfg,
+        dominators: cfg.dominators(),
+        latches: HashSet::new(),
+    };
+
+    structurer.structure_region(Region {
+        start: 0,
+        end: cfg.blocks().len(),
+        follow: None,
+        break_target: None,
+        continue_target: None,
+        condition_block: None,
+    })
+}
+
+fn statements(opcodes: &[Opcode]) -> Vec<TreeElementType> {
+    opcodes.iter().cloned().map(OpcodeType).collect()
+}
+
+impl<'a> Structurer<'a> {
+    fn structure_region(&mut self, region: Region) -> Result<Vec<TreeElementType>, &'static str> {
+        let mut elements = vec![];
+        let mut block = region.start;
+        while block < region.end {
+            block = self.structure_statement(block, region, &mut elements)?;
+        }
+        Ok(elements)
+    }
+
+    /// Adds statement starting at the block, returns the block following it.
+    fn structure_statement(
+        &mut self,
+        block: BlockId,
+        region: Region,
+        elements: &mut Vec<TreeElementType>,
+    ) -> Result<BlockId, &'static str> {
+        if self.is_for_latch(block) {
+            return self.structure_for(block, region, elements);
+        }
+        if let Some(latch) = self.find_latch(block, region) {
+            return self.structure_loop(block, latch, region, elements);
+        }
+
+        let opcodes = &self.cfg.blocks()[block].opcodes;
+        let (terminator, body) = opcodes.split_last().expect("blocks are not empty");
+
+        if region.condition_block == Some(block) {
+            elements.extend(statements(body));
+            return Ok(block + 1);
+        }
+
+        match terminator.code() {
+            OpJump => {
+                elements.extend(statements(body));
+                self.structure_jump(block, region, elements)?;
+                Ok(block + 1)
+            }
+            OpRetn => {
+                elements.extend(statements(body));
+                elements.push(ReturnType(None));
+                Ok(block + 1)
+            }
+            OpSwitch => {
+                elements.extend(statements(body));
+                self.structure_switch(block, region, elements)
+            }
+            code if CONDITIONAL_JUMPS.contains(&code) => {
+                elements.extend(statements(body));
+                self.structure_if(block, region, elements)
+            }
+            OpJrel | OpJumpPri | OpRet | OpHalt => Err("Unsupported jump"),
+            _ => {
+                elements.extend(statements(opcodes));
+                Ok(block + 1)
+            }
+        }
+    }
+
+    fn structure_jump(
+        &self,
+        block: BlockId,
+        region: Region,
+        elements: &mut Vec<TreeElementType>,
+    ) -> Result<(), &'static str> {
+        let target = self.cfg.blocks()[block].successors[0].target;
+
+        if block + 1 == region.end && Some(target) == region.follow {
+            // Control gets there anyway
+        } else if Some(target) == region.break_target {
+            elements.push(BreakType);
+        } else if Some(target) == region.continue_target {
+            elements.push(ContinueType);
+        } else if target == block + 1 && target < region.end {
+            // Jump to the next instruction
+        } else if target == block + 2 && self.is_for_latch(block + 1) {
+            // Skip `for` increment before the first iteration
+        } else {
+            return Err("Unstructured jump");
+        }
+
+        Ok(())
+    }
+
+    fn branch_target(&self, block: BlockId) -> Option<BlockId> {
+        self.cfg.blocks()[block]
+            .successors
+            .iter()
+            .find(|e| e.kind == EdgeKind::Branch)
+            .map(|e| e.target)
+    }
+
+    fn is_conditional(&self, block: BlockId) -> bool {
+        CONDITIONAL_JUMPS.contains(&self.cfg.blocks()[block].terminator().code())
+    }
+
+    /// Block with `for` increment, it precedes the loop condition and is skipped on entry.
+    fn is_for_latch(&self, block: BlockId) -> bool {
+        let blocks = self.cfg.blocks();
+        let header = block + 1;
+        if block == 0 || header >= blocks.len() {
+            return false;
+        }
+
+        let falls_into_header = blocks[block].successors.len() == 1
+            && blocks[block].successors[0].target == header
+            && blocks[block].successors[0].kind == EdgeKind::Fallthrough;
+        let is_latch =
+            self.dominators.dominates(header, block) || blocks[block].predecessors.is_empty();
+        let preheader = &blocks[block - 1];
+        let skipped = preheader.terminator().code() == OpJump
+            && preheader.successors.first().map(|e| e.target) == Some(header);
+
+        falls_into_header && is_latch && skipped
+    }
+
+    /// The outermost loop latch jumping back to the block.
+    fn find_latch(&self, header: BlockId, region: Region) -> Option<BlockId> {
+        (header..region.end).rev().find(|&latch| {
+            !self.latches.contains(&latch)
+                && self.cfg.blocks()[latch]
+                    .successors
+                    .iter()
+                    .any(|e| e.target == header)
+                && self.dominators.dominates(header, latch)
+        })
+    }
+
+    /// Check made by the block terminator, without its setup.
+    fn condition(&self, block: BlockId, negated: bool) -> Condition {
+        Condition::new(self.cfg.blocks()[block].terminator().code(), negated)
+    }
+
+    /// Block jumping out of the loop when its check fails.
+    ///
+    /// Check might be preceded by blocks computing its value, e.g. `a && b`,
+    /// those jump only forward and never past the check.
+    fn loop_test(&self, header: BlockId, exit: BlockId) -> Option<BlockId> {
+        let blocks = self.cfg.blocks();
+        let test = (header..exit)
+            .find(|&b| self.is_conditional(b) && self.branch_target(b) == Some(exit))?;
+
+        let is_value = (header..test).all(|b| {
+            blocks[b]
+                .successors
+                .iter()
+                .all(|e| e.target > b && e.target <= test)
+        });
+
+        if is_value {
+            Some(test)
+        } else {
+            None
+        }
+    }
+
+    /// Loop check, evaluated on each iteration along with the blocks preceding it.
+    fn loop_condition(
+        &mut self,
+        header: BlockId,
+        test: BlockId,
+    ) -> Result<Condition, &'static str> {
+        let mut setup = self.structure_region(Region {
+            start: header,
+            end: test,
+            follow: Some(test),
+            break_target: None,
+            continue_target: None,
+            condition_block: None,
+        })?;
+        let opcodes = &self.cfg.blocks()[test].opcodes;
+        setup.extend(statements(&opcodes[..opcodes.len() - 1]));
+
+        Ok(Condition {
+            setup,
+            ..self.condition(test, true)
+        })
+    }
+
+    fn structure_loop(
+        &mut self,
+        header: BlockId,
+        latch: BlockId,
+        region: Region,
+        elements: &mut Vec<TreeElementType>,
+    ) -> Result<BlockId, &'static str> {
+        let exit = latch + 1;
+        self.latches.insert(latch);
+
+        let loop_region = Region {
+            start: header,
+            end: exit,
+            follow: Some(header),
+            break_target: Some(exit),
+            continue_target: Some(header),
+            condition_block: None,
+        };
+
+        if self.is_conditional(latch) {
+            // Check setup stays at the end of the body
+            let body = self.structure_region(Region {
+                follow: None,
+                continue_target: Some(latch),
+                condition_block: Some(latch),
+                ..loop_region
+            })?;
+
+            elements.push(DoWhileType(DoWhile {
+                body,
+                condition: self.condition(latch, false),
+            }));
+        } else if self.cfg.blocks()[latch].terminator().code() == OpJump {
+            let test = if header != latch {
+                self.loop_test(header, exit)
+            } else {
+                None
+            };
+
+            let statement = if let Some(test) = test {
+                While {
+                    condition: Some(self.loop_condition(header, test)?),
+                    body: self.structure_region(Region {
+                        start: test + 1,
+                        ..loop_region
+                    })?,
+                }
+            } else {
+                While {
+                    condition: None,
+                    body: self.structure_region(loop_region)?,
+                }
+            };
+            elements.push(WhileType(statement));
+        } else {
+            return Err("Loop latch is not a jump");
+        }
+
+        debug_assert!(exit <= region.end);
+        Ok(exit)
+    }
+
+    fn structure_for(
+        &mut self,
+        latch: BlockId,
+        region: Region,
+        elements: &mut Vec<TreeElementType>,
+    ) -> Result<BlockId, &'static str> {
+        let blocks = self.cfg.blocks();
+        let header = latch + 1;
+        self.latches.insert(latch);
+
+        // Body ends with the last jump to the increment
+        let last = (header..blocks.len())
+            .rev()
+            .find(|&b| blocks[b].successors.iter().any(|e| e.target == latch));
+        let exit = match last {
+            Some(last) => last + 1,
+            None if self.is_conditional(header) => self
+                .branch_target(header)
+                .ok_or("Conditional jump got no target")?,
+            None => return Err("Endless for loop got no body"),
+        };
+        if exit > region.end {
+            return Err("For loop exceeds enclosing statement");
+        }
+
+        let test = self.loop_test(header, exit);
+        let condition = match test {
+            Some(test) => Some(self.loop_condition(header, test)?),
+            None => None,
+        };
+        let body = self.structure_region(Region {
+            start: test.map_or(header, |t| t + 1),
+            end: exit,
+            follow: Some(latch),
+            break_target: Some(exit),
+            continue_target: Some(latch),
+            condition_block: None,
+        })?;
+
+        elements.push(ForType(For {
+            condition,
+            increment: statements(&blocks[latch].opcodes),
+            body,
+        }));
+        Ok(exit)
+    }
+
+    fn structure_if(
+        &mut self,
+        block: BlockId,
+        region: Region,
+        elements: &mut Vec<TreeElementType>,
+    ) -> Result<BlockId, &'static str> {
+        let blocks = self.cfg.blocks();
+        let target = self
+            .branch_target(block)
+            .ok_or("Conditional jump got no target")?;
+
+        // Falling off the region goes to its follow, not the next block
+        let is_inside = target > block
+            && target <= region.end
+            && (target < region.end || region.follow == Some(target));
+        if !is_inside {
+            // Jump right out of the loop, as in `if (a) break;`
+            let statement = if Some(target) == region.break_target {
+                BreakType
+            } else if Some(target) == region.continue_target {
+                ContinueType
+            } else {
+                return Err("Conditional jump leaves enclosing statement");
+            };
+
+            elements.push(IfType(If {
+                condition: self.condition(block, false),
+                then_elements: vec![statement],
+                else_elements: None,
+            }));
+            return Ok(block + 1);
+        }
+
+        // Short-circuit `&&`, following checks skip the same statement
+        let mut last = block;
+        while last + 1 < target
+            && self.is_conditional(last + 1)
+            && self.branch_target(last + 1) == Some(target)
+            && blocks[last + 1].predecessors == [last]
+        {
+            last += 1;
+        }
+
+        let mut chain = None;
+        for part in (block + 1..=last).rev() {
+            let opcodes = &blocks[part].opcodes;
+            chain = Some(Box::new(Condition {
+                setup: statements(&opcodes[..opcodes.len() - 1]),
+                and: chain,
+                ..self.condition(part, true)
+            }));
+        }
+        let condition = Condition {
+            and: chain,
+            ..self.condition(block, true)
+        };
+
+        // Then branch jumping over the else branch
+        let mut else_end = None;
+        if target > last + 1 && region.condition_block != Some(target - 1) {
+            let last = &blocks[target - 1];
+            if last.terminator().code() == OpJump {
+                let end = last.successors[0].target;
+                if end > target
+                    && end <= region.end
+                    && (end < region.end || region.follow == Some(end))
+                {
+                    else_end = Some(end);
+                }
+            }
+        }
+
+        let follow = else_end.unwrap_or(target);
+        let branch = Region {
+            start: last + 1,
+            end: target,
+            follow: Some(follow),
+            condition_block: None,
+            ..region
+        };
+        let then_elements = self.structure_region(branch)?;
+        let else_elements = match else_end {
+            Some(end) => Some(self.structure_region(Region {
+                start: target,
+                end,
+                ..branch
+            })?),
+            None => None,
+        };
+
+        elements.push(IfType(If {
+            condition,
+            then_elements,
+            else_elements,
+        }));
+        Ok(follow)
+    }
+
+    fn structure_switch(
+        &mut self,
+        block: BlockId,
+        region: Region,
+        elements: &mut Vec<TreeElementType>,
+    ) -> Result<BlockId, &'static str> {
+        let blocks = self.cfg.blocks();
+        let table_address = blocks[block].terminator().argument().unwrap_or_default();
+        // Cases are followed by the case table and code after the switch
+        let exit = blocks
+            .iter()
+            .position(|b| b.address() > table_address)
+            .unwrap_or(blocks.len());
+        if exit > region.end {
+            return Err("Switch exceeds enclosing statement");
+        }
+
+        let mut case_values: BTreeMap<BlockId, Vec<UCell>> = BTreeMap::new();
+        let mut default = None;
+        for edge in blocks[block].successors.iter() {
+            match edge.kind {
+                EdgeKind::Case(value) => case_values.entry(edge.target).or_default().push(value),
+                EdgeKind::Default if edge.target != exit => default = Some(edge.target),
+                _ => {}
+            }
+        }
+
+        let mut starts: Vec<BlockId> = case_values.keys().cloned().chain(default).collect();
+        starts.sort_unstable();
+        if starts.windows(2).any(|w| w[0] == w[1]) {
+            return Err("Switch default shares body with a case");
+        }
+        if starts.first().is_some_and(|&s| s != block + 1) || starts.iter().any(|&s| s >= exit) {
+            return Err("Switch cases are outside of the switch");
+        }
+
+        let mut switch = Switch {
+            value: Expression::Register(Register::Pri),
+            cases: vec![],
+            default: None,
+        };
+        for (i, &start) in starts.iter().enumerate() {
+            let body = self.structure_region(Region {
+                start,
+                end: starts.get(i + 1).cloned().unwrap_or(exit),
+                follow: Some(exit),
+                condition_block: None,
+                ..region
+            })?;
+
+            match case_values.remove(&start) {
+                Some(values) => switch.cases.push(Case { values, body }),
+                None => switch.default = Some(body),
+            }
+        }
+
+        elements.push(SwitchType(switch));
+        Ok(exit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::structure;
+    use crate::ast::{TreeElement, TreeElementType};
+    use crate::util::tests::decode;
+    use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+
+    fn render(elements: &[TreeElementType]) -> String {
+        elements.iter().map(|e| e.to_string(0).unwrap()).collect()
+    }
+
+    #[test]
+    fn it_recovers_if_else() {
+        let opcodes = decode(&[
+            OpLoadSPri as u64, // 0x00
+            12,
+            OpJzer as u64, // 0x08
+            0x20,
+            OpConstPri as u64, // 0x10
+            1,
+            OpJump as u64, // 0x18
+            0x28,
+            OpConstPri as u64, // 0x20
+            2,
+            OpZeroPri as u64, // 0x28
+        ]);
+
+        assert_eq!(
+            render(&structure(&opcodes).unwrap()),
+            "#emit load.s.pri\t0xC\nif (pri) {\n  #emit const.pri\t0x1\n} else {\n  \
+             #emit const.pri\t0x2\n}\n#emit zero.pri\n"
+        );
+    }
+
+    #[test]
+    fn it_recovers_while_with_break() {
+        let opcodes = decode(&[
+            OpLoadSPri as u64, // 0x00
+            12,
+            OpJzer as u64, // 0x08
+            0x38,
+            OpLoadSPri as u64, // 0x10
+            16,
+            OpJzer as u64, // 0x18
+            0x28,
+            OpJump as u64, // 0x20
+            0x38,
+            OpIncS as u64, // 0x28
+            12,
+            OpJump as u64, // 0x30
+            0x00,
+            OpZeroPri as u64, // 0x38
+        ]);
+
+        assert_eq!(
+            render(&structure(&opcodes).unwrap()),
+            "while (#emit load.s.pri 0xC, pri) {\n  #emit load.s.pri\t0x10\n  \
+             if (pri) {\n    break;\n  }\n  #emit inc.s\t0xC\n}\n#emit zero.pri\n"
+        );
+    }
+
+    #[test]
+    fn it_recovers_do_while() {
+        let opcodes = decode(&[
+            OpIncS as u64, // 0x00
+            12,
+            OpLoadSPri as u64, // 0x08
+            12,
+            OpConstAlt as u64, // 0x10
+            10,
+            OpJsless as u64, // 0x18
+            0x00,
+            OpZeroPri as u64, // 0x20
+        ]);
+
+        assert_eq!(
+            render(&structure(&opcodes).unwrap()),
+            "do {\n  #emit inc.s\t0xC\n  #emit load.s.pri\t0xC\n  #emit const.alt\t0xA\n} \
+             while (pri < alt);\n#emit zero.pri\n"
+        );
+    }
+
+    #[test]
+    fn it_recovers_for_with_continue() {
+        // for (i = 0; i < 10; i++) { if (i) continue; }
+        let opcodes = decode(&[
+            OpZeroS as u64, // 0x00
+            12,
+            OpJump as u64, // 0x08
+            0x18,
+            OpIncS as u64, // 0x10
+            12,
+            OpLoadSPri as u64, // 0x18
+            12,
+            OpConstAlt as u64, // 0x20
+            10,
+            OpJsgeq as u64, // 0x28
+            0x50,
+            OpLoadSPri as u64, // 0x30
+            12,
+            OpJzer as u64, // 0x38
+            0x48,
+            OpJump as u64, // 0x40
+            0x10,
+            OpJump as u64, // 0x48
+            0x10,
+            OpZeroPri as u64, // 0x50
+        ]);
+
+        assert_eq!(
+            render(&structure(&opcodes).unwrap()),
+            "#emit zero.s\t0xC\nfor (; #emit load.s.pri 0xC, #emit const.alt 0xA, pri < alt; \
+             #emit inc.s 0xC) {\n  #emit load.s.pri\t0xC\n  if (pri) {\n    continue;\n  }\n}\n\
+             #emit zero.pri\n"
+        );
+    }
+
+    #[test]
+    fn it_recovers_switch() {
+        // switch (a) { case 1, 2: a = 5; default: return; }
+        let opcodes = decode(&[
+            OpLoadSPri as u64, // 0x00
+            12,
+            OpSwitch as u64, // 0x08
+            0x2C,
+            OpConstPri as u64, // 0x10
+            5,
+            OpJump as u64, // 0x18
+            0x48,
+            OpRetn as u64, // 0x20
+            OpJump as u64, // 0x24
+            0x48,
+            OpCasetbl as u64, // 0x2C
+            2,
+            0x20,
+            1,
+            0x10,
+            2,
+            0x10,
+            OpZeroPri as u64, // 0x48
+        ]);
+
+        assert_eq!(
+            render(&structure(&opcodes).unwrap()),
+            "#emit load.s.pri\t0xC\nswitch (pri) {\n  case 1, 2: {\n    #emit const.pri\t0x5\n  \
+             }\n  default: {\n    return;\n  }\n}\n#emit zero.pri\n"
+        );
+    }
+
+    #[test]
+    fn it_fails_on_unstructured_jump() {
+        let opcodes = decode(&[
+            OpLoadSPri as u64, // 0x00
+            12,
+            OpJzer as u64, // 0x08
+            0x18,
+            OpJump as u64, // 0x10
+            0x00,
+            OpZeroPri as u64, // 0x18
+            OpJump as u64,    // 0x1C
+            0x10,
+        ]);
+
+        assert!(structure(&opcodes).is_err());
+    }
+}
@@ -1,12 +1,120 @@
-use super::super::amx::Opcode;
+use super::control_flow::{DoWhile, For, If, Switch, While};
+use super::expression::Expression;
 use super::function::Function;
-use super::function_call::FunctionCall;
+use super::variable::Declaration;
+use amxmodx_utils::amx::opcode::{Opcode, Operands};
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum TreeElementType {
+    #[serde(rename = "opcode")]
     OpcodeType(Opcode),
+    #[serde(rename = "function")]
     FunctionType(Function),
-    FunctionCallType(FunctionCall),
+    #[serde(rename = "declaration")]
+    DeclarationType(Declaration),
+    /// Expression evaluated for its side effects, e.g. assignment.
+    #[serde(rename = "expression")]
+    ExpressionType(Expression),
+    #[serde(rename = "if")]
+    IfType(If),
+    #[serde(rename = "while")]
+    WhileType(While),
+    #[serde(rename = "do_while")]
+    DoWhileType(DoWhile),
+    #[serde(rename = "for")]
+    ForType(For),
+    #[serde(rename = "switch")]
+    SwitchType(Switch),
+    #[serde(rename = "break")]
+    BreakType,
+    #[serde(rename = "continue")]
+    ContinueType,
+    #[serde(rename = "return")]
+    ReturnType(Option<Expression>),
+}
+
+impl TreeElementType {
+    /// Statement lists nested into the element, e.g. branches of `if`.
+    pub fn bodies_mut(&mut self) -> Vec<&mut Vec<TreeElementType>> {
+        match *self {
+            TreeElementType::FunctionType(ref mut f) => vec![&mut f.tree_elements],
+            TreeElementType::IfType(ref mut i) => {
+                let mut bodies = vec![&mut i.then_elements];
+                bodies.extend(i.else_elements.as_mut());
+                let mut condition = Some(&mut i.condition);
+                while let Some(c) = condition {
+                    bodies.push(&mut c.setup);
+                    condition = c.and.as_deref_mut();
+                }
+                bodies
+            }
+            TreeElementType::WhileType(ref mut w) => {
+                let mut bodies: Vec<_> = w
+                    .condition
+                    .as_mut()
+                    .map(|c| &mut c.setup)
+                    .into_iter()
+                    .collect();
+                bodies.push(&mut w.body);
+                bodies
+            }
+            TreeElementType::DoWhileType(ref mut d) => vec![&mut d.body, &mut d.condition.setup],
+            TreeElementType::ForType(ref mut f) => {
+                let mut bodies: Vec<_> = f
+                    .condition
+                    .as_mut()
+                    .map(|c| &mut c.setup)
+                    .into_iter()
+                    .collect();
+                bodies.push(&mut f.body);
+                bodies.push(&mut f.increment);
+                bodies
+            }
+            TreeElementType::SwitchType(ref mut s) => {
+                let mut bodies: Vec<_> = s.cases.iter_mut().map(|c| &mut c.body).collect();
+                bodies.extend(s.default.as_mut());
+                bodies
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Expressions of the element itself, without ones of nested statement lists.
+    pub fn expressions_mut(&mut self) -> Vec<&mut Expression> {
+        let mut expressions = vec![];
+        let mut condition = match *self {
+            TreeElementType::DeclarationType(ref mut d) => {
+                expressions.push(&mut d.variable);
+                expressions.extend(d.value.as_mut());
+                None
+            }
+            TreeElementType::ExpressionType(ref mut e) => {
+                expressions.push(e);
+                None
+            }
+            TreeElementType::ReturnType(ref mut e) => {
+                expressions.extend(e.as_mut());
+                None
+            }
+            TreeElementType::SwitchType(ref mut s) => {
+                expressions.push(&mut s.value);
+                None
+            }
+            TreeElementType::IfType(ref mut i) => Some(&mut i.condition),
+            TreeElementType::WhileType(ref mut w) => w.condition.as_mut(),
+            TreeElementType::DoWhileType(ref mut d) => Some(&mut d.condition),
+            TreeElementType::ForType(ref mut f) => f.condition.as_mut(),
+            _ => None,
+        };
+
+        while let Some(c) = condition {
+            expressions.push(&mut c.pri);
+            expressions.push(&mut c.alt);
+            condition = c.and.as_deref_mut();
+        }
+        expressions
+    }
 }
 
 pub trait TreeElement {
@@ -19,12 +127,28 @@ impl TreeElement for Opcode {
         source.push_str(&format!(
             "{:>width$}#emit {}",
             "",
-            self.code,
+            self.code(),
             width = (2 * ident)
         ));
 
-        if let Some(p) = self.param {
-            source.push_str(&format!("\t0x{:X}", p));
+        match self.operands() {
+            Operands::Cells(cells) => {
+                for cell in cells.iter() {
+                    source.push_str(&format!("\t0x{:X}", cell));
+                }
+            }
+            Operands::CaseTable(case_table) => {
+                source.push_str(&format!(
+                    "\t0x{:X}\t0x{:X}",
+                    case_table.cases.len(),
+                    case_table.default
+                ));
+                for case in case_table.cases.iter() {
+                    source.push_str(&format!("\t0x{:X}\t0x{:X}", case.value, case.address));
+                }
+            }
+            // Obsolete debug opcodes, payload means nothing for the source
+            Operands::Payload(_) => (),
         }
 
         source.push('\n');
@@ -35,9 +159,32 @@ impl TreeElement for Opcode {
 impl TreeElement for TreeElementType {
     fn to_string(&self, ident: usize) -> Result<String, &'static str> {
         match *self {
-            TreeElementType::OpcodeType(o) => o.to_string(ident),
+            TreeElementType::OpcodeType(ref o) => TreeElement::to_string(o, ident),
             TreeElementType::FunctionType(ref f) => f.to_string(ident),
-            TreeElementType::FunctionCallType(ref c) => c.to_string(ident),
+            TreeElementType::DeclarationType(ref d) => d.to_string(ident),
+            TreeElementType::ExpressionType(ref e) => {
+                Ok(format!("{:>width$}{};\n", "", e, width = (2 * ident)))
+            }
+            TreeElementType::IfType(ref i) => i.to_string(ident),
+            TreeElementType::WhileType(ref w) => w.to_string(ident),
+            TreeElementType::DoWhileType(ref d) => d.to_string(ident),
+            TreeElementType::ForType(ref f) => f.to_string(ident),
+            TreeElementType::SwitchType(ref s) => s.to_string(ident),
+            TreeElementType::BreakType => {
+                Ok(format!("{:>width$}break;\n", "", width = (2 * ident)))
+            }
+            TreeElementType::ContinueType => {
+                Ok(format!("{:>width$}continue;\n", "", width = (2 * ident)))
+            }
+            TreeElementType::ReturnType(None) => {
+                Ok(format!("{:>width$}return;\n", "", width = (2 * ident)))
+            }
+            TreeElementType::ReturnType(Some(ref e)) => Ok(format!(
+                "{:>width$}return {};\n",
+                "",
+                e,
+                width = (2 * ident)
+            )),
         }
     }
 }
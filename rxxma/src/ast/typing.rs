@@ -0,0 +1,259 @@
+use super::expression::Expression;
+use super::function_call::FunctionCall;
+use super::globals::name_globals;
+use super::TreeElementType;
+use crate::analysis::data::{DataLayout, GlobalKind};
+use crate::include::{Database, Parameter};
+use amxmodx_utils::amx::UCell;
+
+/// Tags which tell nothing about the value.
+const UNTYPED_TAGS: &[&str] = &["_", "any"];
+
+/// DAT the constants point to, literals passed by reference become its variables.
+pub struct Data<'a> {
+    pub layout: &'a mut DataLayout,
+    pub cellsize: usize,
+}
+
+/// Types arguments of the calls by the parameters they are passed to.
+///
+/// DAT literals passed by reference become variables first, the rest are written as strings.
+pub fn type_arguments(elements: &mut [TreeElementType], database: &Database, data: &mut Data) {
+    visit(elements, &mut |e| {
+        if let Expression::Call(ref mut call) = *e {
+            promote_references(call, database, data);
+        }
+    });
+    name_globals(elements, data.layout);
+
+    let layout = &*data.layout;
+    visit(elements, &mut |e| {
+        if let Some(string) = literal(e, layout) {
+            *e = Expression::String(string);
+        }
+        if let Expression::Call(ref mut call) = *e {
+            call_arguments(call, database);
+        }
+    });
+}
+
+/// Runs the function over every expression, nested ones go first.
+fn visit(elements: &mut [TreeElementType], function: &mut dyn FnMut(&mut Expression)) {
+    for element in elements.iter_mut() {
+        for e in element.expressions_mut() {
+            expression(e, function);
+        }
+        for body in element.bodies_mut() {
+            visit(body, function);
+        }
+    }
+}
+
+fn expression(expression: &mut Expression, function: &mut dyn FnMut(&mut Expression)) {
+    for child in expression.children_mut() {
+        self::expression(child, function);
+    }
+    function(expression);
+}
+
+fn call_arguments(call: &mut FunctionCall, database: &Database) {
+    let signature = match database.get(&call.name) {
+        Some(s) => s,
+        None => return,
+    };
+
+    for (i, arg) in call.args.iter_mut().enumerate() {
+        if let Some(parameter) = signature.parameter(i) {
+            if let Some(typed) = argument(arg, parameter) {
+                *arg = typed;
+            }
+        }
+    }
+}
+
+/// Literals passed where Pawn takes only variables are the variables, zeroed or alike.
+fn promote_references(call: &FunctionCall, database: &Database, data: &mut Data) {
+    let signature = match database.get(&call.name) {
+        Some(s) => s,
+        None => return,
+    };
+
+    for (i, arg) in call.args.iter().enumerate() {
+        let parameter = match signature.parameter(i) {
+            Some(p) => p,
+            None => continue,
+        };
+        let by_reference = parameter.is_reference && !parameter.is_variadic;
+        let is_array = parameter.is_array() && !parameter.is_string() && !parameter.is_const;
+
+        if let Some(address) = dat_address(arg) {
+            if (by_reference || is_array) && literal(arg, data.layout).is_some() {
+                data.layout.promote(address, is_array, data.cellsize);
+            }
+        }
+    }
+}
+
+/// String literal at the DAT address the expression takes.
+fn literal(expression: &Expression, layout: &DataLayout) -> Option<String> {
+    let address = dat_address(expression)?;
+    match layout
+        .global_at(address)
+        .filter(|g| g.address == address)?
+        .kind
+    {
+        GlobalKind::String(ref s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn dat_address(expression: &Expression) -> Option<UCell> {
+    match *expression {
+        Expression::Address(ref e) => match **e {
+            Expression::Global(address) => Some(address),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Argument as it would be written for the parameter, none if it is written as is.
+fn argument(arg: &Expression, parameter: &Parameter) -> Option<Expression> {
+    match *arg {
+        // Constant arrays which happen to look like strings
+        Expression::String(ref s) if parameter.is_array() && !parameter.is_string() => {
+            let mut cells: Vec<_> = s.chars().map(|c| Expression::Cell(c as i64)).collect();
+            cells.push(Expression::Cell(0));
+            Some(Expression::Array(cells))
+        }
+        // Zeroed buffers passed for strings
+        Expression::Array(ref items)
+            if parameter.is_string() && items.iter().all(|i| *i == Expression::Cell(0)) =>
+        {
+            Some(Expression::String(String::new()))
+        }
+        Expression::Cell(v) if !parameter.is_array() => {
+            let tag = match parameter.tags.as_slice() {
+                [tag] if !UNTYPED_TAGS.contains(&tag.as_str()) => tag,
+                _ => return None,
+            };
+            match (tag.as_str(), v) {
+                ("bool", 0) => Some(Expression::Bool(false)),
+                ("bool", 1) => Some(Expression::Bool(true)),
+                _ => Some(Expression::Tagged(
+                    tag.clone(),
+                    Box::new(Expression::Cell(v)),
+                )),
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{type_arguments, Data};
+    use crate::analysis::data::{DataLayout, Global, GlobalKind};
+    use crate::ast::expression::Expression;
+    use crate::ast::function_call::FunctionCall;
+    use crate::ast::TreeElement;
+    use crate::ast::TreeElementType::{self, ExpressionType};
+    use crate::include::{parse, Database};
+
+    fn call(name: &str, args: Vec<Expression>) -> TreeElementType {
+        ExpressionType(Expression::Call(FunctionCall {
+            name: name.to_owned(),
+            args,
+        }))
+    }
+
+    fn literal(address: u64, string: &str) -> Global {
+        Global {
+            address,
+            size: (string.len() + 1) * 4,
+            name: None,
+            kind: GlobalKind::String(string.to_owned()),
+            cells: string.bytes().map(i64::from).chain(Some(0)).collect(),
+        }
+    }
+
+    fn dat(address: u64) -> Expression {
+        Expression::Address(Box::new(Expression::Global(address)))
+    }
+
+    #[test]
+    fn it_types_arguments_by_signature() {
+        let mut database = Database::new();
+        database.extend(
+            parse(
+                "native set_gravity(index, Float:gravity, bool:reset, origin[3]);
+                 native server_cmd(const command[], any:...);",
+            )
+            .unwrap(),
+        );
+
+        let mut elements = vec![
+            call(
+                "set_gravity",
+                vec![
+                    Expression::Cell(1),
+                    Expression::Cell(1065353216),
+                    Expression::Cell(1),
+                    Expression::String("AB".to_owned()),
+                ],
+            ),
+            call(
+                "server_cmd",
+                vec![
+                    Expression::Array(vec![Expression::Cell(0)]),
+                    Expression::Cell(5),
+                ],
+            ),
+        ];
+        let mut layout = DataLayout { globals: vec![] };
+        let mut data = Data {
+            layout: &mut layout,
+            cellsize: 4,
+        };
+        type_arguments(&mut elements, &database, &mut data);
+
+        let source: String = elements.iter().map(|e| e.to_string(0).unwrap()).collect();
+        assert_eq!(
+            source,
+            "set_gravity(1, Float:1065353216, true, {65, 66, 0});\nserver_cmd(\"\", 5);\n"
+        );
+    }
+
+    #[test]
+    fn it_tells_literals_from_variables_passed_by_reference() {
+        let mut database = Database::new();
+        database.extend(
+            parse(
+                "native get_num(&num);
+                 native set_name(const name[]);",
+            )
+            .unwrap(),
+        );
+
+        let mut elements = vec![
+            call("get_num", vec![dat(0)]),
+            call("set_name", vec![dat(4)]),
+            call("unknown", vec![dat(4), dat(0)]),
+        ];
+        let mut layout = DataLayout {
+            globals: vec![literal(0, ""), literal(4, "hi")],
+        };
+        let mut data = Data {
+            layout: &mut layout,
+            cellsize: 4,
+        };
+        type_arguments(&mut elements, &database, &mut data);
+
+        let source: String = elements.iter().map(|e| e.to_string(0).unwrap()).collect();
+        assert_eq!(
+            source,
+            "get_num(g_var0);\nset_name(\"hi\");\nunknown(\"hi\", g_var0);\n"
+        );
+        assert_eq!(layout.globals[0].kind, GlobalKind::Variable);
+    }
+}
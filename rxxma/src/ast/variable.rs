@@ -0,0 +1,54 @@
+use super::expression::Expression;
+use super::TreeElement;
+use serde::Serialize;
+use std::fmt;
+
+/// Declaration of the variable, `new var0 = 5;` or `new Float:g_var0[33];`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Declaration {
+    /// Declared variable, frame offset until variables get named.
+    pub variable: Expression,
+    pub tag: Option<String>,
+    /// Sizes of array dimensions, empty for plain variables.
+    pub dimensions: Vec<usize>,
+    pub value: Option<Expression>,
+}
+
+impl TreeElement for Declaration {
+    fn to_string(&self, ident: usize) -> Result<String, &'static str> {
+        let mut source = format!("{:>width$}new ", "", width = (2 * ident));
+        if let Some(ref tag) = self.tag {
+            source.push_str(&format!("{}:", tag));
+        }
+        source.push_str(&self.variable.to_string());
+        for size in self.dimensions.iter() {
+            source.push_str(&format!("[{}]", size));
+        }
+        if let Some(ref value) = self.value {
+            source.push_str(&format!(" = {}", value));
+        }
+        source.push_str(";\n");
+        Ok(source)
+    }
+}
+
+/// Function parameter, arrays are passed by address.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Parameter {
+    pub name: String,
+    pub tag: Option<String>,
+    pub is_array: bool,
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref tag) = self.tag {
+            write!(f, "{}:", tag)?;
+        }
+        write!(f, "{}", self.name)?;
+        if self.is_array {
+            write!(f, "[]")?;
+        }
+        Ok(())
+    }
+}
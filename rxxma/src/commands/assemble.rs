@@ -0,0 +1,52 @@
+use std::convert::TryFrom;
+use std::fs;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use failure::Error;
+
+use amxmodx_utils::amx::File as AmxPlugin;
+use amxmodx_utils::amxx::Builder;
+use rxxma::asm::assemble;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("assemble")
+        .about("Assemble listing into amxmodx plugin")
+        .arg(
+            Arg::with_name("file")
+                .value_name("FILE")
+                .help("listing to assemble, as written by disasm --listing")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .value_name("OUTPUT")
+                .help("path of the plugin to write")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("amx")
+                .long("amx")
+                .help("write raw amx image instead of amxmodx file"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let source = fs::read_to_string(matches.value_of("file").expect("file is required"))?;
+    let image = assemble(&source)?;
+
+    let bin = if matches.is_present("amx") {
+        image
+    } else {
+        let plugin = AmxPlugin::try_from(&image[..])?;
+        Builder::new()
+            .section(plugin.cellsize() as u8, plugin.header().stp, &image)
+            .build()?
+    };
+
+    fs::write(matches.value_of("output").expect("output is required"), bin)?;
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use std::io::{self, BufRead, Write};
+
+use clap::{App, ArgMatches, SubCommand};
+use failure::{format_err, Error};
+
+use super::{load_vm, parse_argument, public_args, read_public_args};
+use super::{read_section, section_args};
+use rxxma::analysis::CallGraph;
+use rxxma::vm::debugger::{Breakpoint, Debugger, DumpFormat, Stop};
+use rxxma::vm::{Argument, Cell, VmError};
+
+const HELP: &str = "\
+run PUBLIC [ARGS]     call the public, stops before its first instruction
+break|b WHERE         break at 0xADDRESS, function or native name
+delete|d N            remove breakpoint N
+breakpoints|bl        list breakpoints
+step|s                execute single instruction
+next|n                execute single instruction, stepping over calls
+continue|c            run until breakpoint or return
+registers|r           print registers
+frames|bt             print call stack
+x[/FMT] WHERE [N]     dump N cells at address, register or variable,
+                      FMT is d (ints), x (hex), f (floats) or s (string)
+quit|q                exit";
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("debug")
+        .about("Call the public in the interactive debugger")
+        .args(&section_args())
+        .args(&public_args())
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let plugin = read_section(matches)?;
+    let (public, args) = read_public_args(matches);
+
+    let vm = load_vm(&plugin)?;
+    let publics = plugin.publics()?.collect::<Result<Vec<_>, _>>()?;
+    let natives = plugin.natives()?.collect::<Result<Vec<_>, _>>()?;
+    let debug_info = plugin.debug_info().ok();
+    let graph = CallGraph::build(vm.opcodes(), &publics, &natives, debug_info.as_ref());
+    let mut debugger = Debugger::new(vm, graph, debug_info);
+
+    debugger.start(&public, &args)?;
+    print_position(&debugger);
+    println!("Type help for commands");
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(rxxma) ");
+        io::stdout().flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        match execute(&mut debugger, &line) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the command line, returns whether the session goes on.
+fn execute(debugger: &mut Debugger, line: &str) -> Result<bool, Error> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (command, args) = match words.split_first() {
+        Some((command, args)) => (*command, args),
+        None => return Ok(true),
+    };
+
+    match command {
+        "help" | "h" => println!("{}", HELP),
+        "quit" | "q" => return Ok(false),
+        "run" => {
+            let public = args
+                .first()
+                .ok_or_else(|| format_err!("Public is required"))?;
+            let args: Vec<Argument> = args[1..].iter().map(|&a| parse_argument(a)).collect();
+            debugger.start(public, &args)?;
+            print_position(debugger);
+        }
+        "break" | "b" => {
+            let location = args
+                .first()
+                .ok_or_else(|| format_err!("Location is required"))?;
+            let breakpoint = match number(location) {
+                Some(address) => Breakpoint::Address(address),
+                None => debugger
+                    .resolve(location)
+                    .ok_or_else(|| format_err!("No function or native {}", location))?,
+            };
+            let index = debugger.add_breakpoint(breakpoint);
+            println!("Breakpoint {} at {}", index, describe(debugger, index));
+        }
+        "delete" | "d" => {
+            let index = args
+                .first()
+                .and_then(|i| i.parse().ok())
+                .ok_or_else(|| format_err!("Breakpoint number is required"))?;
+            debugger
+                .remove_breakpoint(index)
+                .ok_or_else(|| format_err!("No breakpoint {}", index))?;
+        }
+        "breakpoints" | "bl" => {
+            for index in 0..debugger.breakpoints().len() {
+                println!("{:>3}  {}", index, describe(debugger, index));
+            }
+        }
+        "step" | "s" => report(debugger, |d| d.step())?,
+        "next" | "n" => report(debugger, |d| d.step_over())?,
+        "continue" | "c" => report(debugger, |d| d.resume())?,
+        "registers" | "r" => {
+            let r = debugger.vm.registers;
+            println!(
+                "pri=0x{:X} alt=0x{:X} frm=0x{:X} stk=0x{:X} hea=0x{:X} cip=0x{:X}",
+                r.pri, r.alt, r.frm, r.stk, r.hea, r.cip
+            );
+        }
+        "frames" | "bt" => {
+            for (i, frame) in debugger.frames().iter().enumerate() {
+                println!(
+                    "#{:<3} 0x{:08X}  {}  frm=0x{:X}",
+                    i,
+                    frame.cip,
+                    debugger.location(frame.cip),
+                    frame.frm
+                );
+            }
+        }
+        _ if command == "x" || command.starts_with("x/") => {
+            let format = match command.trim_start_matches('x').trim_start_matches('/') {
+                "" | "d" => DumpFormat::Int,
+                "x" => DumpFormat::Hex,
+                "f" => DumpFormat::Float,
+                "s" => DumpFormat::String,
+                f => return Err(format_err!("Unknown dump format {}", f)),
+            };
+            let location = args
+                .first()
+                .ok_or_else(|| format_err!("Address is required"))?;
+            let address = address(debugger, location)?;
+            let count = match args.get(1) {
+                Some(count) => count
+                    .parse()
+                    .map_err(|_| format_err!("Invalid count {}", count))?,
+                None => 1,
+            };
+            for line in debugger.dump(address, count, format)? {
+                println!("{}", line);
+            }
+        }
+        _ => println!("Unknown command {}, type help for commands", command),
+    }
+
+    Ok(true)
+}
+
+fn report<F>(debugger: &mut Debugger, run: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut Debugger) -> Result<Stop, VmError>,
+{
+    if !debugger.vm.is_running() {
+        return Err(format_err!("Nothing runs, call a public with run"));
+    }
+    match run(debugger)? {
+        Stop::Returned(value) => println!("Public returned {}", value),
+        Stop::Breakpoint(index) => {
+            println!("Breakpoint {}", index);
+            print_position(debugger);
+        }
+        Stop::Step => print_position(debugger),
+    }
+    Ok(())
+}
+
+fn print_position(debugger: &Debugger) {
+    let cip = debugger.vm.registers.cip;
+    let opcode = debugger
+        .vm
+        .opcode(cip)
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    println!("0x{:08X}  {}  ; {}", cip, opcode, debugger.location(cip));
+}
+
+fn describe(debugger: &Debugger, index: usize) -> String {
+    match debugger.breakpoints()[index] {
+        Breakpoint::Address(address) => debugger.location(address),
+        Breakpoint::Native(native) => format!("native {}", debugger.vm.native_names()[native]),
+    }
+}
+
+/// Address as a number, a register or a debug symbol.
+fn address(debugger: &Debugger, location: &str) -> Result<Cell, Error> {
+    let r = debugger.vm.registers;
+    let address = match location {
+        "pri" => r.pri,
+        "alt" => r.alt,
+        "frm" => r.frm,
+        "stk" => r.stk,
+        "hea" => r.hea,
+        _ => match number(location) {
+            Some(address) => address,
+            None => {
+                debugger
+                    .variable(location)
+                    .ok_or_else(|| format_err!("No variable {} in scope", location))?
+                    .0
+            }
+        },
+    };
+    Ok(address)
+}
+
+fn number(text: &str) -> Option<Cell> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let value = match text.strip_prefix("0x") {
+        Some(hex) => Cell::from_str_radix(hex, 16).ok()?,
+        None => text.parse().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
@@ -0,0 +1,46 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use failure::Error;
+
+use super::{format_arg, is_json, print_json, read_section, section_args, str_to_err};
+use rxxma::ast::Decompiler;
+use rxxma::ast::TreeElement;
+use rxxma::include::Database;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("decompile")
+        .about("Decompile plugin into Pawn source approximation")
+        .args(&section_args())
+        .arg(format_arg())
+        .arg(
+            Arg::with_name("include")
+                .long("include")
+                .short("i")
+                .value_name("DIR")
+                .help("directory of .inc files to type native arguments with")
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let mut database = Database::builtin();
+    for dir in matches.values_of("include").into_iter().flatten() {
+        database.load_dir(dir)?;
+    }
+
+    let mut decompiler = Decompiler::from(read_section(matches)?);
+    decompiler.opcodes_into_functions();
+    decompiler.structure_control_flow();
+    decompiler.decompile_opcodes_by_templates().unwrap();
+    decompiler.type_arguments(&database).map_err(str_to_err)?;
+    decompiler.infer_floats(&database).map_err(str_to_err)?;
+    let ast_plugin = decompiler.into_tree();
+
+    if is_json(matches) {
+        return print_json(&ast_plugin);
+    }
+
+    println!("{}", ast_plugin.to_string(0).map_err(str_to_err)?);
+    Ok(())
+}
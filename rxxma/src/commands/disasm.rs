@@ -0,0 +1,74 @@
+use std::fs;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use failure::{format_err, Error};
+
+use super::{format_arg, is_json, print_json, read_section, section_args};
+use amxmodx_utils::amx::opcode_type::OpcodeType::OpProc;
+use amxmodx_utils::amx::tables::Public;
+use rxxma::asm::listing;
+use rxxma::vm::coverage::Coverage;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("disasm")
+        .about("Disassemble code section")
+        .args(&section_args())
+        .arg(format_arg())
+        .arg(
+            Arg::with_name("listing")
+                .long("listing")
+                .short("l")
+                .help("print listing with labels and names, which can be assembled back"),
+        )
+        .arg(
+            Arg::with_name("coverage")
+                .long("coverage")
+                .short("c")
+                .value_name("FILE")
+                .help("show instruction hits from the coverage file written by `trace`")
+                .takes_value(true),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let plugin = read_section(matches)?;
+    if is_json(matches) {
+        if matches.is_present("coverage") {
+            return Err(format_err!("Coverage is not shown in JSON output"));
+        }
+        let opcodes = plugin.opcodes()?.collect::<Result<Vec<_>, _>>()?;
+        return print_json(&opcodes);
+    }
+
+    let hits = match matches.value_of("coverage") {
+        Some(path) => Some(Coverage::read_file(&fs::read_to_string(path)?)?),
+        None => None,
+    };
+    if matches.is_present("listing") {
+        print!("{}", listing(&plugin, hits.as_ref())?);
+        return Ok(());
+    }
+
+    let publics = plugin.publics()?.collect::<Result<Vec<Public>, _>>()?;
+
+    for opcode in plugin.opcodes()? {
+        let opcode = opcode?;
+
+        // Functions are separated, publics are named
+        if opcode.code() == OpProc {
+            println!();
+            if let Some(public) = publics.iter().find(|p| p.address == opcode.address()) {
+                println!("; public {}", public.name);
+            }
+        }
+        // Hits go before the address, never executed instructions are dashed
+        let column = match hits.as_ref().map(|h| h.get(&opcode.address())) {
+            Some(Some(&count)) if count > 0 => format!("{:>8}  ", count),
+            Some(_) => format!("{:>8}  ", "-"),
+            None => String::new(),
+        };
+        println!("{}0x{:08X}  {}", column, opcode.address(), opcode);
+    }
+
+    Ok(())
+}
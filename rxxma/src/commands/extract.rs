@@ -0,0 +1,43 @@
+use std::fs;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use failure::Error;
+
+use super::{file_arg, find_section, read_file};
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("extract")
+        .about("Write raw amx image of the section")
+        .arg(file_arg())
+        .arg(
+            Arg::with_name("cellsize")
+                .long("cellsize")
+                .value_name("CELLSIZE")
+                .help("cellsize of section to extract, 32 bit section is preferred by default")
+                .possible_values(&["4", "8"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .value_name("OUTPUT")
+                .help("path of the amx file to write")
+                .required(true)
+                .takes_value(true),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let amxmodx_file = read_file(matches)?;
+    let sections = amxmodx_file.sections().collect::<Result<Vec<_>, _>>()?;
+    let section = find_section(&sections, matches)?;
+
+    // Image is written as stored, compact encoding is kept
+    let image = section.unpack_body()?;
+    fs::write(
+        matches.value_of("output").expect("output is required"),
+        image,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use clap::{App, ArgMatches, SubCommand};
+use failure::Error;
+
+use super::{format_arg, is_json, print_json, read_section, section_args};
+use rxxma::model::Plugin;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("info")
+        .about("Show plugin name, header, flags and table sizes")
+        .args(&section_args())
+        .arg(format_arg())
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let plugin = Plugin::from(&read_section(matches)?)?;
+    if is_json(matches) {
+        return print_json(&plugin);
+    }
+
+    match plugin.info {
+        Some(ref info) => {
+            println!("name:      {}", info.name);
+            println!("version:   {}", info.version);
+            println!("author:    {}", info.author);
+        }
+        None if plugin.natives.iter().any(|n| n.name == "register_plugin") => {
+            println!("name:      unknown, register_plugin arguments are not resolved")
+        }
+        None => println!("name:      unknown, register_plugin is not used"),
+    }
+
+    let header = &plugin.header;
+    println!("cellsize:  {}", plugin.cellsize);
+    println!("size:      {}", header.size);
+    println!("flags:     {:?}", header.flags);
+    println!("defsize:   {}", header.defsize);
+    println!("cod:       0x{:08X}", header.cod);
+    println!("dat:       0x{:08X}", header.dat);
+    println!("hea:       0x{:08X}", header.hea);
+    println!("stp:       0x{:08X}", header.stp);
+    match header.cip {
+        0xFFFF_FFFF => println!("cip:       none"),
+        cip => println!("cip:       0x{:08X}", cip),
+    }
+
+    println!("publics:   {}", plugin.publics.len());
+    println!("natives:   {}", plugin.natives.len());
+    println!("libraries: {}", plugin.libraries.len());
+    println!("pubvars:   {}", plugin.pubvars.len());
+    println!("tags:      {}", plugin.tags.len());
+
+    Ok(())
+}
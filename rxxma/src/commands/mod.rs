@@ -0,0 +1,182 @@
+use failure::format_err;
+use log::trace;
+
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+use clap::{Arg, ArgMatches};
+use failure::Error;
+use serde::Serialize;
+
+use amxmodx_utils::amx::File as AmxPlugin;
+use amxmodx_utils::amxx::File as AmxmodxFile;
+use amxmodx_utils::amxx::Section as AmxmodxSection;
+use rxxma::util::is_equivalent;
+use rxxma::vm::stubs::register_core;
+use rxxma::vm::{Argument, Vm};
+
+pub mod assemble;
+pub mod debug;
+pub mod decompile;
+pub mod disasm;
+pub mod extract;
+pub mod info;
+pub mod sections;
+pub mod strings;
+pub mod trace;
+
+pub fn str_to_err(e: &str) -> Error {
+    format_err!("{}", e)
+}
+
+/// Arguments of the commands working with a single plugin section.
+pub fn section_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        file_arg(),
+        Arg::with_name("cellsize")
+            .long("cellsize")
+            .value_name("CELLSIZE")
+            .help("cellsize of section to analyze, 32 bit section is preferred by default")
+            .possible_values(&["4", "8"])
+            .takes_value(true),
+        Arg::with_name("check-sections")
+            .long("check-sections")
+            .help("ensure all file sections contain the same plugin"),
+    ]
+}
+
+pub fn file_arg() -> Arg<'static, 'static> {
+    Arg::with_name("file")
+        .value_name("FILE")
+        .help("amxmodx file to analyze")
+        .required(true)
+        .takes_value(true)
+}
+
+/// `--format` of the commands which can print JSON.
+pub fn format_arg() -> Arg<'static, 'static> {
+    Arg::with_name("format")
+        .long("format")
+        .value_name("FORMAT")
+        .help("output format")
+        .possible_values(&["text", "json"])
+        .default_value("text")
+        .takes_value(true)
+}
+
+pub fn is_json(matches: &ArgMatches) -> bool {
+    matches.value_of("format") == Some("json")
+}
+
+pub fn print_json<T: Serialize>(value: &T) -> Result<(), Error> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+pub fn read_file(matches: &ArgMatches) -> Result<AmxmodxFile, Error> {
+    let file_path = PathBuf::from(matches.value_of("file").expect("file is required"));
+    Ok(AmxmodxFile::try_from(file_path)?)
+}
+
+fn check_sections_match(sections: &[AmxmodxSection]) -> Result<(), Error> {
+    let plugins = sections
+        .iter()
+        .map(AmxmodxSection::unpack)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (i, plugin) in plugins.iter().enumerate().skip(1) {
+        if !is_equivalent(&plugins[0], plugin)? {
+            return Err(format_err!("Section {} does not match section 1", i + 1));
+        }
+    }
+
+    Ok(())
+}
+
+/// Section chosen with `--cellsize`.
+pub fn find_section<'a, 'b>(
+    sections: &'a [AmxmodxSection<'b>],
+    matches: &ArgMatches,
+) -> Result<&'a AmxmodxSection<'b>, Error> {
+    let cellsize: Option<u8> = matches
+        .value_of("cellsize")
+        .map(|c| c.parse().expect("validated by possible values"));
+
+    match cellsize {
+        Some(cellsize) => sections
+            .iter()
+            .find(|s| s.metadata().cellsize == cellsize)
+            .ok_or_else(|| format_err!("File has no {} bit sections", u32::from(cellsize) * 8)),
+        // Prefer 32 bit section, fallback to whatever file has
+        None => sections
+            .iter()
+            .find(|s| s.metadata().cellsize == 4)
+            .or_else(|| sections.first())
+            .ok_or("File has no sections")
+            .map_err(str_to_err),
+    }
+}
+
+/// Plugin of the section chosen with `--cellsize`.
+pub fn read_section(matches: &ArgMatches) -> Result<AmxPlugin, Error> {
+    let amxmodx_file = read_file(matches)?;
+    let sections = amxmodx_file.sections().collect::<Result<Vec<_>, _>>()?;
+
+    if matches.is_present("check-sections") {
+        check_sections_match(&sections)?;
+    }
+
+    let section = find_section(&sections, matches)?;
+    trace!("-------------------------------------------");
+    trace!(
+        " Reading amxmod plugin from {} bit section ",
+        section.metadata().cellsize * 8
+    );
+    trace!("-------------------------------------------");
+    Ok(section.unpack()?)
+}
+
+/// `PUBLIC` to run and its `ARGS`, numbers are passed as cells and the rest as strings.
+pub fn public_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("public")
+            .value_name("PUBLIC")
+            .help("public function to call")
+            .default_value("plugin_init"),
+        Arg::with_name("args")
+            .value_name("ARGS")
+            .help("arguments of the public")
+            .multiple(true),
+    ]
+}
+
+pub fn read_public_args(matches: &ArgMatches) -> (String, Vec<Argument>) {
+    let public = matches.value_of("public").expect("has default value");
+    let args = matches
+        .values_of("args")
+        .map(|values| values.map(parse_argument).collect())
+        .unwrap_or_default();
+    (public.to_owned(), args)
+}
+
+pub fn parse_argument(arg: &str) -> Argument {
+    match arg.parse() {
+        Ok(number) => Argument::Cell(number),
+        Err(_) => Argument::from(arg),
+    }
+}
+
+/// Machine with the core natives, the rest of natives return 0.
+pub fn load_vm(plugin: &AmxPlugin) -> Result<Vm, Error> {
+    let mut vm = Vm::new(plugin)?;
+    register_core(vm.natives_mut());
+
+    let missing: Vec<String> = vm.missing_natives().iter().map(|&n| n.to_owned()).collect();
+    if !missing.is_empty() {
+        eprintln!("Natives returning 0: {}", missing.join(", "));
+    }
+    for native in missing.iter() {
+        vm.natives_mut().mock(native, 0);
+    }
+    Ok(vm)
+}
@@ -0,0 +1,37 @@
+use clap::{App, ArgMatches, SubCommand};
+use failure::Error;
+
+use super::{file_arg, format_arg, is_json, print_json, read_file};
+use rxxma::model::AmxxFile;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("sections")
+        .about("List sections of the file")
+        .arg(file_arg())
+        .arg(format_arg())
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let amxmodx_file = read_file(matches)?;
+    if is_json(matches) {
+        return print_json(&AmxxFile::from(&amxmodx_file)?);
+    }
+
+    println!(
+        "{:>3} {:>8} {:>10} {:>10} {:>10}",
+        "#", "cellsize", "disksize", "imagesize", "memsize"
+    );
+    for (i, section) in amxmodx_file.sections().enumerate() {
+        let metadata = section?.metadata();
+        println!(
+            "{:>3} {:>8} {:>10} {:>10} {:>10}",
+            i + 1,
+            metadata.cellsize,
+            metadata.disksize,
+            metadata.imagesize,
+            metadata.memsize
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use failure::{format_err, Error};
+
+use super::{format_arg, is_json, print_json, read_section, section_args};
+use rxxma::analysis::strings::strings;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("strings")
+        .about("List strings of DAT section with their addresses")
+        .args(&section_args())
+        .arg(format_arg())
+        .arg(
+            Arg::with_name("min-length")
+                .long("min-length")
+                .short("n")
+                .value_name("LENGTH")
+                .help("shortest string to list, referenced strings are always listed")
+                .default_value("4")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("xrefs")
+                .long("xrefs")
+                .short("x")
+                .help("list addresses of the code referring to strings"),
+        )
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let min_length = matches
+        .value_of("min-length")
+        .expect("has default value")
+        .parse()
+        .map_err(|_| format_err!("Minimal string length should be a number"))?;
+    let xrefs = matches.is_present("xrefs");
+
+    let plugin = read_section(matches)?;
+    let opcodes = plugin.opcodes()?.collect::<Result<Vec<_>, _>>()?;
+
+    let found = strings(plugin.dat_slice()?, plugin.cellsize(), &opcodes, min_length);
+
+    if is_json(matches) {
+        return print_json(&found);
+    }
+
+    for string in found {
+        println!(
+            "0x{:08X} {:<8} {:?}",
+            string.address, string.kind, string.value
+        );
+        if xrefs && !string.references.is_empty() {
+            let references: Vec<String> = string
+                .references
+                .iter()
+                .map(|r| format!("0x{:08X}", r))
+                .collect();
+            println!("           xrefs: {}", references.join(", "));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::fs;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use failure::Error;
+use serde::Serialize;
+
+use super::{format_arg, is_json, load_vm, print_json, public_args, read_public_args};
+use super::{read_section, section_args};
+use rxxma::analysis::CallGraph;
+use rxxma::vm::coverage::Coverage;
+use rxxma::vm::trace::{TraceEntry, Tracer};
+use rxxma::vm::Cell;
+
+pub fn subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("trace")
+        .about("Run the public, tracing executed instructions and their coverage")
+        .args(&section_args())
+        .args(&public_args())
+        .arg(format_arg())
+        .arg(
+            Arg::with_name("coverage")
+                .long("coverage")
+                .short("c")
+                .value_name("FILE")
+                .help("write hits of every instruction, `disasm --coverage` shows them")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .long("quiet")
+                .short("q")
+                .help("print coverage summary only"),
+        )
+}
+
+#[derive(Serialize)]
+struct Trace {
+    result: Cell,
+    trace: Vec<TraceEntry>,
+    coverage: Coverage,
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let plugin = read_section(matches)?;
+    let (public, args) = read_public_args(matches);
+    let quiet = matches.is_present("quiet");
+    let json = is_json(matches);
+
+    let mut vm = load_vm(&plugin)?;
+    let mut tracer = Tracer::new();
+    let mut trace = vec![];
+    let result = tracer.exec(&mut vm, &public, &args, |entry| match (quiet, json) {
+        (true, _) => (),
+        (false, true) => trace.push(entry.clone()),
+        (false, false) => println!("{}", entry),
+    })?;
+
+    let publics = plugin.publics()?.collect::<Result<Vec<_>, _>>()?;
+    let natives = plugin.natives()?.collect::<Result<Vec<_>, _>>()?;
+    let debug_info = plugin.debug_info().ok();
+    let graph = CallGraph::build(vm.opcodes(), &publics, &natives, debug_info.as_ref());
+    let coverage = Coverage::new(vm.opcodes(), &graph, tracer.hits())?;
+
+    if let Some(path) = matches.value_of("coverage") {
+        fs::write(path, coverage.to_file())?;
+    }
+
+    if json {
+        return print_json(&Trace {
+            result,
+            trace,
+            coverage,
+        });
+    }
+
+    if !quiet {
+        println!();
+    }
+    println!("{} returned {}", public, result);
+    for function in coverage.functions.iter() {
+        println!(
+            "{:<32} {:>5}/{:<5} instructions {:>4}/{:<4} blocks {:>6.1}%",
+            function.name,
+            function.executed,
+            function.instructions,
+            function.blocks_executed(),
+            function.blocks.len(),
+            100.0 * function.executed as f64 / function.instructions.max(1) as f64
+        );
+    }
+
+    Ok(())
+}
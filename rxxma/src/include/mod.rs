@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use failure::Fail;
+
+mod parser;
+
+pub use self::parser::{parse, SyntaxError};
+
+#[derive(Debug, Fail)]
+pub enum IncludeError {
+    #[fail(display = "Failed to read {}: {}", _0, _1)]
+    Io(String, #[cause] io::Error),
+    #[fail(display = "Failed to parse {}: {}", _0, _1)]
+    Syntax(String, #[cause] SyntaxError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureKind {
+    Native,
+    Forward,
+    Stock,
+}
+
+impl fmt::Display for SignatureKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SignatureKind::Native => write!(f, "native"),
+            SignatureKind::Forward => write!(f, "forward"),
+            SignatureKind::Stock => write!(f, "stock"),
+        }
+    }
+}
+
+/// Parameter of the declaration, `const Float:origin[3] = {0.0, 0.0, 0.0}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    /// Tags the argument might have, `{Float,_}:` gives two.
+    pub tags: Vec<String>,
+    pub is_const: bool,
+    /// Passed by reference, `&value`.
+    pub is_reference: bool,
+    /// Sizes of array dimensions as written, empty ones are none.
+    pub dimensions: Vec<Option<String>>,
+    /// Any number of the arguments, `...`.
+    pub is_variadic: bool,
+    /// Default value as written.
+    pub default: Option<String>,
+}
+
+impl Parameter {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Untagged array of unspecified size, include files pass strings this way.
+    pub fn is_string(&self) -> bool {
+        self.tags.is_empty() && self.dimensions.len() == 1 && self.dimensions[0].is_none()
+    }
+
+    pub fn is_array(&self) -> bool {
+        !self.dimensions.is_empty()
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_const {
+            write!(f, "const ")?;
+        }
+        if self.is_reference {
+            write!(f, "&")?;
+        }
+        match self.tags.len() {
+            0 => (),
+            1 => write!(f, "{}:", self.tags[0])?,
+            _ => write!(f, "{{{}}}:", self.tags.join(","))?,
+        }
+        write!(f, "{}", self.name)?;
+        for dimension in self.dimensions.iter() {
+            write!(f, "[{}]", dimension.as_ref().map_or("", |d| d.as_str()))?;
+        }
+        if let Some(ref default) = self.default {
+            write!(f, " = {}", default)?;
+        }
+        Ok(())
+    }
+}
+
+/// Declaration of the function found in include file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub kind: SignatureKind,
+    pub name: String,
+    /// Tag of the return value.
+    pub tag: Option<String>,
+    pub parameters: Vec<Parameter>,
+}
+
+impl Signature {
+    /// Parameter the argument at the position is passed to, varargs take the rest.
+    pub fn parameter(&self, position: usize) -> Option<&Parameter> {
+        self.parameters
+            .get(position)
+            .or_else(|| self.parameters.last().filter(|p| p.is_variadic))
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ", self.kind)?;
+        if let Some(ref tag) = self.tag {
+            write!(f, "{}:", tag)?;
+        }
+        let parameters: Vec<String> = self.parameters.iter().map(|p| p.to_string()).collect();
+        write!(f, "{}({});", self.name, parameters.join(", "))
+    }
+}
+
+/// Signatures of natives, forwards and stocks by name.
+#[derive(Debug, Clone, Default)]
+pub struct Database {
+    signatures: HashMap<String, Signature>,
+}
+
+impl Database {
+    pub fn new() -> Database {
+        Database::default()
+    }
+
+    /// Float natives every plugin might use, Pawn implements float operators with them.
+    pub fn builtin() -> Database {
+        let mut database = Database::new();
+        database.extend(parse(include_str!("float.inc")).expect("builtin include is valid"));
+        database
+    }
+
+    /// Loads every `.inc` file of the directory, subdirectories included.
+    pub fn load_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<(), IncludeError> {
+        for file in include_files(path.as_ref())? {
+            self.load_file(&file)?;
+        }
+        Ok(())
+    }
+
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), IncludeError> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let source = fs::read(path).map_err(|e| IncludeError::Io(name.clone(), e))?;
+
+        // Include files are not always UTF-8, names and tags are ASCII anyway
+        let source = String::from_utf8_lossy(&source);
+        let signatures = parse(&source).map_err(|e| IncludeError::Syntax(name, e))?;
+        self.extend(signatures);
+        Ok(())
+    }
+
+    /// Adds signatures, the first declaration wins, e.g. of `#if` branches.
+    pub fn extend<I: IntoIterator<Item = Signature>>(&mut self, signatures: I) {
+        for signature in signatures {
+            self.signatures
+                .entry(signature.name.clone())
+                .or_insert(signature);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Signature> {
+        self.signatures.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+}
+
+/// Include files ordered by path, so loading is reproducible.
+fn include_files(path: &Path) -> Result<Vec<PathBuf>, IncludeError> {
+    let io_error = |e| IncludeError::Io(path.display().to_string(), e);
+    let mut files = vec![];
+
+    for entry in fs::read_dir(path).map_err(io_error)? {
+        let entry_path = entry.map_err(io_error)?.path();
+        if entry_path.is_dir() {
+            files.extend(include_files(&entry_path)?);
+        } else if entry_path.extension().is_some_and(|e| e == "inc") {
+            files.push(entry_path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Database, SignatureKind};
+
+    #[test]
+    fn it_loads_include_directory() {
+        let mut database = Database::new();
+        database.load_dir("test/fixtures/include").unwrap();
+
+        let signature = database.get("set_user_gravity").unwrap();
+        assert_eq!(signature.kind, SignatureKind::Native);
+        assert_eq!(
+            signature.to_string(),
+            "native set_user_gravity(index, Float:gravity = 1.0);"
+        );
+        assert!(signature.parameter(1).unwrap().has_tag("Float"));
+        assert_eq!(
+            database.get("client_putinserver").unwrap().kind,
+            SignatureKind::Forward
+        );
+
+        // Nested directory
+        assert!(database.get("floatadd").is_some());
+
+        let format = database.get("format").unwrap();
+        assert!(format.parameter(0).unwrap().is_string());
+        assert!(format.parameter(5).unwrap().is_variadic);
+    }
+}
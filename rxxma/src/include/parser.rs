@@ -0,0 +1,499 @@
+use failure::Fail;
+
+use super::{Parameter, Signature, SignatureKind};
+
+#[derive(Debug, Fail, PartialEq)]
+#[fail(display = "line {}: {}", line, message)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub message: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Identifier or keyword.
+    Word(String),
+    /// Number, string or character literal as written.
+    Literal(String),
+    Ellipsis,
+    Punct(char),
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match *self {
+            Token::Word(ref s) | Token::Literal(ref s) => s.clone(),
+            Token::Ellipsis => "...".to_owned(),
+            Token::Punct(c) => c.to_string(),
+        }
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        matches!(*self, Token::Word(ref w) if w == word)
+    }
+}
+
+/// Splits the source into tokens with their lines.
+///
+/// Comments and preprocessor directives are dropped, so declarations of every `#if`
+/// branch are kept.
+fn tokenize(source: &str) -> Vec<(Token, usize)> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut line_start = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).cloned();
+
+        if c == '\n' {
+            line += 1;
+            line_start = true;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c == '#' && line_start {
+            // Directive lasts until the end of the line, unless it is continued with `\`
+            while i < chars.len() && chars[i] != '\n' {
+                if chars[i] == '\\' && chars.get(i + 1) == Some(&'\n') {
+                    line += 1;
+                    i += 1;
+                }
+                i += 1;
+            }
+            continue;
+        }
+        line_start = false;
+
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        if c == '/' && next == Some('*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            i += 2;
+            continue;
+        }
+
+        let start = i;
+        let token = if c.is_alphabetic() || c == '_' || c == '@' {
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '@')
+            {
+                i += 1;
+            }
+            Token::Word(chars[start..i].iter().collect())
+        } else if c.is_ascii_digit() {
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '.' || chars[i] == '_')
+            {
+                i += 1;
+            }
+            Token::Literal(chars[start..i].iter().collect())
+        } else if c == '"' || c == '\'' {
+            // Pawn escapes with `^` by default, `\` is accepted too
+            i += 1;
+            while i < chars.len() && chars[i] != c && chars[i] != '\n' {
+                if chars[i] == '^' || chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i += 1;
+            Token::Literal(chars[start..i.min(chars.len())].iter().collect())
+        } else if c == '.' && next == Some('.') && chars.get(i + 2) == Some(&'.') {
+            i += 3;
+            Token::Ellipsis
+        } else {
+            i += 1;
+            Token::Punct(c)
+        };
+        tokens.push((token, line));
+    }
+
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(t, _)| t)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.position + offset).map(|(t, _)| t)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    fn error(&self, message: &'static str) -> SyntaxError {
+        let line = self
+            .tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, l)| *l);
+        SyntaxError { line, message }
+    }
+
+    fn eat(&mut self, punct: char) -> bool {
+        if self.peek() == Some(&Token::Punct(punct)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: char, message: &'static str) -> Result<(), SyntaxError> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    fn word(&mut self, message: &'static str) -> Result<String, SyntaxError> {
+        match self.peek() {
+            Some(Token::Word(w)) => {
+                let word = w.clone();
+                self.position += 1;
+                Ok(word)
+            }
+            _ => Err(self.error(message)),
+        }
+    }
+
+    /// Skips tokens until the closing bracket of the one just consumed.
+    fn skip_group(&mut self, open: char, close: char) {
+        let mut depth = 1;
+        while let Some(token) = self.next() {
+            if token == Token::Punct(open) {
+                depth += 1;
+            } else if token == Token::Punct(close) {
+                depth -= 1;
+                if depth == 0 {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Skips the statement up to `;` or the end of the block it opens.
+    fn skip_statement(&mut self) {
+        while let Some(token) = self.next() {
+            match token {
+                Token::Punct(';') => return,
+                Token::Punct('{') => {
+                    self.skip_group('{', '}');
+                    return;
+                }
+                Token::Punct('(') => self.skip_group('(', ')'),
+                _ => (),
+            }
+        }
+    }
+
+    /// Text of the tokens up to the delimiter outside of brackets, the delimiter is not consumed.
+    fn text_until(&mut self, delimiters: &[char]) -> String {
+        let mut text = String::new();
+        let mut depth = 0;
+        let mut previous_word = false;
+
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Punct(c) if depth == 0 && delimiters.contains(&c) => break,
+                Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => depth += 1,
+                Token::Punct(')') | Token::Punct(']') | Token::Punct('}') => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                }
+                _ => (),
+            }
+
+            let is_word = matches!(token, Token::Word(_) | Token::Literal(_));
+            if is_word && previous_word {
+                text.push(' ');
+            }
+            previous_word = is_word;
+            text.push_str(&token.text());
+            self.position += 1;
+        }
+
+        text
+    }
+
+    /// Tags before the name, `Float:` or `{Float,_}:`.
+    fn tags(&mut self) -> Result<Vec<String>, SyntaxError> {
+        match (self.peek(), self.peek_at(1)) {
+            (Some(Token::Word(tag)), Some(Token::Punct(':'))) => {
+                let tag = tag.clone();
+                self.position += 2;
+                Ok(vec![tag])
+            }
+            (Some(Token::Punct('{')), _) => {
+                self.position += 1;
+                let mut tags = vec![self.word("expected tag name")?];
+                while self.eat(',') {
+                    tags.push(self.word("expected tag name")?);
+                }
+                self.expect('}', "expected `}` after tags")?;
+                self.expect(':', "expected `:` after tags")?;
+                Ok(tags)
+            }
+            _ => Ok(vec![]),
+        }
+    }
+
+    fn parameter(&mut self) -> Result<Parameter, SyntaxError> {
+        let mut is_const = false;
+        let mut is_reference = false;
+        loop {
+            if self.peek().is_some_and(|t| t.is_word("const")) {
+                is_const = true;
+                self.position += 1;
+            } else if self.eat('&') {
+                is_reference = true;
+            } else {
+                break;
+            }
+        }
+
+        let tags = self.tags()?;
+        let (name, is_variadic) = if self.peek() == Some(&Token::Ellipsis) {
+            self.position += 1;
+            ("...".to_owned(), true)
+        } else {
+            (self.word("expected parameter name")?, false)
+        };
+
+        let mut dimensions = vec![];
+        while self.eat('[') {
+            let size = self.text_until(&[']']);
+            self.expect(']', "expected `]` after array size")?;
+            dimensions.push(Some(size).filter(|s| !s.is_empty()));
+        }
+
+        let default = if self.eat('=') {
+            Some(self.text_until(&[',', ')']))
+        } else {
+            None
+        };
+
+        Ok(Parameter {
+            name,
+            tags,
+            is_const,
+            is_reference,
+            dimensions,
+            is_variadic,
+            default,
+        })
+    }
+
+    /// Declaration after its keywords, none for variables and constants.
+    fn declaration(&mut self, kind: SignatureKind) -> Result<Option<Signature>, SyntaxError> {
+        let tag = self.tags()?.pop();
+        let mut name = self.word("expected function name")?;
+
+        // Overloaded operators, `Float:operator*(Float:oper1, Float:oper2)`
+        if name == "operator" {
+            while let Some(Token::Punct(c)) = self.peek().cloned() {
+                if c == '(' {
+                    break;
+                }
+                name.push(c);
+                self.position += 1;
+            }
+        }
+
+        if !self.eat('(') {
+            self.skip_statement();
+            return Ok(None);
+        }
+
+        let mut parameters = vec![];
+        if !self.eat(')') {
+            loop {
+                parameters.push(self.parameter()?);
+                if self.eat(')') {
+                    break;
+                }
+                self.expect(',', "expected `,` or `)` after parameter")?;
+            }
+        }
+
+        // Body of the stock or alias of the native operator, `= floatmul`
+        match self.peek() {
+            Some(Token::Punct('{')) => {
+                self.position += 1;
+                self.skip_group('{', '}');
+            }
+            Some(Token::Punct(';')) => self.position += 1,
+            Some(Token::Punct('=')) => self.skip_statement(),
+            _ => (),
+        }
+
+        Ok(Some(Signature {
+            kind,
+            name,
+            tag,
+            parameters,
+        }))
+    }
+
+    fn parse(&mut self) -> Result<Vec<Signature>, SyntaxError> {
+        let mut signatures = vec![];
+
+        while let Some(token) = self.peek().cloned() {
+            // Qualifiers go in any order, `static stock const`
+            let start = self.position;
+            let mut kind = None;
+            while let Some(Token::Word(word)) = self.peek() {
+                let qualifier = match word.as_str() {
+                    "native" => Some(SignatureKind::Native),
+                    "forward" => Some(SignatureKind::Forward),
+                    "stock" => Some(SignatureKind::Stock),
+                    "static" | "public" | "const" => None,
+                    _ => break,
+                };
+                kind = kind.or(qualifier);
+                self.position += 1;
+            }
+
+            if let Some(kind) = kind {
+                signatures.extend(self.declaration(kind)?);
+                continue;
+            }
+            if self.position != start {
+                continue;
+            }
+
+            self.position += 1;
+            match token {
+                Token::Punct('{') => self.skip_group('{', '}'),
+                Token::Punct('(') => self.skip_group('(', ')'),
+                _ => (),
+            }
+        }
+
+        Ok(signatures)
+    }
+}
+
+/// Declarations of natives, forwards and stocks in the include file source.
+pub fn parse(source: &str) -> Result<Vec<Signature>, SyntaxError> {
+    Parser {
+        tokens: tokenize(source),
+        position: 0,
+    }
+    .parse()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::include::SignatureKind;
+
+    fn signatures(source: &str) -> Vec<String> {
+        parse(source)
+            .unwrap()
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn it_parses_declarations() {
+        let source = r#"
+            #if defined _amxmodx_included
+              #endinput
+            #endif
+            #define MAX_NAME_LENGTH 32
+
+            /* Sets the name,
+               version and author */
+            native register_plugin(const plugin_name[], const version[], const author[], const url[] = "", const description[] = "");
+            native bool:is_user_alive(index); // Alive check
+            native get_user_origin(index, origin[3], mode = 0);
+            native Float:floatadd(Float:dividend, Float:divisor);
+            native format(output[], len, const format[], any:...);
+            native get_players(players[MAX_PLAYERS], &num, const flags[] = "", const team[] = "");
+            native set_task(Float:time, const function[], id = 0, const parameter[] = "", len = 0, const flags[] = "", repeat = 0);
+            native clamp(value, min = cellmin, max = cellmax);
+            native Float:operator*(Float:oper1, Float:oper2) = floatmul;
+            forward client_putinserver(id);
+            native print({Float,_}:value, const text[]="hi^"");
+        "#;
+
+        assert_eq!(
+            signatures(source),
+            vec![
+                "native register_plugin(const plugin_name[], const version[], const author[], const url[] = \"\", const description[] = \"\");",
+                "native bool:is_user_alive(index);",
+                "native get_user_origin(index, origin[3], mode = 0);",
+                "native Float:floatadd(Float:dividend, Float:divisor);",
+                "native format(output[], len, const format[], any:...);",
+                "native get_players(players[MAX_PLAYERS], &num, const flags[] = \"\", const team[] = \"\");",
+                "native set_task(Float:time, const function[], id = 0, const parameter[] = \"\", len = 0, const flags[] = \"\", repeat = 0);",
+                "native clamp(value, min = cellmin, max = cellmax);",
+                "native Float:operator*(Float:oper1, Float:oper2);",
+                "forward client_putinserver(id);",
+                "native print({Float,_}:value, const text[] = \"hi^\"\");",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_skips_stock_bodies_and_variables() {
+        let source = r#"
+            enum { print_notify = 1, print_console };
+            stock const MESSAGE[] = "hello";
+            new g_count;
+            stock bool:is_valid(const player, Float:range = 1.5) {
+                if (player > 0) { return true; }
+                return false;
+            }
+            static stock helper() {}
+        "#;
+
+        let parsed = parse(source).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].kind, SignatureKind::Stock);
+        assert_eq!(
+            parsed[0].to_string(),
+            "stock bool:is_valid(const player, Float:range = 1.5);"
+        );
+        assert_eq!(parsed[1].name, "helper");
+    }
+
+    #[test]
+    fn it_reports_syntax_errors_with_line() {
+        let error = parse("\nnative broken(index;\n").unwrap_err();
+        assert_eq!(error.line, 2);
+    }
+}
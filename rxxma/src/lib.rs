@@ -1,13 +1,9 @@
 #![cfg_attr(feature = "strict", deny(warnings))]
 
-#[macro_use]
-extern crate bitflags;
-#[macro_use]
-extern crate enum_primitive;
-#[macro_use]
-extern crate failure;
-
-pub mod amx;
-pub mod amxx;
+pub mod analysis;
+pub mod asm;
 pub mod ast;
+pub mod include;
+pub mod model;
 pub mod util;
+pub mod vm;
@@ -1,16 +1,8 @@
-use failure::format_err;
-use log::trace;
+use clap::{App, AppSettings};
 
-use std::convert::TryFrom;
-use std::path::PathBuf;
+mod commands;
 
-use clap::{App, Arg};
-use failure::Error;
-
-use rxxma::amx::Plugin as AmxPlugin;
-use rxxma::amxx::File as AmxmodxFile;
-use rxxma::ast::Decompiler;
-use rxxma::ast::TreeElement;
+use commands::{assemble, debug, decompile, disasm, extract, info, sections, strings, trace};
 
 macro_rules! die {
     ($fmt:expr) => ({
@@ -23,37 +15,6 @@ macro_rules! die {
     });
 }
 
-fn str_to_err(e: &str) -> Error {
-    format_err!("{}", e)
-}
-
-fn read_32bit_section(file_path: PathBuf) -> Result<AmxPlugin, Error> {
-    let amxmodx_file = AmxmodxFile::try_from(file_path)?;
-    let sections = amxmodx_file.sections()?;
-
-    let section_32bit = sections
-        .into_iter()
-        .find(|ref s| s.cellsize == 4)
-        .ok_or("File has no 32 bit sections. 64 bit are not supported")
-        .map_err(str_to_err)?;
-
-    trace!("-------------------------------------------");
-    trace!(" Reading amxmod plugin from 32 bit section ");
-    trace!("-------------------------------------------");
-    section_32bit.unpack_section()
-}
-
-fn decompile(file_path: PathBuf) -> Result<String, Error> {
-    let amxmod_plugin = read_32bit_section(file_path)?;
-
-    let mut decompiler = Decompiler::from(amxmod_plugin);
-    decompiler.opcodes_into_functions();
-    decompiler.decompile_opcodes_by_templates().unwrap();
-    let ast_plugin = decompiler.into_tree();
-
-    Ok(ast_plugin.to_string(0).map_err(str_to_err)?)
-}
-
 fn main() {
     env_logger::init();
 
@@ -61,24 +22,32 @@ fn main() {
         .version("0.0.1")
         .about("Amxmodx plugin reverse utility")
         .author("Fedcomp")
-        .arg(
-            Arg::with_name("file")
-                .value_name("FILE")
-                .help("amxmodx file to analyze")
-                .required(true)
-                .takes_value(true),
-        )
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(info::subcommand())
+        .subcommand(sections::subcommand())
+        .subcommand(extract::subcommand())
+        .subcommand(disasm::subcommand())
+        .subcommand(decompile::subcommand())
+        .subcommand(strings::subcommand())
+        .subcommand(assemble::subcommand())
+        .subcommand(trace::subcommand())
+        .subcommand(debug::subcommand())
         .get_matches();
 
-    let file_path = matches.value_of("file").unwrap();
-    let file_path_buf = PathBuf::from(file_path);
-
-    let source = {
-        match decompile(file_path_buf) {
-            Ok(s) => s,
-            Err(e) => die!("{}", e),
-        }
+    let result = match matches.subcommand() {
+        ("info", Some(m)) => info::run(m),
+        ("sections", Some(m)) => sections::run(m),
+        ("extract", Some(m)) => extract::run(m),
+        ("disasm", Some(m)) => disasm::run(m),
+        ("decompile", Some(m)) => decompile::run(m),
+        ("strings", Some(m)) => strings::run(m),
+        ("assemble", Some(m)) => assemble::run(m),
+        ("trace", Some(m)) => trace::run(m),
+        ("debug", Some(m)) => debug::run(m),
+        _ => unreachable!("subcommand is required"),
     };
 
-    println!("{}", source);
+    if let Err(e) = result {
+        die!("{}", e);
+    }
 }
@@ -0,0 +1,100 @@
+//! Serializable summaries of the plugin file, used for machine readable output.
+
+use failure::Error;
+use serde::Serialize;
+
+use amxmodx_utils::amx::tables::{Library, Native, Public, Pubvar, Tag};
+use amxmodx_utils::amx::{File as AmxPlugin, Header};
+use amxmodx_utils::amxx::section::Metadata;
+use amxmodx_utils::amxx::{File as AmxmodxFile, ParseError};
+
+use crate::analysis::metadata::{plugin_info, PluginInfo};
+
+/// Amxmodx file, a container of the same plugin compiled for each cellsize.
+#[derive(Debug, Serialize)]
+pub struct AmxxFile {
+    pub sections: Vec<Metadata>,
+}
+
+impl AmxxFile {
+    pub fn from(file: &AmxmodxFile) -> Result<AmxxFile, ParseError> {
+        let sections = file
+            .sections()
+            .map(|s| s.map(|s| s.metadata()))
+            .collect::<Result<_, _>>()?;
+
+        Ok(AmxxFile { sections })
+    }
+}
+
+/// Header, tables and `register_plugin` arguments of the plugin image.
+#[derive(Debug, Serialize)]
+pub struct Plugin {
+    pub cellsize: usize,
+    pub header: Header,
+    pub info: Option<PluginInfo>,
+    pub publics: Vec<Public>,
+    pub natives: Vec<Native>,
+    pub libraries: Vec<Library>,
+    pub pubvars: Vec<Pubvar>,
+    pub tags: Vec<Tag>,
+}
+
+impl Plugin {
+    pub fn from(plugin: &AmxPlugin) -> Result<Plugin, Error> {
+        let opcodes = plugin.opcodes()?.collect::<Result<Vec<_>, _>>()?;
+        let publics = plugin.publics()?.collect::<Result<Vec<_>, _>>()?;
+        let natives = plugin.natives()?.collect::<Result<Vec<_>, _>>()?;
+        let info = plugin_info(
+            &opcodes,
+            &publics,
+            &natives,
+            plugin.dat_slice()?,
+            plugin.cellsize(),
+        );
+
+        Ok(Plugin {
+            cellsize: plugin.cellsize(),
+            header: plugin.header(),
+            info,
+            publics,
+            natives,
+            libraries: plugin.libraries()?.collect::<Result<_, _>>()?,
+            pubvars: plugin.pubvars()?.collect::<Result<_, _>>()?,
+            tags: plugin.tags()?.collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AmxxFile, Plugin};
+    use crate::util::tests::load_fixture;
+    use amxmodx_utils::amxx::File as AmxmodxFile;
+    use serde_json::json;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn it_serializes_file_and_plugin() {
+        let bin = load_fixture("simple.amxx183");
+        let file = AmxmodxFile::try_from(&bin[..]).unwrap();
+
+        let sections = serde_json::to_value(AmxxFile::from(&file).unwrap()).unwrap();
+        assert_eq!(sections["sections"][0]["cellsize"], json!(4));
+
+        let section = file.sections().next().unwrap().unwrap();
+        let plugin = Plugin::from(&section.unpack().unwrap()).unwrap();
+        let plugin = serde_json::to_value(plugin).unwrap();
+
+        assert_eq!(plugin["header"]["flags"], json!(["debug"]));
+        assert_eq!(
+            plugin["publics"],
+            json!([{"name": "plugin_init", "address": 8}])
+        );
+        assert_eq!(plugin["natives"][0]["name"], json!("register_plugin"));
+        assert_eq!(
+            plugin["info"],
+            json!({"name": "simple plugin", "version": "0.1", "author": "Fedcomp"})
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use amxmodx_utils::amx::opcode_type::OpcodeType;
+use amxmodx_utils::amx::File as AmxFile;
+use amxmodx_utils::amx::UCell;
+use failure::Error;
+
+fn natives_names(amx_file: &AmxFile) -> Result<Vec<String>, Error> {
+    Ok(amx_file
+        .natives()?
+        .map(|n| n.map(|n| n.name))
+        .collect::<Result<_, _>>()?)
+}
+
+fn publics_in_cells(amx_file: &AmxFile) -> Result<Vec<(String, UCell)>, Error> {
+    let cellsize = amx_file.cellsize() as UCell;
+    Ok(amx_file
+        .publics()?
+        .map(|p| p.map(|p| (p.name, p.address / cellsize)))
+        .collect::<Result<_, _>>()?)
+}
+
+fn opcodes_in_cells(amx_file: &AmxFile) -> Result<Vec<(OpcodeType, UCell)>, Error> {
+    let cellsize = amx_file.cellsize() as UCell;
+    Ok(amx_file
+        .opcodes()?
+        .map(|o| o.map(|o| (o.code(), o.address() / cellsize)))
+        .collect::<Result<_, _>>()?)
+}
+
+/// Checks that both plugins, possibly built for different cellsizes,
+/// contain the same publics, natives and code.
+///
+/// Addresses are compared in cells, operands are not compared since
+/// most of them are cellsize dependent offsets.
+pub fn is_equivalent(amx_file: &AmxFile, other: &AmxFile) -> Result<bool, Error> {
+    Ok(natives_names(amx_file)? == natives_names(other)?
+        && publics_in_cells(amx_file)? == publics_in_cells(other)?
+        && opcodes_in_cells(amx_file)? == opcodes_in_cells(other)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::is_equivalent;
+    use crate::util::tests::load_fixture;
+    use amxmodx_utils::amx::File as AmxFile;
+    use amxmodx_utils::amxx::File as AmxxFile;
+
+    #[test]
+    fn it_finds_sections_equivalent() {
+        let amxx_bin = load_fixture("simple.amxx181");
+        let amxx_file = AmxxFile::try_from(&amxx_bin[..]).unwrap();
+        let plugins: Vec<AmxFile> = amxx_file
+            .sections()
+            .map(|s| s.unwrap().unpack().unwrap())
+            .collect();
+
+        assert!(is_equivalent(&plugins[0], &plugins[1]).unwrap());
+        assert!(is_equivalent(&plugins[1], &plugins[0]).unwrap());
+    }
+
+    #[test]
+    fn it_finds_different_plugins_not_equivalent() {
+        let simple = AmxFile::try_from(&load_fixture("simple.amx183")[..]).unwrap();
+        let two_natives = AmxFile::try_from(&load_fixture("two_natives.amx183")[..]).unwrap();
+
+        assert!(!is_equivalent(&simple, &two_natives).unwrap());
+    }
+}
@@ -1,6 +1,8 @@
 pub mod debug_u8;
+pub mod equivalence;
 pub mod string_zero;
 pub use self::debug_u8::DebugU8;
+pub use self::equivalence::is_equivalent;
 pub use self::string_zero::ReadByteString;
 
 #[cfg(test)]
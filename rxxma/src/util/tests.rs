@@ -1,9 +1,43 @@
+use std::convert::TryFrom;
 use std::fs::File;
 use std::io::prelude::*;
 
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::opcodes_iterator::OpcodesIterator;
+use amxmodx_utils::amx::File as AmxPlugin;
+
+use crate::asm::assemble;
+use crate::vm::Vm;
+
 pub fn load_fixture(filename: &str) -> Vec<u8> {
     let mut file_bin: Vec<u8> = Vec::new();
     let mut file = File::open(format!("test/fixtures/{}", filename)).unwrap();
     file.read_to_end(&mut file_bin).unwrap();
     file_bin
 }
+
+/// Decodes listing of 32 bit cells into opcodes, first opcode is at address 0.
+pub fn decode(cells: &[u64]) -> Vec<Opcode> {
+    let cod_bin: Vec<u8> = cells
+        .iter()
+        .flat_map(|&c| (c as u32).to_le_bytes().to_vec())
+        .collect();
+
+    OpcodesIterator::new(&cod_bin, 4)
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+/// DAT section of 32 bit cells.
+pub fn cells(values: &[u32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|v| v.to_le_bytes().to_vec())
+        .collect()
+}
+
+/// Machine running the plugin assembled from the listing.
+pub fn vm(source: &str) -> Vm {
+    let image = assemble(source).unwrap();
+    Vm::new(&AmxPlugin::try_from(&image[..]).unwrap()).unwrap()
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use failure::{format_err, Error};
+use serde::Serialize;
+
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::opcode_type::OpcodeType::OpCasetbl;
+use amxmodx_utils::amx::UCell;
+
+use crate::analysis::cfg::CfgError;
+use crate::analysis::xref::Callee;
+use crate::analysis::{functions, CallGraph, Cfg};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlockCoverage {
+    pub address: UCell,
+    pub instructions: usize,
+    /// Times the block was entered.
+    pub hits: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FunctionCoverage {
+    pub address: UCell,
+    pub name: String,
+    pub instructions: usize,
+    pub executed: usize,
+    pub blocks: Vec<BlockCoverage>,
+}
+
+impl FunctionCoverage {
+    pub fn blocks_executed(&self) -> usize {
+        self.blocks.iter().filter(|b| b.hits > 0).count()
+    }
+}
+
+/// Executed code of the plugin by functions and their basic blocks.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Coverage {
+    pub functions: Vec<FunctionCoverage>,
+    /// Executions of every instruction, case tables excluded.
+    pub instructions: BTreeMap<UCell, u64>,
+}
+
+impl Coverage {
+    /// Maps instruction hits, as counted by `Tracer`, over the functions.
+    pub fn new(
+        opcodes: &[Opcode],
+        call_graph: &CallGraph,
+        hits: &BTreeMap<UCell, u64>,
+    ) -> Result<Coverage, CfgError> {
+        let hits_at = |address: UCell| hits.get(&address).cloned().unwrap_or(0);
+
+        let mut coverage = Coverage {
+            functions: vec![],
+            instructions: opcodes
+                .iter()
+                .filter(|o| o.code() != OpCasetbl)
+                .map(|o| (o.address(), hits_at(o.address())))
+                .collect(),
+        };
+
+        for function in functions(opcodes) {
+            let address = function[0].address();
+            let cfg = Cfg::from_opcodes(function)?;
+            let blocks: Vec<BlockCoverage> = cfg
+                .blocks()
+                .iter()
+                .map(|b| BlockCoverage {
+                    address: b.address(),
+                    instructions: b.opcodes.len(),
+                    hits: hits_at(b.address()),
+                })
+                .collect();
+
+            coverage.functions.push(FunctionCoverage {
+                address,
+                name: call_graph.name(Callee::Function(address)),
+                instructions: blocks.iter().map(|b| b.instructions).sum(),
+                executed: function
+                    .iter()
+                    .filter(|o| o.code() != OpCasetbl && hits_at(o.address()) > 0)
+                    .count(),
+                blocks,
+            });
+        }
+
+        Ok(coverage)
+    }
+
+    /// Coverage file, the address and hits of every instruction per line.
+    ///
+    /// Addresses are written as `disasm` does, so the file lines up with its output.
+    pub fn to_file(&self) -> String {
+        self.instructions
+            .iter()
+            .map(|(address, hits)| format!("0x{:08X} {}\n", address, hits))
+            .collect()
+    }
+
+    /// Reads hits back from the coverage file.
+    pub fn read_file(text: &str) -> Result<BTreeMap<UCell, u64>, Error> {
+        let mut hits = BTreeMap::new();
+        for (i, line) in text
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+        {
+            let parsed = line.split_once(' ').and_then(|(address, count)| {
+                let address = UCell::from_str_radix(address.trim_start_matches("0x"), 16).ok()?;
+                Some((address, count.trim().parse().ok()?))
+            });
+            let (address, count) =
+                parsed.ok_or_else(|| format_err!("Invalid coverage line {}: {}", i + 1, line))?;
+            hits.insert(address, count);
+        }
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Coverage;
+    use crate::analysis::CallGraph;
+    use crate::util::tests::vm;
+    use crate::vm::trace::Tracer;
+
+    #[test]
+    fn it_maps_hits_over_functions_and_blocks() {
+        // Only the positive branch runs
+        let mut vm = vm(r#"
+            .public abs
+            .code
+                    halt 0
+            abs:
+                    proc
+                    load.s.pri 0xC
+                    zero.alt
+                    jsgeq done
+                    neg
+            done:
+                    retn
+        "#);
+        let mut tracer = Tracer::new();
+        tracer.exec(&mut vm, "abs", &[5.into()], |_| ()).unwrap();
+
+        let call_graph = CallGraph::build(vm.opcodes(), vm.publics(), &[], None);
+        let coverage = Coverage::new(vm.opcodes(), &call_graph, tracer.hits()).unwrap();
+
+        let function = &coverage.functions[0];
+        assert_eq!(function.name, "abs");
+        assert_eq!((function.executed, function.instructions), (5, 6));
+        let blocks: Vec<(u64, u64)> = function
+            .blocks
+            .iter()
+            .map(|b| (b.address, b.hits))
+            .collect();
+        assert_eq!(blocks, [(8, 1), (32, 0), (36, 1)]);
+
+        let file = coverage.to_file();
+        assert!(file.starts_with("0x00000000 1\n0x00000008 1\n"));
+        assert_eq!(Coverage::read_file(&file).unwrap(), coverage.instructions);
+        assert!(Coverage::read_file("0x0 x").is_err());
+    }
+}
@@ -0,0 +1,372 @@
+use std::fmt;
+
+use amxmodx_utils::amx::debug::{DebugInfo, Symbol, SymbolClass, SymbolKind};
+use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+use amxmodx_utils::amx::UCell;
+
+use super::{Argument, Cell, Step, Vm, VmError};
+use crate::analysis::xref::Callee;
+use crate::analysis::CallGraph;
+
+/// Frames deeper than that are considered a corrupted stack.
+const MAX_FRAMES: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    /// Instruction at the code address is about to run.
+    Address(Cell),
+    /// Native with the index is about to be called.
+    Native(usize),
+}
+
+/// Why execution stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stop {
+    Step,
+    /// Breakpoint with the index of `Debugger::breakpoints`.
+    Breakpoint(usize),
+    /// Called public returned the value.
+    Returned(Cell),
+}
+
+/// Function frame of the call stack.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    /// Instruction to run next in the frame.
+    pub cip: Cell,
+    pub frm: Cell,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DumpFormat {
+    Int,
+    Hex,
+    Float,
+    String,
+}
+
+/// Machine controlled by breakpoints and steps, names come from the call graph and debug info.
+#[derive(Debug)]
+pub struct Debugger {
+    pub vm: Vm,
+    call_graph: CallGraph,
+    debug_info: Option<DebugInfo>,
+    breakpoints: Vec<Breakpoint>,
+}
+
+impl Debugger {
+    pub fn new(vm: Vm, call_graph: CallGraph, debug_info: Option<DebugInfo>) -> Self {
+        Debugger {
+            vm,
+            call_graph,
+            debug_info,
+            breakpoints: vec![],
+        }
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    pub fn remove_breakpoint(&mut self, index: usize) -> Option<Breakpoint> {
+        if index < self.breakpoints.len() {
+            Some(self.breakpoints.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Breakpoint at the start of the function or before the call of the native.
+    pub fn resolve(&self, name: &str) -> Option<Breakpoint> {
+        if let Some(function) = self.call_graph.function_by_name(name) {
+            return Some(Breakpoint::Address(function.address as Cell));
+        }
+        match self.call_graph.native(name)? {
+            Callee::Native(index) => Some(Breakpoint::Native(index as usize)),
+            Callee::Function(_) => None,
+        }
+    }
+
+    /// Calls the public, execution stops before its first instruction.
+    ///
+    /// The call in progress is abandoned.
+    pub fn start(&mut self, public: &str, args: &[Argument]) -> Result<(), VmError> {
+        self.vm.abort();
+        let index = self
+            .vm
+            .public_index(public)
+            .ok_or_else(|| VmError::UnknownPublic(public.to_owned()))?;
+        self.vm.call(index, args)
+    }
+
+    /// Executes a single instruction.
+    pub fn step(&mut self) -> Result<Stop, VmError> {
+        self.run(|_| true)
+    }
+
+    /// Executes a single instruction, running called functions to their return.
+    pub fn step_over(&mut self) -> Result<Stop, VmError> {
+        let cip = self.vm.registers.cip;
+        let is_call = self
+            .vm
+            .opcode(cip)
+            .is_some_and(|o| o.code() == OpCall || o.code() == OpCallPri);
+        if !is_call {
+            return self.step();
+        }
+
+        let stk = self.vm.registers.stk;
+        let index = self
+            .vm
+            .opcodes()
+            .iter()
+            .position(|o| o.address() as Cell == cip);
+        let next = index
+            .and_then(|i| self.vm.opcodes().get(i + 1))
+            .map(|o| o.address() as Cell);
+        // Returning pops the arguments, deeper recursive calls keep the stack lower
+        self.run(|vm| Some(vm.registers.cip) == next && vm.registers.stk >= stk)
+    }
+
+    /// Runs until a breakpoint is hit or the public returns.
+    pub fn resume(&mut self) -> Result<Stop, VmError> {
+        self.run(|_| false)
+    }
+
+    fn run<F: Fn(&Vm) -> bool>(&mut self, until: F) -> Result<Stop, VmError> {
+        loop {
+            if let Step::Returned(value) = self.vm.step()? {
+                return Ok(Stop::Returned(value));
+            }
+            if let Some(index) = self.breakpoint_hit() {
+                return Ok(Stop::Breakpoint(index));
+            }
+            if until(&self.vm) {
+                return Ok(Stop::Step);
+            }
+        }
+    }
+
+    fn breakpoint_hit(&self) -> Option<usize> {
+        let cip = self.vm.registers.cip;
+        let opcode = self.vm.opcode(cip)?;
+        let native = match opcode.code() {
+            OpSysreqC => opcode.argument().map(|a| a as usize),
+            OpSysreqPri => Some(self.vm.registers.pri as usize),
+            _ => None,
+        };
+
+        self.breakpoints.iter().position(|b| match *b {
+            Breakpoint::Address(address) => address == cip,
+            Breakpoint::Native(index) => native == Some(index),
+        })
+    }
+
+    /// Call stack from the innermost frame, empty when nothing runs.
+    pub fn frames(&self) -> Vec<Frame> {
+        let mut frames = vec![];
+        if !self.vm.is_running() {
+            return frames;
+        }
+
+        let cellsize = self.vm.cellsize() as Cell;
+        let mut frame = Frame {
+            cip: self.vm.registers.cip,
+            frm: self.vm.registers.frm,
+        };
+        // Frame holds the caller FRM followed by the return address
+        while frames.len() < MAX_FRAMES {
+            frames.push(frame);
+            let caller = self.vm.read_cell(frame.frm);
+            let address = self.vm.read_cell(frame.frm + cellsize);
+            match (caller, address) {
+                (Ok(frm), Ok(cip)) if cip != 0 => frame = Frame { cip, frm },
+                _ => break,
+            }
+        }
+        frames
+    }
+
+    /// Function, offset and source line of the code address.
+    pub fn location(&self, cip: Cell) -> String {
+        let address = cip as UCell;
+        let function = self
+            .call_graph
+            .functions
+            .iter()
+            .rev()
+            .find(|f| f.address <= address);
+
+        let mut location = match function {
+            Some(f) => format!(
+                "{}+0x{:X}",
+                self.call_graph.name(Callee::Function(f.address)),
+                address - f.address
+            ),
+            None => format!("0x{:X}", address),
+        };
+        if let Some(debug_info) = self.debug_info.as_ref() {
+            if let (Some(file), Some(line)) = (debug_info.file(address), debug_info.line(address)) {
+                location.push_str(&format!(" at {}:{}", file.name, line));
+            }
+        }
+        location
+    }
+
+    /// Variable visible at CIP by its debug name, returns the address of its value.
+    ///
+    /// Locals shadow globals, references are followed to the variable they point at.
+    pub fn variable(&self, name: &str) -> Option<(Cell, &Symbol)> {
+        let debug_info = self.debug_info.as_ref()?;
+        let cip = self.vm.registers.cip as UCell;
+
+        let symbol = debug_info
+            .locals(cip)
+            .find(|s| s.name == name)
+            .or_else(|| {
+                debug_info.symbols.iter().find(|s| {
+                    s.name == name
+                        && s.kind != SymbolKind::Function
+                        && s.class != SymbolClass::Local
+                })
+            })?;
+
+        let offset = self.vm.wrap(symbol.address as i64);
+        let address = match symbol.class {
+            SymbolClass::Local => self.vm.registers.frm + offset,
+            _ => offset,
+        };
+        match symbol.kind {
+            SymbolKind::Reference | SymbolKind::RefArray => {
+                Some((self.vm.read_cell(address).ok()?, symbol))
+            }
+            _ => Some((address, symbol)),
+        }
+    }
+
+    /// Cells at the address, one line each, strings take a single line.
+    pub fn dump(
+        &self,
+        address: Cell,
+        count: usize,
+        format: DumpFormat,
+    ) -> Result<Vec<String>, VmError> {
+        if format == DumpFormat::String {
+            let string = self.vm.read_string(address)?;
+            return Ok(vec![format!("0x{:08X}  {:?}", address, string)]);
+        }
+
+        let cells = self.vm.read_array(address, count)?;
+        let cellsize = self.vm.cellsize() as Cell;
+        Ok(cells
+            .iter()
+            .enumerate()
+            .map(|(i, &cell)| {
+                let value = match format {
+                    DumpFormat::Hex => format!("0x{:X}", self.vm.unsigned(cell)),
+                    DumpFormat::Float => self.vm.cell_to_float(cell).to_string(),
+                    _ => cell.to_string(),
+                };
+                format!("0x{:08X}  {}", address + i as Cell * cellsize, value)
+            })
+            .collect())
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Breakpoint::Address(address) => write!(f, "0x{:X}", address),
+            Breakpoint::Native(index) => write!(f, "native #{}", index),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Breakpoint, Debugger, DumpFormat, Frame, Stop};
+    use crate::analysis::CallGraph;
+    use crate::util::tests::vm;
+    use amxmodx_utils::amx::tables::Native;
+
+    fn debugger() -> Debugger {
+        // inner(x) calls greet(x), outer() calls inner(7)
+        let mut vm = vm(r#"
+            .native greet
+            .public outer
+            .public inner
+            .data
+            name: .string "bob"
+            .code
+                    halt 0
+            inner:
+                    proc
+                    push.s 0xC
+                    push.c 4
+                    sysreq.c greet
+                    stack 8
+                    retn
+            outer:
+                    proc
+                    push.c 7
+                    push.c 4
+                    call inner
+                    add.c 1
+                    retn
+        "#);
+        vm.natives_mut().mock("greet", 41);
+
+        let natives = [Native {
+            name: "greet".to_owned(),
+            address: 0,
+        }];
+        let call_graph = CallGraph::build(vm.opcodes(), vm.publics(), &natives, None);
+        Debugger::new(vm, call_graph, None)
+    }
+
+    #[test]
+    fn it_stops_at_breakpoints() {
+        let mut debugger = debugger();
+        let native = debugger.resolve("greet").unwrap();
+        assert_eq!(native, Breakpoint::Native(0));
+        assert_eq!(debugger.add_breakpoint(native), 0);
+        assert_eq!(debugger.resolve("inner"), Some(Breakpoint::Address(8)));
+        assert_eq!(debugger.resolve("missing"), None);
+
+        debugger.start("outer", &[]).unwrap();
+        assert_eq!(debugger.resume().unwrap(), Stop::Breakpoint(0));
+        assert_eq!(debugger.location(debugger.vm.registers.cip), "inner+0x14");
+
+        let frames = debugger.frames();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(debugger.location(frames[1].cip), "outer+0x1C");
+        assert_eq!(debugger.vm.read_cell(frames[0].frm + 12).unwrap(), 7);
+
+        assert_eq!(debugger.resume().unwrap(), Stop::Returned(42));
+        assert_eq!(debugger.frames(), Vec::<Frame>::new());
+    }
+
+    #[test]
+    fn it_steps_over_calls() {
+        let mut debugger = debugger();
+        debugger.start("outer", &[]).unwrap();
+        for _ in 0..3 {
+            assert_eq!(debugger.step().unwrap(), Stop::Step);
+        }
+        assert_eq!(debugger.location(debugger.vm.registers.cip), "outer+0x14");
+
+        assert_eq!(debugger.step_over().unwrap(), Stop::Step);
+        assert_eq!(debugger.location(debugger.vm.registers.cip), "outer+0x1C");
+        assert_eq!(debugger.vm.registers.pri, 41);
+
+        let dump = debugger.dump(0, 1, DumpFormat::String).unwrap();
+        assert_eq!(dump, ["0x00000000  \"bob\""]);
+        let dump = debugger.dump(0, 2, DumpFormat::Hex).unwrap();
+        assert_eq!(dump, ["0x00000000  0x62", "0x00000004  0x6F"]);
+    }
+}
@@ -0,0 +1,1111 @@
+//! Abstract machine running plugins without the game server.
+//!
+//! Memory starts with DAT section, followed by the heap growing up and
+//! the stack growing down from `stp`. Addresses are relative to DAT section,
+//! code addresses are relative to COD section, as in the image.
+
+pub mod coverage;
+pub mod debugger;
+mod native;
+pub mod stubs;
+pub mod trace;
+
+pub use self::native::{Call, NativeFn, Natives};
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::mem;
+
+use failure::{Error, Fail};
+use serde::Serialize;
+
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::opcode_type::OpcodeType::*;
+use amxmodx_utils::amx::tables::Public;
+use amxmodx_utils::amx::{File as AmxPlugin, UCell};
+
+/// Value of the cell, sign extended from the plugin cellsize.
+pub type Cell = i64;
+
+/// Largest character of unpacked strings, cells above it start packed strings.
+const UNPACKED_MAX: u64 = 0xFF;
+
+#[derive(Debug, Fail)]
+pub enum VmError {
+    #[fail(display = "Plugin has no public {}", _0)]
+    UnknownPublic(String),
+    #[fail(display = "Native {} is not registered", _0)]
+    UnregisteredNative(String),
+    #[fail(display = "Plugin has no native with index {}", _0)]
+    InvalidNativeIndex(Cell),
+    #[fail(display = "Native {} failed: {}", _0, _1)]
+    Native(String, String),
+    #[fail(display = "Invalid instruction at 0x{:X}", _0)]
+    InvalidInstruction(Cell),
+    #[fail(display = "Memory access out of bounds at 0x{:X}", _0)]
+    MemoryAccess(Cell),
+    #[fail(display = "Stack overflow")]
+    StackOverflow,
+    #[fail(display = "Stack underflow")]
+    StackUnderflow,
+    #[fail(display = "Heap underflow")]
+    HeapUnderflow,
+    #[fail(display = "Division by zero at 0x{:X}", _0)]
+    DivideByZero(Cell),
+    #[fail(display = "Index {} out of bounds, maximum is {}", _0, _1)]
+    Bounds(Cell, Cell),
+    #[fail(display = "Plugin halted with code {}", _0)]
+    Halted(Cell),
+    #[fail(display = "No function is running")]
+    NotRunning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct Registers {
+    /// Primary register, accumulator and return value.
+    pub pri: Cell,
+    /// Alternate register.
+    pub alt: Cell,
+    /// Frame pointer, stack address of the current function frame.
+    pub frm: Cell,
+    /// Stack pointer.
+    pub stk: Cell,
+    /// Heap pointer.
+    pub hea: Cell,
+    /// Address of the next instruction.
+    pub cip: Cell,
+}
+
+/// Argument passed to the public, arrays and strings are copied to the heap.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Argument {
+    Cell(Cell),
+    Array(Vec<Cell>),
+    String(String),
+}
+
+impl From<Cell> for Argument {
+    fn from(value: Cell) -> Self {
+        Argument::Cell(value)
+    }
+}
+
+impl From<&str> for Argument {
+    fn from(value: &str) -> Self {
+        Argument::String(value.to_owned())
+    }
+}
+
+/// Result of a single instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    Running,
+    /// Called public returned the value.
+    Returned(Cell),
+}
+
+#[derive(Debug)]
+pub struct Vm {
+    pub registers: Registers,
+    cellsize: usize,
+    memory: Vec<u8>,
+    /// Heap bottom, the end of DAT section.
+    heap_base: Cell,
+    /// Stack top, the highest address stack may take.
+    stack_top: Cell,
+    /// Image offsets of COD and DAT sections, as `lctrl` reports them.
+    cod: Cell,
+    dat: Cell,
+    opcodes: Vec<Opcode>,
+    /// Index of the opcode by its address.
+    addresses: HashMap<UCell, usize>,
+    /// Address right after the last instruction.
+    cod_end: UCell,
+    publics: Vec<Public>,
+    native_names: Vec<String>,
+    natives: Natives,
+    /// Native calls made so far.
+    calls: Vec<Call>,
+    /// Registers before the running call, its stack and heap are restored after it.
+    call: Option<Registers>,
+}
+
+impl Vm {
+    pub fn new(plugin: &AmxPlugin) -> Result<Vm, Error> {
+        let header = plugin.header();
+        let cellsize = plugin.cellsize();
+        let opcodes = plugin.opcodes()?.collect::<Result<Vec<_>, _>>()?;
+        let addresses = opcodes
+            .iter()
+            .enumerate()
+            .map(|(i, o)| (o.address(), i))
+            .collect();
+
+        let mut memory = plugin.dat_slice()?.to_vec();
+        let memory_size = header.stp.saturating_sub(header.dat) as usize;
+        memory.resize(memory_size.max(memory.len()), 0);
+
+        let heap_base = Cell::from(header.hea - header.dat);
+        // The topmost cell is reserved, as the reference implementation does
+        let stack_top = memory.len() as Cell - cellsize as Cell;
+        let registers = Registers {
+            frm: stack_top,
+            stk: stack_top,
+            hea: heap_base,
+            ..Registers::default()
+        };
+
+        Ok(Vm {
+            registers,
+            cellsize,
+            memory,
+            heap_base,
+            stack_top,
+            cod: Cell::from(header.cod),
+            dat: Cell::from(header.dat),
+            opcodes,
+            addresses,
+            cod_end: UCell::from(header.dat - header.cod),
+            publics: plugin.publics()?.collect::<Result<_, _>>()?,
+            native_names: plugin
+                .natives()?
+                .map(|n| n.map(|n| n.name))
+                .collect::<Result<_, _>>()?,
+            natives: Natives::new(),
+            calls: vec![],
+            call: None,
+        })
+    }
+
+    pub fn cellsize(&self) -> usize {
+        self.cellsize
+    }
+
+    pub fn natives_mut(&mut self) -> &mut Natives {
+        &mut self.natives
+    }
+
+    /// Natives called so far, with their arguments.
+    pub fn calls(&self) -> &[Call] {
+        &self.calls
+    }
+
+    /// Calls of the native, in the order they were made.
+    pub fn calls_of(&self, native: &str) -> Vec<&Call> {
+        self.calls.iter().filter(|c| c.native == native).collect()
+    }
+
+    pub fn clear_calls(&mut self) {
+        self.calls.clear();
+    }
+
+    /// Natives the plugin uses, which are not registered.
+    pub fn missing_natives(&self) -> Vec<&str> {
+        self.native_names
+            .iter()
+            .filter(|n| !self.natives.contains(n))
+            .map(String::as_str)
+            .collect()
+    }
+
+    pub fn publics(&self) -> &[Public] {
+        &self.publics
+    }
+
+    pub fn public_index(&self, name: &str) -> Option<usize> {
+        self.publics.iter().position(|p| p.name == name)
+    }
+
+    /// Names of the natives by index.
+    pub fn native_names(&self) -> &[String] {
+        &self.native_names
+    }
+
+    /// Instruction at the code address.
+    pub fn opcode(&self, address: Cell) -> Option<&Opcode> {
+        let index = self.addresses.get(&(address as UCell))?;
+        Some(&self.opcodes[*index])
+    }
+
+    pub fn opcodes(&self) -> &[Opcode] {
+        &self.opcodes
+    }
+
+    /// Whether a public is being executed.
+    pub fn is_running(&self) -> bool {
+        self.call.is_some()
+    }
+
+    /// Sign extends the value to the cell width.
+    pub(crate) fn wrap(&self, value: i64) -> Cell {
+        match self.cellsize {
+            4 => Cell::from(value as i32),
+            _ => value,
+        }
+    }
+
+    pub(crate) fn unsigned(&self, value: Cell) -> u64 {
+        match self.cellsize {
+            4 => u64::from(value as u32),
+            _ => value as u64,
+        }
+    }
+
+    /// Float stored in the cell, 32 bit cells hold `f32`.
+    pub fn cell_to_float(&self, cell: Cell) -> f64 {
+        match self.cellsize {
+            4 => f64::from(f32::from_bits(cell as u32)),
+            _ => f64::from_bits(cell as u64),
+        }
+    }
+
+    pub fn float_to_cell(&self, value: f64) -> Cell {
+        match self.cellsize {
+            4 => Cell::from((value as f32).to_bits() as i32),
+            _ => value.to_bits() as Cell,
+        }
+    }
+
+    fn range(&self, address: Cell, size: usize) -> Result<std::ops::Range<usize>, VmError> {
+        let start = usize::try_from(address).map_err(|_| VmError::MemoryAccess(address))?;
+        match start.checked_add(size) {
+            Some(end) if end <= self.memory.len() => Ok(start..end),
+            _ => Err(VmError::MemoryAccess(address)),
+        }
+    }
+
+    /// Operand of `lodb.i` and `strb.i`, 1, 2 or 4 bytes fitting into the cell.
+    fn byte_size(&self, size: Cell, cip: Cell) -> Result<usize, VmError> {
+        match size {
+            1 | 2 | 4 if size as usize <= self.cellsize => Ok(size as usize),
+            _ => Err(VmError::InvalidInstruction(cip)),
+        }
+    }
+
+    /// Operand of `movs`, `cmps` and `fill`, a whole number of cells.
+    fn block_size(&self, size: Cell, cip: Cell) -> Result<usize, VmError> {
+        match size {
+            0.. if size % self.cellsize as Cell == 0 => Ok(size as usize),
+            _ => Err(VmError::InvalidInstruction(cip)),
+        }
+    }
+
+    /// Operand of `lidx.b` and `idxaddr.b`, shifts out of the cell are malformed.
+    fn shift(&self, shift: Cell, cip: Cell) -> Result<u32, VmError> {
+        match shift {
+            0.. if shift < (self.cellsize * 8) as Cell => Ok(shift as u32),
+            _ => Err(VmError::InvalidInstruction(cip)),
+        }
+    }
+
+    pub fn read_cell(&self, address: Cell) -> Result<Cell, VmError> {
+        let range = self.range(address, self.cellsize)?;
+        let mut cell = [0u8; 8];
+        cell[..self.cellsize].copy_from_slice(&self.memory[range]);
+        Ok(self.wrap(i64::from_le_bytes(cell)))
+    }
+
+    pub fn write_cell(&mut self, address: Cell, value: Cell) -> Result<(), VmError> {
+        let range = self.range(address, self.cellsize)?;
+        let cellsize = self.cellsize;
+        self.memory[range].copy_from_slice(&value.to_le_bytes()[..cellsize]);
+        Ok(())
+    }
+
+    pub fn read_array(&self, address: Cell, count: usize) -> Result<Vec<Cell>, VmError> {
+        (0..count)
+            .map(|i| self.read_cell(address.wrapping_add((i * self.cellsize) as Cell)))
+            .collect()
+    }
+
+    pub fn write_array(&mut self, address: Cell, values: &[Cell]) -> Result<(), VmError> {
+        for (i, &value) in values.iter().enumerate() {
+            self.write_cell(address.wrapping_add((i * self.cellsize) as Cell), value)?;
+        }
+        Ok(())
+    }
+
+    /// Zero terminated string, either unpacked or packed.
+    pub fn read_string(&self, address: Cell) -> Result<String, VmError> {
+        let mut bytes = vec![];
+        let mut cell_address = address;
+        let packed = self.unsigned(self.read_cell(address)?) > UNPACKED_MAX;
+
+        'cells: loop {
+            let cell = self.unsigned(self.read_cell(cell_address)?);
+            cell_address += self.cellsize as Cell;
+
+            if !packed {
+                if cell == 0 {
+                    break;
+                }
+                bytes.push(cell as u8);
+                continue;
+            }
+            for shift in (0..self.cellsize).rev() {
+                let c = (cell >> (shift * 8)) as u8;
+                if c == 0 {
+                    break 'cells;
+                }
+                bytes.push(c);
+            }
+        }
+
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Writes unpacked string of at most `max_length` characters, returns the number written.
+    pub fn write_string(
+        &mut self,
+        address: Cell,
+        text: &str,
+        max_length: usize,
+    ) -> Result<usize, VmError> {
+        let bytes: Vec<u8> = text.bytes().take(max_length).collect();
+        let mut cells: Vec<Cell> = bytes.iter().map(|&b| Cell::from(b)).collect();
+        cells.push(0);
+        self.write_array(address, &cells)?;
+        Ok(bytes.len())
+    }
+
+    /// Allocates cells on the heap, returns their address.
+    pub fn allot(&mut self, cells: usize) -> Result<Cell, VmError> {
+        let address = self.registers.hea;
+        let hea = address + (cells * self.cellsize) as Cell;
+        if hea > self.registers.stk {
+            return Err(VmError::StackOverflow);
+        }
+        self.registers.hea = hea;
+        Ok(address)
+    }
+
+    /// Frees the heap down to the address returned by `allot`.
+    pub fn release(&mut self, address: Cell) -> Result<(), VmError> {
+        if address < self.heap_base {
+            return Err(VmError::HeapUnderflow);
+        }
+        self.registers.hea = self.registers.hea.min(address);
+        Ok(())
+    }
+
+    pub fn push(&mut self, value: Cell) -> Result<(), VmError> {
+        let stk = self.registers.stk.wrapping_sub(self.cellsize as Cell);
+        if stk < self.registers.hea {
+            return Err(VmError::StackOverflow);
+        }
+        self.write_cell(stk, value)?;
+        self.registers.stk = stk;
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Result<Cell, VmError> {
+        let stk = self.registers.stk;
+        if stk > self.stack_top {
+            return Err(VmError::StackUnderflow);
+        }
+        let value = self.read_cell(stk)?;
+        self.registers.stk = stk + self.cellsize as Cell;
+        Ok(value)
+    }
+
+    fn check_stack(&self) -> Result<(), VmError> {
+        let Registers { stk, hea, .. } = self.registers;
+        if stk < hea {
+            Err(VmError::StackOverflow)
+        } else if stk > self.stack_top {
+            Err(VmError::StackUnderflow)
+        } else if hea < self.heap_base {
+            Err(VmError::HeapUnderflow)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Runs the public with the arguments until it returns, `amx_Exec` by name.
+    pub fn exec(&mut self, public: &str, args: &[Argument]) -> Result<Cell, VmError> {
+        let index = self
+            .public_index(public)
+            .ok_or_else(|| VmError::UnknownPublic(public.to_owned()))?;
+        self.exec_index(index, args)
+    }
+
+    /// Runs the public with the index of publics table.
+    pub fn exec_index(&mut self, index: usize, args: &[Argument]) -> Result<Cell, VmError> {
+        self.call(index, args)?;
+        loop {
+            if let Step::Returned(value) = self.step()? {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Prepares the call of the public, which is then run with `step`.
+    ///
+    /// Public returns to the address 0, which holds `halt 0` in every plugin.
+    pub fn call(&mut self, index: usize, args: &[Argument]) -> Result<(), VmError> {
+        let address = self
+            .publics
+            .get(index)
+            .map(|p| p.address as Cell)
+            .ok_or_else(|| VmError::UnknownPublic(format!("#{}", index)))?;
+        let saved = self.registers;
+
+        let mut values = vec![];
+        for arg in args.iter() {
+            values.push(match *arg {
+                Argument::Cell(value) => value,
+                Argument::Array(ref cells) => {
+                    let array = self.allot(cells.len())?;
+                    self.write_array(array, cells)?;
+                    array
+                }
+                Argument::String(ref text) => {
+                    let string = self.allot(text.len() + 1)?;
+                    self.write_string(string, text, text.len())?;
+                    string
+                }
+            });
+        }
+        for &value in values.iter().rev() {
+            self.push(value)?;
+        }
+        self.push((values.len() * self.cellsize) as Cell)?;
+        self.push(0)?;
+
+        self.registers.cip = address;
+        self.call = Some(saved);
+        Ok(())
+    }
+
+    /// Stops the running call, restoring stack and heap.
+    pub fn abort(&mut self) {
+        if let Some(saved) = self.call.take() {
+            self.registers.frm = saved.frm;
+            self.registers.stk = saved.stk;
+            self.registers.hea = saved.hea;
+        }
+    }
+
+    fn call_native(&mut self, index: Cell) -> Result<(), VmError> {
+        let name = usize::try_from(index)
+            .ok()
+            .and_then(|i| self.native_names.get(i))
+            .cloned()
+            .ok_or(VmError::InvalidNativeIndex(index))?;
+
+        let stk = self.registers.stk;
+        let count = self.read_cell(stk)? as usize / self.cellsize;
+        let args = self.read_array(stk + self.cellsize as Cell, count)?;
+
+        let result = self.invoke(&name, &args)?;
+        self.registers.pri = self.wrap(result);
+        Ok(())
+    }
+
+    /// Calls the registered native as the plugin would, the call is recorded.
+    pub fn invoke(&mut self, name: &str, args: &[Cell]) -> Result<Cell, VmError> {
+        // Native gets the machine, so it is taken out of the registry for the call
+        let mut natives = mem::take(&mut self.natives);
+        let result = match natives.get_mut(name) {
+            Some(native) => {
+                self.calls.push(Call {
+                    native: name.to_owned(),
+                    args: args.to_vec(),
+                });
+                native(self, args)
+            }
+            None => Err(VmError::UnregisteredNative(name.to_owned())),
+        };
+        self.natives = natives;
+        result
+    }
+
+    /// Executes the instruction at CIP.
+    pub fn step(&mut self) -> Result<Step, VmError> {
+        if self.call.is_none() {
+            return Err(VmError::NotRunning);
+        }
+
+        let result = self.execute();
+        match result {
+            Ok(Some(0)) => {
+                let value = self.registers.pri;
+                self.abort();
+                Ok(Step::Returned(value))
+            }
+            Ok(Some(code)) => {
+                self.abort();
+                Err(VmError::Halted(code))
+            }
+            Ok(None) => Ok(Step::Running),
+            Err(e) => {
+                self.abort();
+                Err(e)
+            }
+        }
+    }
+
+    /// Executes the instruction, returns the code of `halt`.
+    fn execute(&mut self) -> Result<Option<Cell>, VmError> {
+        let cip = self.registers.cip;
+        let index = *self
+            .addresses
+            .get(&(cip as UCell))
+            .ok_or(VmError::InvalidInstruction(cip))?;
+        let opcode = self.opcodes[index].clone();
+        let next = self
+            .opcodes
+            .get(index + 1)
+            .map_or(self.cod_end, Opcode::address) as Cell;
+        let arg = self.wrap(opcode.argument().unwrap_or(0) as i64);
+        let cellsize = self.cellsize as Cell;
+        let bits = (self.cellsize * 8) as u32;
+
+        self.registers.cip = next;
+        let r = self.registers;
+        match opcode.code() {
+            OpLoadPri => self.registers.pri = self.read_cell(arg)?,
+            OpLoadAlt => self.registers.alt = self.read_cell(arg)?,
+            OpLoadSPri => self.registers.pri = self.read_cell(r.frm.wrapping_add(arg))?,
+            OpLoadSAlt => self.registers.alt = self.read_cell(r.frm.wrapping_add(arg))?,
+            OpLrefPri => self.registers.pri = self.read_cell(self.read_cell(arg)?)?,
+            OpLrefAlt => self.registers.alt = self.read_cell(self.read_cell(arg)?)?,
+            OpLrefSPri => {
+                self.registers.pri = self.read_cell(self.read_cell(r.frm.wrapping_add(arg))?)?
+            }
+            OpLrefSAlt => {
+                self.registers.alt = self.read_cell(self.read_cell(r.frm.wrapping_add(arg))?)?
+            }
+            OpLoadI => self.registers.pri = self.read_cell(r.pri)?,
+            OpLodbI => {
+                let range = self.range(r.pri, self.byte_size(arg, cip)?)?;
+                let mut cell = [0u8; 8];
+                cell[..range.len()].copy_from_slice(&self.memory[range]);
+                self.registers.pri = i64::from_le_bytes(cell);
+            }
+            OpConstPri => self.registers.pri = arg,
+            OpConstAlt => self.registers.alt = arg,
+            OpAddrPri => self.registers.pri = r.frm.wrapping_add(arg),
+            OpAddrAlt => self.registers.alt = r.frm.wrapping_add(arg),
+            OpStorPri => self.write_cell(arg, r.pri)?,
+            OpStorAlt => self.write_cell(arg, r.alt)?,
+            OpStorSPri => self.write_cell(r.frm.wrapping_add(arg), r.pri)?,
+            OpStorSAlt => self.write_cell(r.frm.wrapping_add(arg), r.alt)?,
+            OpSrefPri => {
+                let address = self.read_cell(arg)?;
+                self.write_cell(address, self.registers.pri)?;
+            }
+            OpSrefAlt => {
+                let address = self.read_cell(arg)?;
+                self.write_cell(address, self.registers.alt)?;
+            }
+            OpSrefSPri => {
+                let address = self.read_cell(r.frm.wrapping_add(arg))?;
+                self.write_cell(address, self.registers.pri)?;
+            }
+            OpSrefSAlt => {
+                let address = self.read_cell(r.frm.wrapping_add(arg))?;
+                self.write_cell(address, self.registers.alt)?;
+            }
+            OpStorI => self.write_cell(r.alt, r.pri)?,
+            OpStrbI => {
+                let range = self.range(r.alt, self.byte_size(arg, cip)?)?;
+                let bytes = r.pri.to_le_bytes();
+                let size = range.len();
+                self.memory[range].copy_from_slice(&bytes[..size]);
+            }
+            OpLidx => {
+                self.registers.pri =
+                    self.read_cell(r.alt.wrapping_add(r.pri.wrapping_mul(cellsize)))?
+            }
+            OpLidxB => {
+                let shift = self.shift(arg, cip)?;
+                self.registers.pri =
+                    self.read_cell(r.alt.wrapping_add(r.pri.wrapping_shl(shift)))?;
+            }
+            OpIdxaddr => self.registers.pri = r.alt.wrapping_add(r.pri.wrapping_mul(cellsize)),
+            OpIdxaddrB => {
+                let shift = self.shift(arg, cip)?;
+                self.registers.pri = r.alt.wrapping_add(r.pri.wrapping_shl(shift));
+            }
+            OpAlignPri if arg < cellsize => self.registers.pri ^= cellsize - arg,
+            OpAlignAlt if arg < cellsize => self.registers.alt ^= cellsize - arg,
+            OpAlignPri | OpAlignAlt => {}
+            OpLctrl => {
+                self.registers.pri = match arg {
+                    0 => self.cod,
+                    1 => self.dat,
+                    2 => r.hea,
+                    3 => self.stack_top,
+                    4 => r.stk,
+                    5 => r.frm,
+                    6 => r.cip,
+                    _ => return Err(VmError::InvalidInstruction(cip)),
+                }
+            }
+            OpSctrl => match arg {
+                2 => self.registers.hea = r.pri,
+                4 => self.registers.stk = r.pri,
+                5 => self.registers.frm = r.pri,
+                6 => self.registers.cip = r.pri,
+                _ => return Err(VmError::InvalidInstruction(cip)),
+            },
+            OpMovePri => self.registers.pri = r.alt,
+            OpMoveAlt => self.registers.alt = r.pri,
+            OpXchg => mem::swap(&mut self.registers.pri, &mut self.registers.alt),
+            OpPushPri => self.push(r.pri)?,
+            OpPushAlt => self.push(r.alt)?,
+            OpPushR => {
+                for _ in 0..arg {
+                    self.push(self.registers.pri)?;
+                }
+            }
+            OpPushC => self.push(arg)?,
+            OpPush => self.push(self.read_cell(arg)?)?,
+            OpPushS => self.push(self.read_cell(r.frm.wrapping_add(arg))?)?,
+            OpPushaddr => self.push(r.frm.wrapping_add(arg))?,
+            OpPopPri => self.registers.pri = self.pop()?,
+            OpPopAlt => self.registers.alt = self.pop()?,
+            OpStack => {
+                self.registers.alt = r.stk;
+                self.registers.stk = r.stk.wrapping_add(arg);
+                self.check_stack()?;
+            }
+            OpHeap => {
+                self.registers.alt = r.hea;
+                self.registers.hea = r.hea.wrapping_add(arg);
+                self.check_stack()?;
+            }
+            OpProc => {
+                self.push(r.frm)?;
+                self.registers.frm = self.registers.stk;
+            }
+            OpRet | OpRetn => {
+                self.registers.frm = self.pop()?;
+                self.registers.cip = self.pop()?;
+                if opcode.code() == OpRetn {
+                    // Arguments and their size are removed by the called function
+                    let size = self.read_cell(self.registers.stk)?;
+                    self.registers.stk =
+                        self.registers.stk.wrapping_add(size).wrapping_add(cellsize);
+                    self.check_stack()?;
+                }
+            }
+            OpCall => {
+                self.push(next)?;
+                self.registers.cip = arg;
+            }
+            OpCallPri => {
+                self.push(next)?;
+                self.registers.cip = self.registers.pri;
+            }
+            OpJump => self.registers.cip = arg,
+            OpJrel => self.registers.cip = next.wrapping_add(arg),
+            OpJumpPri => self.registers.cip = r.pri,
+            OpJzer | OpJnz | OpJeq | OpJneq | OpJless | OpJleq | OpJgrtr | OpJgeq | OpJsless
+            | OpJsleq | OpJsgrtr | OpJsgeq => {
+                let (pri, alt) = (r.pri, r.alt);
+                let (upri, ualt) = (self.unsigned(pri), self.unsigned(alt));
+                let taken = match opcode.code() {
+                    OpJzer => pri == 0,
+                    OpJnz => pri != 0,
+                    OpJeq => pri == alt,
+                    OpJneq => pri != alt,
+                    OpJless => upri < ualt,
+                    OpJleq => upri <= ualt,
+                    OpJgrtr => upri > ualt,
+                    OpJgeq => upri >= ualt,
+                    OpJsless => pri < alt,
+                    OpJsleq => pri <= alt,
+                    OpJsgrtr => pri > alt,
+                    _ => pri >= alt,
+                };
+                if taken {
+                    self.registers.cip = arg;
+                }
+            }
+            OpShl => self.registers.pri = shift_left(r.pri, r.alt, bits),
+            OpShr => self.registers.pri = shift_right(self.unsigned(r.pri), r.alt, bits) as Cell,
+            OpSshr => self.registers.pri = r.pri >> (r.alt as u32 % bits),
+            OpShlCPri => self.registers.pri = shift_left(r.pri, arg, bits),
+            OpShlCAlt => self.registers.alt = shift_left(r.alt, arg, bits),
+            OpShrCPri => self.registers.pri = shift_right(self.unsigned(r.pri), arg, bits) as Cell,
+            OpShrCAlt => self.registers.alt = shift_right(self.unsigned(r.alt), arg, bits) as Cell,
+            OpSmul => self.registers.pri = r.pri.wrapping_mul(r.alt),
+            OpSdiv | OpSdivAlt => {
+                let (dividend, divisor) = match opcode.code() {
+                    OpSdiv => (r.pri, r.alt),
+                    _ => (r.alt, r.pri),
+                };
+                if divisor == 0 {
+                    return Err(VmError::DivideByZero(cip));
+                }
+                // Pawn rounds the quotient towards negative infinity
+                let mut quotient = dividend.wrapping_div(divisor);
+                let mut remainder = dividend.wrapping_rem(divisor);
+                if remainder != 0 && (remainder < 0) != (divisor < 0) {
+                    quotient -= 1;
+                    remainder += divisor;
+                }
+                self.registers.pri = quotient;
+                self.registers.alt = remainder;
+            }
+            OpUmul => {
+                self.registers.pri = self.unsigned(r.pri).wrapping_mul(self.unsigned(r.alt)) as Cell
+            }
+            OpUdiv | OpUdivAlt => {
+                let (pri, alt) = (self.unsigned(r.pri), self.unsigned(r.alt));
+                let (dividend, divisor) = match opcode.code() {
+                    OpUdiv => (pri, alt),
+                    _ => (alt, pri),
+                };
+                if divisor == 0 {
+                    return Err(VmError::DivideByZero(cip));
+                }
+                self.registers.pri = (dividend / divisor) as Cell;
+                self.registers.alt = (dividend % divisor) as Cell;
+            }
+            OpAdd => self.registers.pri = r.pri.wrapping_add(r.alt),
+            OpSub => self.registers.pri = r.pri.wrapping_sub(r.alt),
+            OpSubAlt => self.registers.pri = r.alt.wrapping_sub(r.pri),
+            OpAnd => self.registers.pri &= r.alt,
+            OpOr => self.registers.pri |= r.alt,
+            OpXor => self.registers.pri ^= r.alt,
+            OpNot => self.registers.pri = Cell::from(r.pri == 0),
+            OpNeg => self.registers.pri = r.pri.wrapping_neg(),
+            OpInvert => self.registers.pri = !r.pri,
+            OpAddC => self.registers.pri = r.pri.wrapping_add(arg),
+            OpSmulC => self.registers.pri = r.pri.wrapping_mul(arg),
+            OpZeroPri => self.registers.pri = 0,
+            OpZeroAlt => self.registers.alt = 0,
+            OpZero => self.write_cell(arg, 0)?,
+            OpZeroS => self.write_cell(r.frm.wrapping_add(arg), 0)?,
+            OpSignPri => self.registers.pri = Cell::from(r.pri as i8),
+            OpSignAlt => self.registers.alt = Cell::from(r.alt as i8),
+            OpEq | OpNeq | OpLess | OpLeq | OpGrtr | OpGeq | OpSless | OpSleq | OpSgrtr
+            | OpSgeq => {
+                let (pri, alt) = (r.pri, r.alt);
+                let (upri, ualt) = (self.unsigned(pri), self.unsigned(alt));
+                let holds = match opcode.code() {
+                    OpEq => pri == alt,
+                    OpNeq => pri != alt,
+                    OpLess => upri < ualt,
+                    OpLeq => upri <= ualt,
+                    OpGrtr => upri > ualt,
+                    OpGeq => upri >= ualt,
+                    OpSless => pri < alt,
+                    OpSleq => pri <= alt,
+                    OpSgrtr => pri > alt,
+                    _ => pri >= alt,
+                };
+                self.registers.pri = Cell::from(holds);
+            }
+            OpEqCPri => self.registers.pri = Cell::from(r.pri == arg),
+            OpEqCAlt => self.registers.pri = Cell::from(r.alt == arg),
+            OpIncPri => self.registers.pri = r.pri.wrapping_add(1),
+            OpIncAlt => self.registers.alt = r.alt.wrapping_add(1),
+            OpDecPri => self.registers.pri = r.pri.wrapping_sub(1),
+            OpDecAlt => self.registers.alt = r.alt.wrapping_sub(1),
+            OpInc | OpIncS | OpIncI | OpDec | OpDecS | OpDecI => {
+                let address = match opcode.code() {
+                    OpInc | OpDec => arg,
+                    OpIncS | OpDecS => r.frm.wrapping_add(arg),
+                    _ => r.pri,
+                };
+                let delta = match opcode.code() {
+                    OpInc | OpIncS | OpIncI => 1,
+                    _ => -1,
+                };
+                let value = self.read_cell(address)?;
+                self.write_cell(address, value.wrapping_add(delta))?;
+            }
+            OpMovs => {
+                let size = self.block_size(arg, cip)?;
+                let source = self.range(r.pri, size)?;
+                let target = self.range(r.alt, size)?;
+                self.memory.copy_within(source, target.start);
+            }
+            OpCmps => {
+                let size = self.block_size(arg, cip)?;
+                let a = self.range(r.alt, size)?;
+                let b = self.range(r.pri, size)?;
+                let difference = self.memory[a]
+                    .iter()
+                    .zip(self.memory[b].iter())
+                    .map(|(&x, &y)| i64::from(x) - i64::from(y))
+                    .find(|&d| d != 0)
+                    .unwrap_or(0);
+                self.registers.pri = difference;
+            }
+            OpFill => {
+                let value = r.pri;
+                let start = r.alt;
+                for offset in (0..self.block_size(arg, cip)?).step_by(self.cellsize) {
+                    self.write_cell(start.wrapping_add(offset as Cell), value)?;
+                }
+            }
+            OpHalt => return Ok(Some(arg)),
+            OpBounds => {
+                if self.unsigned(r.pri) > self.unsigned(arg) {
+                    return Err(VmError::Bounds(r.pri, arg));
+                }
+            }
+            OpSysreqPri => self.call_native(r.pri)?,
+            // Direct calls are relocated natives, which plugin files never hold
+            OpSysreqC | OpSysreqD => self.call_native(arg)?,
+            OpSwitch => {
+                let table = self
+                    .opcode(arg)
+                    .and_then(Opcode::case_table)
+                    .ok_or(VmError::InvalidInstruction(arg))?;
+                let pri = self.unsigned(self.registers.pri);
+                self.registers.cip = table
+                    .cases
+                    .iter()
+                    .find(|c| self.unsigned(c.value as Cell) == pri)
+                    .map_or(table.default, |c| c.address)
+                    as Cell;
+            }
+            OpSwapPri | OpSwapAlt => {
+                let top = self.read_cell(r.stk)?;
+                let register = match opcode.code() {
+                    OpSwapPri => r.pri,
+                    _ => r.alt,
+                };
+                self.write_cell(self.registers.stk, register)?;
+                match opcode.code() {
+                    OpSwapPri => self.registers.pri = top,
+                    _ => self.registers.alt = top,
+                }
+            }
+            OpFile | OpLine | OpSymbol | OpSrange | OpSymtag | OpNop | OpBreak => {}
+            OpNone | OpCasetbl => return Err(VmError::InvalidInstruction(cip)),
+        }
+
+        self.registers.pri = self.wrap(self.registers.pri);
+        self.registers.alt = self.wrap(self.registers.alt);
+        Ok(None)
+    }
+}
+
+fn shift_left(value: Cell, shift: Cell, bits: u32) -> Cell {
+    value.wrapping_shl(shift as u32 % bits)
+}
+
+fn shift_right(value: u64, shift: Cell, bits: u32) -> u64 {
+    value >> (shift as u32 % bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Argument, Vm, VmError};
+    use crate::util::tests::{load_fixture, vm};
+    use amxmodx_utils::amxx::File as AmxmodxFile;
+    use std::cell::RefCell;
+    use std::convert::TryFrom;
+    use std::rc::Rc;
+
+    #[test]
+    fn it_calls_functions_with_arguments() {
+        // sum(a, b) returns a + b, calc(x) returns sum(x, 2) * 3 / -2
+        let mut vm = vm(r#"
+            .public calc
+            .code
+                    halt 0
+            sum:
+                    proc
+                    load.s.pri 0xC
+                    load.s.alt 0x10
+                    add
+                    retn
+            calc:
+                    proc
+                    push.c 2
+                    push.s 0xC
+                    push.c 8
+                    call sum
+                    smul.c 3
+                    const.alt -2
+                    xchg
+                    sdiv.alt
+                    retn
+        "#);
+
+        assert_eq!(vm.exec("calc", &[Argument::Cell(5)]).unwrap(), -11);
+        assert_eq!(vm.exec_index(0, &[Argument::Cell(-3)]).unwrap(), 1);
+        // Stack is back where it was
+        assert_eq!(vm.registers.stk, vm.stack_top);
+    }
+
+    #[test]
+    fn it_runs_loops_switches_and_globals() {
+        // Counts to 10 in the global, then switches over it
+        let mut vm = vm(r#"
+            .public count
+            .data
+            counter: .cell 0
+            .code
+                    halt 0
+            count:
+                    proc
+            again:
+                    inc counter
+                    load.pri counter
+                    const.alt 10
+                    jsless again
+                    switch cases
+            cases:
+                    casetbl other
+                    case 10, ten
+            other:
+                    zero.pri
+                    retn
+            ten:
+                    const.pri 100
+                    retn
+        "#);
+
+        assert_eq!(vm.exec("count", &[]).unwrap(), 100);
+        assert_eq!(vm.read_cell(0).unwrap(), 10);
+        assert_eq!(vm.exec("count", &[]).unwrap(), 0);
+    }
+
+    #[test]
+    fn it_dispatches_natives() {
+        let mut vm = vm(r#"
+            .native greet
+            .public plugin_init
+            .data
+            name: .string "world"
+            .code
+                    halt 0
+            plugin_init:
+                    proc
+                    push.s 0xC
+                    push.c name
+                    push.c 8
+                    sysreq.c greet
+                    stack 0xC
+                    retn
+        "#);
+
+        assert_eq!(vm.missing_natives(), ["greet"]);
+        match vm.exec("plugin_init", &[Argument::Cell(1)]) {
+            Err(VmError::UnregisteredNative(ref name)) if name == "greet" => (),
+            e => panic!("Error should be UnregisteredNative, got {:?}", e),
+        }
+
+        let greeted = Rc::new(RefCell::new(vec![]));
+        let recorder = greeted.clone();
+        vm.natives_mut().register("greet", move |vm, args| {
+            recorder
+                .borrow_mut()
+                .push((vm.read_string(args[0])?, args[1]));
+            Ok(7)
+        });
+
+        assert_eq!(vm.exec("plugin_init", &["unused".into()]).unwrap(), 7);
+        assert_eq!(*greeted.borrow(), [("world".to_owned(), vm.heap_base)]);
+    }
+
+    #[test]
+    fn it_runs_plugin_init_with_mocked_natives() {
+        let bin = load_fixture("simple.amxx183");
+        let file = AmxmodxFile::try_from(&bin[..]).unwrap();
+        let section = file.sections().next().unwrap().unwrap();
+        let mut vm = Vm::new(&section.unpack().unwrap()).unwrap();
+
+        let registered = Rc::new(RefCell::new(vec![]));
+        let recorder = registered.clone();
+        vm.natives_mut()
+            .register("register_plugin", move |vm, args| {
+                let strings = args
+                    .iter()
+                    .map(|&a| vm.read_string(a))
+                    .collect::<Result<Vec<_>, _>>()?;
+                recorder.borrow_mut().push(strings);
+                Ok(0)
+            });
+        assert!(vm.missing_natives().is_empty());
+
+        vm.exec("plugin_init", &[]).unwrap();
+        assert_eq!(*registered.borrow(), [["simple plugin", "0.1", "Fedcomp"]]);
+    }
+
+    #[test]
+    fn it_fails_on_bad_memory_access() {
+        let mut vm = vm(r#"
+            .public bad
+            .code
+                    halt 0
+            bad:
+                    proc
+                    load.pri -4
+                    retn
+        "#);
+
+        match vm.exec("bad", &[]) {
+            Err(VmError::MemoryAccess(-4)) => (),
+            e => panic!("Error should be MemoryAccess, got {:?}", e),
+        }
+        assert!(!vm.is_running());
+    }
+
+    #[test]
+    fn it_fails_on_malformed_operands() {
+        let mut narrow = vm(r#"
+            .public load
+            .public store
+            .public index
+            .public address
+            .public move
+            .code
+                    halt 0
+            load:
+                    proc
+                    lobd.i 16
+                    retn
+            store:
+                    proc
+                    strb.i -1
+                    retn
+            index:
+                    proc
+                    lidx.b 200
+                    retn
+            address:
+                    proc
+                    idxaddr.b -1
+                    retn
+            move:
+                    proc
+                    const.pri 0x8
+                    movs -0x4
+                    retn
+        "#);
+
+        for public in &["load", "store", "index", "address", "move"] {
+            match narrow.exec(public, &[]) {
+                Err(VmError::InvalidInstruction(_)) => (),
+                e => panic!("Error should be InvalidInstruction, got {:?}", e),
+            }
+        }
+
+        let mut wide = vm(r#"
+            .cellsize 8
+            .public index
+            .code
+                    halt 0
+            index:
+                    proc
+                    const.pri 0x7FFFFFFFFFFFFFFF
+                    lidx
+                    retn
+        "#);
+
+        match wide.exec("index", &[]) {
+            Err(VmError::MemoryAccess(_)) => (),
+            e => panic!("Error should be MemoryAccess, got {:?}", e),
+        }
+    }
+}
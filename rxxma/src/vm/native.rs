@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{Cell, Vm, VmError};
+
+/// Native implementation, takes the arguments passed by the plugin and returns PRI.
+///
+/// Arrays and strings are passed by address, see `Vm::read_string` and friends.
+pub type NativeFn = Box<dyn FnMut(&mut Vm, &[Cell]) -> Result<Cell, VmError>>;
+
+/// Native called by the plugin, arguments are the cells it pushed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub native: String,
+    pub args: Vec<Cell>,
+}
+
+/// Natives available to the plugin, by name.
+#[derive(Default)]
+pub struct Natives {
+    callbacks: HashMap<String, NativeFn>,
+}
+
+impl fmt::Debug for Natives {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names: Vec<&String> = self.callbacks.keys().collect();
+        names.sort();
+        f.debug_struct("Natives")
+            .field("callbacks", &names)
+            .finish()
+    }
+}
+
+impl Natives {
+    pub fn new() -> Self {
+        Natives::default()
+    }
+
+    /// Registers the native, replacing the one registered under the same name.
+    pub fn register<F>(&mut self, name: &str, callback: F) -> &mut Self
+    where
+        F: FnMut(&mut Vm, &[Cell]) -> Result<Cell, VmError> + 'static,
+    {
+        self.callbacks.insert(name.to_owned(), Box::new(callback));
+        self
+    }
+
+    /// Registers the native always returning the value.
+    pub fn mock(&mut self, name: &str, value: Cell) -> &mut Self {
+        self.register(name, move |_, _| Ok(value))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.callbacks.contains_key(name)
+    }
+
+    pub(crate) fn get_mut(&mut self, name: &str) -> Option<&mut NativeFn> {
+        self.callbacks.get_mut(name)
+    }
+}
@@ -0,0 +1,123 @@
+use std::cmp::Ordering;
+
+use super::arg;
+use crate::vm::{Cell, Natives, Vm, VmError};
+
+/// `floatround_method` of float.inc, `floatround_round` and unknown methods round to nearest.
+const FLOATROUND_FLOOR: Cell = 1;
+const FLOATROUND_CEIL: Cell = 2;
+const FLOATROUND_TOZERO: Cell = 3;
+
+pub(super) fn register(natives: &mut Natives) {
+    natives
+        .register("float", |vm, args| {
+            Ok(vm.float_to_cell(arg("float", args, 0)? as f64))
+        })
+        .register("floatstr", |vm, args| {
+            let string = vm.read_string(arg("floatstr", args, 0)?)?;
+            Ok(vm.float_to_cell(string.trim().parse().unwrap_or(0.0)))
+        })
+        .register("floatadd", |vm, args| {
+            binary(vm, "floatadd", args, |a, b| a + b)
+        })
+        .register("floatsub", |vm, args| {
+            binary(vm, "floatsub", args, |a, b| a - b)
+        })
+        .register("floatmul", |vm, args| {
+            binary(vm, "floatmul", args, |a, b| a * b)
+        })
+        .register("floatdiv", |vm, args| {
+            binary(vm, "floatdiv", args, |a, b| a / b)
+        })
+        .register("floatabs", |vm, args| {
+            Ok(vm.float_to_cell(float(vm, "floatabs", args, 0)?.abs()))
+        })
+        .register("floatsqroot", |vm, args| {
+            Ok(vm.float_to_cell(float(vm, "floatsqroot", args, 0)?.sqrt()))
+        })
+        .register("floatcmp", |vm, args| {
+            let a = float(vm, "floatcmp", args, 0)?;
+            let b = float(vm, "floatcmp", args, 1)?;
+            Ok(match a.partial_cmp(&b) {
+                Some(Ordering::Less) => -1,
+                Some(Ordering::Greater) => 1,
+                _ => 0,
+            })
+        })
+        .register("floatround", |vm, args| {
+            let value = float(vm, "floatround", args, 0)?;
+            let rounded = match arg("floatround", args, 1)? {
+                FLOATROUND_FLOOR => value.floor(),
+                FLOATROUND_CEIL => value.ceil(),
+                FLOATROUND_TOZERO => value.trunc(),
+                _ => (value + 0.5).floor(),
+            };
+            Ok(rounded as Cell)
+        });
+}
+
+fn float(vm: &Vm, native: &str, args: &[Cell], index: usize) -> Result<f64, VmError> {
+    Ok(vm.cell_to_float(arg(native, args, index)?))
+}
+
+fn binary<F>(vm: &mut Vm, native: &str, args: &[Cell], operation: F) -> Result<Cell, VmError>
+where
+    F: Fn(f64, f64) -> f64,
+{
+    let a = float(vm, native, args, 0)?;
+    let b = float(vm, native, args, 1)?;
+    Ok(vm.float_to_cell(operation(a, b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::register;
+    use crate::util::tests::vm;
+
+    #[test]
+    fn it_runs_float_natives() {
+        // Float:half = 0.5, x = floatround(float(3) * half + 1.0)
+        let mut vm = vm(r#"
+            .native float
+            .native floatmul
+            .native floatadd
+            .native floatround
+            .public calc
+            .code
+                    halt 0
+            calc:
+                    proc
+                    push.c 3
+                    push.c 4
+                    sysreq.c float
+                    stack 8
+                    push.c 0x3F000000
+                    push.pri
+                    push.c 8
+                    sysreq.c floatmul
+                    stack 0xC
+                    push.c 0x3F800000
+                    push.pri
+                    push.c 8
+                    sysreq.c floatadd
+                    stack 0xC
+                    push.c 0
+                    push.pri
+                    push.c 8
+                    sysreq.c floatround
+                    stack 0xC
+                    retn
+        "#);
+        register(vm.natives_mut());
+
+        assert_eq!(vm.exec("calc", &[]).unwrap(), 3);
+        let sum = vm.calls_of("floatround")[0].args[0];
+        assert_eq!(vm.cell_to_float(sum), 2.5);
+
+        let minus = vm.float_to_cell(-2.5);
+        assert_eq!(vm.invoke("floatround", &[minus, 1]).unwrap(), -3);
+        assert_eq!(vm.invoke("floatround", &[minus, 3]).unwrap(), -2);
+        assert_eq!(vm.invoke("floatcmp", &[minus, sum]).unwrap(), -1);
+        assert_eq!(vm.invoke("floatround", &[minus, 7]).unwrap(), -2);
+    }
+}
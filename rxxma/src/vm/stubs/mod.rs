@@ -0,0 +1,32 @@
+//! Rust implementations of the common AMX Mod X natives.
+//!
+//! Natives behave as the game server's ones for the plugin, registration
+//! natives remember what was registered in `Registrations` instead.
+
+mod float;
+mod registration;
+mod string;
+
+pub use self::registration::{Command, Cvar, PluginInfo, Registrations};
+pub use self::string::format;
+
+use super::{Cell, Natives, VmError};
+
+/// Registers string, float and registration natives.
+pub fn register_core(natives: &mut Natives) -> Registrations {
+    string::register(natives);
+    float::register(natives);
+    registration::register(natives)
+}
+
+/// Argument of the native, the compiler always passes the default ones.
+fn arg(native: &str, args: &[Cell], index: usize) -> Result<Cell, VmError> {
+    args.get(index)
+        .cloned()
+        .ok_or_else(|| VmError::Native(native.to_owned(), format!("missing argument {}", index)))
+}
+
+/// Maximum length argument, negative lengths write nothing.
+fn length(native: &str, args: &[Cell], index: usize) -> Result<usize, VmError> {
+    Ok(arg(native, args, index)?.max(0) as usize)
+}
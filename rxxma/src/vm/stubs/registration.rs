@@ -0,0 +1,146 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::arg;
+pub use crate::analysis::metadata::PluginInfo;
+use crate::vm::{Cell, Natives, Vm, VmError};
+
+/// Arguments of `register_clcmd`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub command: String,
+    /// Public handling the command.
+    pub function: String,
+    pub flags: Cell,
+    pub info: String,
+}
+
+/// Arguments of `register_cvar`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cvar {
+    pub name: String,
+    pub value: String,
+    pub flags: Cell,
+}
+
+#[derive(Debug, Default)]
+struct Registered {
+    plugins: Vec<PluginInfo>,
+    commands: Vec<Command>,
+    cvars: Vec<Cvar>,
+}
+
+/// What the plugin registered, shared with the registered natives.
+#[derive(Debug, Clone, Default)]
+pub struct Registrations {
+    registered: Rc<RefCell<Registered>>,
+}
+
+impl Registrations {
+    pub fn plugins(&self) -> Vec<PluginInfo> {
+        self.registered.borrow().plugins.clone()
+    }
+
+    pub fn commands(&self) -> Vec<Command> {
+        self.registered.borrow().commands.clone()
+    }
+
+    pub fn cvars(&self) -> Vec<Cvar> {
+        self.registered.borrow().cvars.clone()
+    }
+}
+
+/// Registers the natives, each returns the id of what it registered.
+pub(super) fn register(natives: &mut Natives) -> Registrations {
+    let registrations = Registrations::default();
+
+    let registered = registrations.registered.clone();
+    natives.register("register_plugin", move |vm, args| {
+        let plugin = PluginInfo {
+            name: string(vm, "register_plugin", args, 0)?,
+            version: string(vm, "register_plugin", args, 1)?,
+            author: string(vm, "register_plugin", args, 2)?,
+        };
+        let plugins = &mut registered.borrow_mut().plugins;
+        plugins.push(plugin);
+        Ok(plugins.len() as Cell - 1)
+    });
+
+    let registered = registrations.registered.clone();
+    natives.register("register_clcmd", move |vm, args| {
+        let command = Command {
+            command: string(vm, "register_clcmd", args, 0)?,
+            function: string(vm, "register_clcmd", args, 1)?,
+            flags: arg("register_clcmd", args, 2)?,
+            info: string(vm, "register_clcmd", args, 3)?,
+        };
+        let commands = &mut registered.borrow_mut().commands;
+        commands.push(command);
+        Ok(commands.len() as Cell - 1)
+    });
+
+    let registered = registrations.registered.clone();
+    natives.register("register_cvar", move |vm, args| {
+        let cvar = Cvar {
+            name: string(vm, "register_cvar", args, 0)?,
+            value: string(vm, "register_cvar", args, 1)?,
+            flags: arg("register_cvar", args, 2)?,
+        };
+        let cvars = &mut registered.borrow_mut().cvars;
+        cvars.push(cvar);
+        // Cvar pointers are never null
+        Ok(cvars.len() as Cell)
+    });
+
+    registrations
+}
+
+fn string(vm: &Vm, native: &str, args: &[Cell], index: usize) -> Result<String, VmError> {
+    vm.read_string(arg(native, args, index)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{register, Command, PluginInfo};
+    use crate::util::tests::load_fixture;
+    use crate::vm::Vm;
+    use amxmodx_utils::amxx::File as AmxmodxFile;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn it_records_registrations() {
+        let bin = load_fixture("simple.amxx183");
+        let file = AmxmodxFile::try_from(&bin[..]).unwrap();
+        let section = file.sections().next().unwrap().unwrap();
+        let mut vm = Vm::new(&section.unpack().unwrap()).unwrap();
+        let registrations = register(vm.natives_mut());
+
+        vm.exec("plugin_init", &[]).unwrap();
+        assert_eq!(
+            registrations.plugins(),
+            [PluginInfo {
+                name: "simple plugin".to_owned(),
+                version: "0.1".to_owned(),
+                author: "Fedcomp".to_owned(),
+            }]
+        );
+        assert_eq!(vm.calls_of("register_plugin").len(), 1);
+
+        let strings = vm.allot(32).unwrap();
+        vm.write_string(strings, "say /hp", 16).unwrap();
+        vm.write_string(strings + 64, "cmd_hp", 16).unwrap();
+        let id = vm
+            .invoke("register_clcmd", &[strings, strings + 64, -1, strings + 28])
+            .unwrap();
+        assert_eq!(id, 0);
+        assert_eq!(
+            registrations.commands(),
+            [Command {
+                command: "say /hp".to_owned(),
+                function: "cmd_hp".to_owned(),
+                flags: -1,
+                info: "".to_owned(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use super::{arg, length};
+use crate::vm::{Cell, Natives, Vm, VmError};
+
+pub(super) fn register(natives: &mut Natives) {
+    natives
+        .register("format", |vm, args| format_native(vm, "format", args))
+        .register("formatex", |vm, args| format_native(vm, "formatex", args))
+        .register("copy", |vm, args| {
+            let source = vm.read_string(arg("copy", args, 2)?)?;
+            let written = vm.write_string(args[0], &source, length("copy", args, 1)?)?;
+            Ok(written as Cell)
+        })
+        .register("strlen", |vm, args| {
+            Ok(vm.read_string(arg("strlen", args, 0)?)?.len() as Cell)
+        })
+        .register("equal", |vm, args| equal(vm, "equal", args, false))
+        .register("equali", |vm, args| equal(vm, "equali", args, true))
+        .register("contain", |vm, args| contain(vm, "contain", args, false))
+        .register("containi", |vm, args| contain(vm, "containi", args, true))
+        .register("str_to_num", |vm, args| {
+            Ok(str_to_num(&vm.read_string(arg("str_to_num", args, 0)?)?))
+        })
+        .register("num_to_str", |vm, args| {
+            let number = arg("num_to_str", args, 0)?.to_string();
+            let written = vm.write_string(
+                arg("num_to_str", args, 1)?,
+                &number,
+                length("num_to_str", args, 2)?,
+            )?;
+            Ok(written as Cell)
+        });
+}
+
+/// `format(output[], len, const format[], any:...)`, returns the number of characters written.
+fn format_native(vm: &mut Vm, native: &str, args: &[Cell]) -> Result<Cell, VmError> {
+    let pattern = vm.read_string(arg(native, args, 2)?)?;
+    let text = format(vm, &pattern, &args[3..]).map_err(|e| match e {
+        VmError::Native(_, message) => VmError::Native(native.to_owned(), message),
+        e => e,
+    })?;
+    let written = vm.write_string(args[0], &text, length(native, args, 1)?)?;
+    Ok(written as Cell)
+}
+
+/// `equal(const a[], const b[], c = 0)`, compares at most `c` characters when it is positive.
+fn equal(vm: &mut Vm, native: &str, args: &[Cell], ignore_case: bool) -> Result<Cell, VmError> {
+    let mut a = vm.read_string(arg(native, args, 0)?)?;
+    let mut b = vm.read_string(arg(native, args, 1)?)?;
+    if ignore_case {
+        a = a.to_lowercase();
+        b = b.to_lowercase();
+    }
+    let equal = match arg(native, args, 2)? {
+        count if count > 0 => a
+            .chars()
+            .take(count as usize)
+            .eq(b.chars().take(count as usize)),
+        _ => a == b,
+    };
+    Ok(Cell::from(equal))
+}
+
+/// `contain(const source[], const string[])`, returns the position or -1.
+fn contain(vm: &mut Vm, native: &str, args: &[Cell], ignore_case: bool) -> Result<Cell, VmError> {
+    let mut source = vm.read_string(arg(native, args, 0)?)?;
+    let mut string = vm.read_string(arg(native, args, 1)?)?;
+    if ignore_case {
+        source = source.to_lowercase();
+        string = string.to_lowercase();
+    }
+    Ok(source.find(&string).map_or(-1, |i| i as Cell))
+}
+
+/// Number at the start of the string, as C `atoi` does.
+fn str_to_num(string: &str) -> Cell {
+    let string = string.trim_start();
+    let (negative, digits) = match string.as_bytes().first() {
+        Some(b'-') => (true, &string[1..]),
+        Some(b'+') => (false, &string[1..]),
+        _ => (false, string),
+    };
+    let number = digits
+        .bytes()
+        .take_while(u8::is_ascii_digit)
+        .fold(0 as Cell, |n, d| {
+            n.wrapping_mul(10).wrapping_add(Cell::from(d - b'0'))
+        });
+    if negative {
+        number.wrapping_neg()
+    } else {
+        number
+    }
+}
+
+/// Formats the arguments, which are passed by reference as for any variadic native.
+///
+/// Supports `%d %i %u %x %X %b %c %s %f %%` with `-` and `0` flags, width and precision.
+pub fn format(vm: &Vm, pattern: &str, args: &[Cell]) -> Result<String, VmError> {
+    let mut output = String::new();
+    let mut args = args.iter();
+    let mut next = || {
+        args.next()
+            .cloned()
+            .ok_or_else(|| VmError::Native("format".to_owned(), "not enough arguments".to_owned()))
+    };
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+
+        let (mut left, mut zero) = (false, false);
+        loop {
+            match chars.peek() {
+                Some('-') => left = true,
+                Some('0') => zero = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        let width = number(&mut chars).unwrap_or(0);
+        let precision = match chars.peek() {
+            Some('.') => {
+                chars.next();
+                Some(number(&mut chars).unwrap_or(0))
+            }
+            _ => None,
+        };
+
+        let conversion = match chars.next() {
+            Some(conversion) => conversion,
+            None => {
+                output.push('%');
+                break;
+            }
+        };
+        let text = match conversion {
+            '%' => "%".to_owned(),
+            'd' | 'i' => vm.read_cell(next()?)?.to_string(),
+            'u' => vm.unsigned(vm.read_cell(next()?)?).to_string(),
+            'x' => format!("{:x}", vm.unsigned(vm.read_cell(next()?)?)),
+            'X' => format!("{:X}", vm.unsigned(vm.read_cell(next()?)?)),
+            'b' => format!("{:b}", vm.unsigned(vm.read_cell(next()?)?)),
+            'c' => char::from(vm.read_cell(next()?)? as u8).to_string(),
+            's' => {
+                let string = vm.read_string(next()?)?;
+                match precision {
+                    Some(precision) => string.chars().take(precision).collect(),
+                    None => string,
+                }
+            }
+            'f' => {
+                let value = vm.cell_to_float(vm.read_cell(next()?)?);
+                format!("{:.*}", precision.unwrap_or(6), value)
+            }
+            _ => {
+                output.push('%');
+                output.push(conversion);
+                continue;
+            }
+        };
+
+        let padding = width.saturating_sub(text.chars().count());
+        if left {
+            output.push_str(&text);
+            output.extend(std::iter::repeat_n(' ', padding));
+        } else if zero && "diuxXbf".contains(conversion) {
+            // Zeros go after the sign
+            let digits = match text.strip_prefix('-') {
+                Some(digits) => {
+                    output.push('-');
+                    digits
+                }
+                None => &text[..],
+            };
+            output.extend(std::iter::repeat_n('0', padding));
+            output.push_str(digits);
+        } else {
+            output.extend(std::iter::repeat_n(' ', padding));
+            output.push_str(&text);
+        }
+    }
+
+    Ok(output)
+}
+
+fn number(chars: &mut Peekable<Chars>) -> Option<usize> {
+    let mut number = None;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        number = Some(number.unwrap_or(0) * 10 + digit as usize);
+        chars.next();
+    }
+    number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format, register};
+    use crate::util::tests::vm;
+    use crate::vm::Cell;
+
+    #[test]
+    fn it_runs_string_natives() {
+        let mut vm = vm(r#"
+            .data
+            hello: .string "Hello"
+            pattern: .string "%s #%03d: %-4s|%.2f %x%%"
+            number: .cell -7
+            short: .string "ab"
+            pi: .cell 0x40490FDB
+            digits: .string "  -42abc"
+            .code
+                    halt 0
+        "#);
+        register(vm.natives_mut());
+        let (hello, pattern, number, short, pi, digits) = (0, 24, 124, 128, 140, 144);
+        let buffer = vm.allot(64).unwrap();
+
+        assert_eq!(
+            format(
+                &vm,
+                &vm.read_string(pattern).unwrap(),
+                &[hello, number, short, pi, number]
+            )
+            .unwrap(),
+            "Hello #-07: ab  |3.14 fffffff9%"
+        );
+        assert!(format(&vm, "%d", &[]).is_err());
+
+        let args = [buffer, 4, pattern, hello, number, short, pi, number];
+        assert_eq!(vm.invoke("format", &args).unwrap(), 4);
+        assert_eq!(vm.read_string(buffer).unwrap(), "Hell");
+
+        assert_eq!(vm.invoke("copy", &[buffer, 63, short]).unwrap(), 2);
+        assert_eq!(vm.read_string(buffer).unwrap(), "ab");
+        assert_eq!(vm.invoke("strlen", &[hello]).unwrap(), 5);
+        assert_eq!(vm.invoke("equal", &[hello, hello, 0]).unwrap(), 1);
+        assert_eq!(vm.invoke("equal", &[hello, short, 0]).unwrap(), 0);
+        assert_eq!(vm.invoke("contain", &[digits, short]).unwrap(), 5);
+        assert_eq!(vm.invoke("contain", &[hello, short]).unwrap(), -1);
+        assert_eq!(vm.invoke("str_to_num", &[digits]).unwrap(), -42);
+        assert_eq!(vm.invoke("num_to_str", &[-15, buffer, 63]).unwrap(), 3);
+        assert_eq!(vm.read_string(buffer).unwrap(), "-15");
+
+        let calls: Vec<Cell> = vm.calls_of("strlen").iter().map(|c| c.args[0]).collect();
+        assert_eq!(calls, [hello]);
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use amxmodx_utils::amx::opcode::Opcode;
+use amxmodx_utils::amx::UCell;
+
+use super::{Argument, Cell, Registers, Step, Vm, VmError};
+
+/// Machine state right before the instruction was executed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceEntry {
+    pub opcode: Opcode,
+    pub registers: Registers,
+    /// Cell at STK, none when the stack is empty.
+    pub stack_top: Option<Cell>,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let r = &self.registers;
+        write!(
+            f,
+            "0x{:08X}  {:<32} pri={:X} alt={:X} frm={:X} stk={:X} hea={:X}",
+            r.cip,
+            self.opcode.to_string(),
+            r.pri,
+            r.alt,
+            r.frm,
+            r.stk,
+            r.hea
+        )?;
+        if let Some(top) = self.stack_top {
+            write!(f, " [stk]={:X}", top)?;
+        }
+        Ok(())
+    }
+}
+
+/// Steps the machine, counting executions of every instruction.
+#[derive(Debug, Clone, Default)]
+pub struct Tracer {
+    hits: BTreeMap<UCell, u64>,
+}
+
+impl Tracer {
+    pub fn new() -> Self {
+        Tracer::default()
+    }
+
+    /// Executes the instruction at CIP, returns the state before it.
+    pub fn step(&mut self, vm: &mut Vm) -> Result<(TraceEntry, Step), VmError> {
+        let registers = vm.registers;
+        let opcode = vm
+            .opcode(registers.cip)
+            .cloned()
+            .ok_or(VmError::InvalidInstruction(registers.cip))?;
+        let stack_top = if registers.stk < vm.stack_top {
+            vm.read_cell(registers.stk).ok()
+        } else {
+            None
+        };
+        let entry = TraceEntry {
+            opcode,
+            registers,
+            stack_top,
+        };
+
+        *self.hits.entry(registers.cip as UCell).or_default() += 1;
+        let step = vm.step()?;
+        Ok((entry, step))
+    }
+
+    /// Runs the public as `Vm::exec` does, passing each executed instruction to `log`.
+    pub fn exec<F>(
+        &mut self,
+        vm: &mut Vm,
+        public: &str,
+        args: &[Argument],
+        mut log: F,
+    ) -> Result<Cell, VmError>
+    where
+        F: FnMut(&TraceEntry),
+    {
+        let index = vm
+            .public_index(public)
+            .ok_or_else(|| VmError::UnknownPublic(public.to_owned()))?;
+        vm.call(index, args)?;
+        loop {
+            let (entry, step) = self.step(vm)?;
+            log(&entry);
+            if let Step::Returned(value) = step {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Number of executions by instruction address, only executed ones are present.
+    pub fn hits(&self) -> &BTreeMap<UCell, u64> {
+        &self.hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tracer;
+    use crate::util::tests::vm;
+
+    #[test]
+    fn it_traces_instructions() {
+        let mut vm = vm(r#"
+            .public twice
+            .code
+                    halt 0
+            twice:
+                    proc
+                    load.s.pri 0xC
+                    push.pri
+                    pop.alt
+                    add
+                    retn
+        "#);
+        let mut tracer = Tracer::new();
+        let mut trace = vec![];
+
+        let result = tracer.exec(&mut vm, "twice", &[21.into()], |e| trace.push(e.clone()));
+        assert_eq!(result.unwrap(), 42);
+
+        let cips: Vec<i64> = trace.iter().map(|e| e.registers.cip).collect();
+        // Public returns to `halt` at the start of the code
+        assert_eq!(cips, [8, 12, 20, 24, 28, 32, 0]);
+        assert_eq!(trace[3].stack_top, Some(21));
+        assert_eq!(trace[5].registers.pri, 42);
+        assert!(trace[3].to_string().starts_with("0x00000018  pop.alt"));
+
+        tracer.exec(&mut vm, "twice", &[1.into()], |_| ()).unwrap();
+        assert_eq!(tracer.hits().get(&8), Some(&2));
+        assert_eq!(tracer.hits().get(&16), None);
+    }
+}